@@ -0,0 +1,171 @@
+//! Central model of what the app is doing
+//!
+//! Commands, page loads and watcher events report changes through `update`;
+//! the tray icon and tooltip are derived from the resulting state instead of
+//! being computed from window checks at each call site.
+
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::tray;
+
+/// What the tray icon shows, in order of precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Activity {
+    Offline,
+    Generating,
+    Unread,
+    Idle,
+}
+
+/// The conversation shown in the main window
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Conversation {
+    pub title: Option<String>,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct AppState {
+    pub online: bool,
+    /// A message was submitted and its response has not completed yet
+    pub generating: bool,
+    /// A response completed while the main window was not focused
+    pub unread: bool,
+    pub conversation: Option<Conversation>,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self {
+            online: true,
+            generating: false,
+            unread: false,
+            conversation: None,
+        }
+    }
+}
+
+impl AppState {
+    pub fn activity(&self) -> Activity {
+        if !self.online {
+            Activity::Offline
+        } else if self.generating {
+            Activity::Generating
+        } else if self.unread {
+            Activity::Unread
+        } else {
+            Activity::Idle
+        }
+    }
+
+    /// Tray tooltip describing the activity and active conversation
+    pub fn tooltip(&self) -> String {
+        let title = self.conversation.as_ref().and_then(|c| c.title.as_deref());
+        match (self.activity(), title) {
+            (Activity::Offline, _) => "Kimi - Offline".to_string(),
+            (Activity::Generating, Some(title)) => {
+                format!("Kimi - Generating a response in \"{}\"", title)
+            }
+            (Activity::Generating, None) => "Kimi - Generating a response".to_string(),
+            (Activity::Unread, Some(title)) => format!("Kimi - Response ready in \"{}\"", title),
+            (Activity::Unread, None) => "Kimi - Response ready".to_string(),
+            (Activity::Idle, Some(title)) => format!("Kimi - {}", title),
+            (Activity::Idle, None) => "Kimi".to_string(),
+        }
+    }
+
+    /// Record the page shown in the main window, keeping the known title if
+    /// the URL did not change
+    pub fn set_page(&mut self, url: &str, title: Option<String>) {
+        let title = match &self.conversation {
+            Some(current) if current.url == url && title.is_none() => current.title.clone(),
+            _ => title,
+        };
+        self.conversation = Some(Conversation {
+            title,
+            url: url.to_string(),
+        });
+    }
+}
+
+/// Apply a change and, if anything changed, update the tray and emit
+/// `app-state-changed`
+pub fn update<F>(app: &AppHandle, f: F)
+where
+    F: FnOnce(&mut AppState),
+{
+    let Some(state) = app.try_state::<Mutex<AppState>>() else {
+        return;
+    };
+    let changed = {
+        let mut state = state.lock().unwrap();
+        let before = state.clone();
+        f(&mut state);
+        (*state != before).then(|| state.clone())
+    };
+    if let Some(state) = changed {
+        if let Err(e) = tray::show_state(app, &state) {
+            eprintln!("Failed to update tray icon: {}", e);
+        }
+        let _ = app.emit("app-state-changed", &state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation(title: Option<&str>) -> Option<Conversation> {
+        Some(Conversation {
+            title: title.map(str::to_string),
+            url: "https://www.kimi.com/chat/1".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_activity_precedence() {
+        let mut state = AppState {
+            unread: true,
+            ..Default::default()
+        };
+        assert_eq!(state.activity(), Activity::Unread);
+        state.generating = true;
+        assert_eq!(state.activity(), Activity::Generating);
+        state.online = false;
+        assert_eq!(state.activity(), Activity::Offline);
+        assert_eq!(AppState::default().activity(), Activity::Idle);
+    }
+
+    #[test]
+    fn test_tooltip_names_conversation() {
+        let mut state = AppState {
+            conversation: conversation(Some("Trip plan")),
+            ..Default::default()
+        };
+        assert_eq!(state.tooltip(), "Kimi - Trip plan");
+        state.generating = true;
+        assert_eq!(
+            state.tooltip(),
+            "Kimi - Generating a response in \"Trip plan\""
+        );
+        state.generating = false;
+        state.unread = true;
+        state.conversation = conversation(None);
+        assert_eq!(state.tooltip(), "Kimi - Response ready");
+        state.online = false;
+        assert_eq!(state.tooltip(), "Kimi - Offline");
+    }
+
+    #[test]
+    fn test_set_page_keeps_title_for_same_url() {
+        let mut state = AppState::default();
+        state.set_page("https://www.kimi.com/chat/1", Some("Trip plan".to_string()));
+        state.set_page("https://www.kimi.com/chat/1", None);
+        assert_eq!(state.conversation, conversation(Some("Trip plan")));
+        state.set_page("https://www.kimi.com/chat/2", None);
+        assert_eq!(state.conversation.unwrap().title, None);
+    }
+}
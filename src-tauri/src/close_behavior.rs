@@ -0,0 +1,85 @@
+//! What the main window's close button does
+//!
+//! Hiding to the tray keeps Kimi running for the launcher shortcut and
+//! response notifications, which is easy to miss, so the first time it
+//! happens a notification explains it.
+
+use tauri::{AppHandle, CloseRequestApi, Manager};
+
+use crate::notifications;
+use crate::settings;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CloseBehavior {
+    /// Hide the window and keep running in the tray
+    #[default]
+    HideToTray,
+    /// Minimize the window
+    Minimize,
+    /// Exit Kimi
+    Quit,
+}
+
+/// Handle a close request on the main window
+pub fn close_requested(app: &AppHandle, api: &CloseRequestApi) {
+    let settings = settings::load(app).unwrap_or_default();
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    // The window is never destroyed; quitting goes through `exit` so the
+    // tray and hidden windows go away with it
+    api.prevent_close();
+    match settings.close_behavior {
+        CloseBehavior::HideToTray => {
+            let _ = window.hide();
+            if !settings.close_hint_shown {
+                show_hint(app);
+            }
+        }
+        CloseBehavior::Minimize => {
+            let _ = window.minimize();
+        }
+        CloseBehavior::Quit => app.exit(0),
+    }
+}
+
+fn show_hint(app: &AppHandle) {
+    if let Err(e) = settings::update(app, |s| s.close_hint_shown = true) {
+        eprintln!("Failed to save close hint state: {}", e);
+    }
+    let quit_keys = if cfg!(target_os = "macos") {
+        "Cmd+Q"
+    } else {
+        "Ctrl+Q"
+    };
+    notifications::show(
+        app,
+        "Kimi is still running",
+        &format!(
+            "Closing the window keeps Kimi in the tray. Use Quit from the tray menu or {} to exit, or change this in Settings.",
+            quit_keys
+        ),
+        Some(Box::new(|app: &AppHandle| {
+            if let Some(settings) = app.get_webview_window("settings") {
+                let _ = settings.show();
+                let _ = settings.set_focus();
+            }
+        })),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_close_behavior_serde() {
+        assert_eq!(
+            serde_json::to_string(&CloseBehavior::HideToTray).unwrap(),
+            "\"hide_to_tray\""
+        );
+        let behavior: CloseBehavior = serde_json::from_str("\"quit\"").unwrap();
+        assert_eq!(behavior, CloseBehavior::Quit);
+    }
+}
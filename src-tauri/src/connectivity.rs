@@ -0,0 +1,478 @@
+//! Connectivity to the configured Kimi host
+//!
+//! A background task probes the host through the configured proxy, keeps an
+//! online/degraded/offline state and moves the main window between the chat
+//! and the offline page when that state changes. Probes back off while the
+//! host stays unreachable.
+//!
+//! A probe opens a connection to the host; for `http://` origins, such as a
+//! local stand-in, it also sends a `HEAD` request and checks the status. For
+//! `https://` origins, Kimi's own, it stops at the TCP connect, so a server
+//! error never shows up as [`Probe::ServerError`] there, only a slow connect
+//! as degraded.
+//!
+//! In [`ProxyMode::System`] the probe only sees the `*_proxy` environment
+//! variables, while the webview also follows PAC scripts and desktop proxy
+//! settings. There a Kimi page that loaded wins over a failed probe.
+
+use std::io::{Read, Write};
+use std::net::Shutdown;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Emitter, Manager, Url};
+use tokio::sync::Notify;
+
+use crate::app_state;
+use crate::proxy::{self, ProxyMode, ProxySettings};
+use crate::settings;
+use crate::wrappers::{
+    config::{Timeouts, Urls},
+    set_offline_state, WrapperError, WrapperResult,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Connectivity {
+    Online,
+    /// Reachable but slow or returning server errors, or a probe just failed
+    Degraded,
+    Offline,
+}
+
+/// Result of one probe
+///
+/// `ServerError` only comes from `http://` origins; see the module docs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Probe {
+    Reachable { elapsed_ms: u64 },
+    ServerError { status: u16 },
+    Unreachable { message: String },
+}
+
+/// Probe `target` once
+pub fn probe(proxy_settings: &ProxySettings, target: &Url, timeout: Duration) -> Probe {
+    let started = Instant::now();
+    let (mut stream, _) = match proxy::open_stream(proxy_settings, target, timeout) {
+        Ok(opened) => opened,
+        Err(e) => {
+            return Probe::Unreachable {
+                message: e.to_string(),
+            }
+        }
+    };
+    if target.scheme() == "http" {
+        match head_status(&mut stream, target) {
+            Ok(status) if status >= 500 => return Probe::ServerError { status },
+            Ok(_) => {}
+            Err(message) => return Probe::Unreachable { message },
+        }
+    }
+    let _ = stream.shutdown(Shutdown::Both);
+    Probe::Reachable {
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+fn head_status(stream: &mut std::net::TcpStream, target: &Url) -> Result<u16, String> {
+    let host = match target.port() {
+        Some(port) => format!("{}:{}", target.host_str().unwrap_or_default(), port),
+        None => target.host_str().unwrap_or_default().to_string(),
+    };
+    write!(
+        stream,
+        "HEAD {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        target.path(),
+        host
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut response = Vec::new();
+    let mut buf = [0u8; 512];
+    while !response.windows(2).any(|w| w == b"\r\n") && response.len() < 4096 {
+        let n = stream.read(&mut buf).map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        response.extend_from_slice(&buf[..n]);
+    }
+    String::from_utf8_lossy(&response)
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| "no HTTP response".to_string())
+}
+
+/// Connectivity state machine fed with probe results
+#[derive(Debug, Default)]
+pub struct Monitor {
+    /// `None` until the first probe
+    state: Option<Connectivity>,
+    failures: u32,
+}
+
+impl Monitor {
+    pub fn state(&self) -> Option<Connectivity> {
+        self.state
+    }
+
+    /// Record a probe and return the new state
+    pub fn record(&mut self, probe: &Probe) -> Connectivity {
+        let state = match probe {
+            Probe::Reachable { elapsed_ms } => {
+                self.failures = 0;
+                if *elapsed_ms > Timeouts::CONNECTIVITY_SLOW_PROBE {
+                    Connectivity::Degraded
+                } else {
+                    Connectivity::Online
+                }
+            }
+            Probe::ServerError { .. } => {
+                self.failures = 0;
+                Connectivity::Degraded
+            }
+            Probe::Unreachable { .. } => {
+                self.failures += 1;
+                if self.failures < Timeouts::CONNECTIVITY_FAILURES_BEFORE_OFFLINE {
+                    Connectivity::Degraded
+                } else {
+                    Connectivity::Offline
+                }
+            }
+        };
+        self.state = Some(state);
+        state
+    }
+
+    /// Delay before the next probe
+    pub fn next_delay(&self) -> Duration {
+        let ms = match self.state {
+            None | Some(Connectivity::Online) => Timeouts::CONNECTIVITY_PROBE_INTERVAL,
+            Some(Connectivity::Degraded) => Timeouts::CONNECTIVITY_RETRY_DELAY,
+            Some(Connectivity::Offline) => {
+                let doublings = self
+                    .failures
+                    .saturating_sub(Timeouts::CONNECTIVITY_FAILURES_BEFORE_OFFLINE)
+                    .min(16);
+                (Timeouts::CONNECTIVITY_RETRY_DELAY << doublings)
+                    .min(Timeouts::CONNECTIVITY_MAX_BACKOFF)
+            }
+        };
+        Duration::from_millis(ms)
+    }
+}
+
+/// Page the main window should switch to after a state change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Navigation {
+    OfflinePage,
+    Chat,
+}
+
+/// `page_loaded` is set when a failed probe must not override a Kimi page
+/// the main window loaded; see the module docs
+pub fn navigation(
+    previous: Option<Connectivity>,
+    next: Connectivity,
+    page_loaded: bool,
+) -> Option<Navigation> {
+    if page_loaded {
+        return None;
+    }
+    match (previous, next) {
+        (Some(Connectivity::Offline), Connectivity::Offline) => None,
+        (_, Connectivity::Offline) => Some(Navigation::OfflinePage),
+        (Some(Connectivity::Offline), _) => Some(Navigation::Chat),
+        _ => None,
+    }
+}
+
+/// Payload of the `connectivity-changed` event
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ConnectivityChanged {
+    pub state: Connectivity,
+    pub previous: Option<Connectivity>,
+    /// Why the last probe failed or was degraded
+    pub detail: Option<String>,
+}
+
+/// Managed handle of the background monitor
+#[derive(Default)]
+pub struct ConnectivityMonitor {
+    monitor: Mutex<Monitor>,
+    wake: Notify,
+}
+
+/// Start probing the Kimi host in the background
+pub fn spawn(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let settings = settings::load(&app).unwrap_or_default();
+            let target = settings.endpoints().chat();
+            let proxy_settings = settings.proxy.clone();
+            let timeout = Duration::from_millis(Timeouts::CONNECTIVITY_PROBE_TIMEOUT);
+            let result = tauri::async_runtime::spawn_blocking(move || {
+                probe(&proxy_settings, &target, timeout)
+            })
+            .await;
+            let handle = app.state::<ConnectivityMonitor>();
+            let result = match result {
+                Ok(result) => result,
+                Err(e) => {
+                    eprintln!("Connectivity probe failed to run: {}", e);
+                    let delay = handle.monitor.lock().unwrap().next_delay();
+                    let _ = tokio::time::timeout(delay, handle.wake.notified()).await;
+                    continue;
+                }
+            };
+            let (previous, state, delay) = {
+                let mut monitor = handle.monitor.lock().unwrap();
+                let previous = monitor.state();
+                let state = monitor.record(&result);
+                (previous, state, monitor.next_delay())
+            };
+            if previous != Some(state) {
+                state_changed(&app, previous, state, &result).await;
+            }
+
+            let _ = tokio::time::timeout(delay, handle.wake.notified()).await;
+        }
+    });
+}
+
+async fn state_changed(
+    app: &AppHandle,
+    previous: Option<Connectivity>,
+    state: Connectivity,
+    probe: &Probe,
+) {
+    let detail = match probe {
+        Probe::Reachable { elapsed_ms } if state == Connectivity::Degraded => {
+            Some(format!("slow response ({} ms)", elapsed_ms))
+        }
+        Probe::Reachable { .. } => None,
+        Probe::ServerError { status } => Some(format!("server error ({})", status)),
+        Probe::Unreachable { message } => Some(message.clone()),
+    };
+    let _ = app.emit(
+        "connectivity-changed",
+        ConnectivityChanged {
+            state,
+            previous,
+            detail,
+        },
+    );
+    let page_loaded = settings::load(app).unwrap_or_default().proxy.mode == ProxyMode::System
+        && shows_kimi_page(app);
+    let result = match navigation(previous, state, page_loaded) {
+        Some(Navigation::OfflinePage) => show_offline_page(app).await,
+        Some(Navigation::Chat) => show_chat(app),
+        None => Ok(()),
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to follow connectivity change: {}", e);
+    }
+}
+
+/// Whether the main window finished loading a Kimi page
+fn shows_kimi_page(app: &AppHandle) -> bool {
+    app.try_state::<Mutex<app_state::AppState>>()
+        .is_some_and(|state| state.lock().unwrap().online)
+}
+
+/// Probe now instead of waiting for the next scheduled probe
+pub fn check_now(app: &AppHandle) {
+    if let Some(handle) = app.try_state::<ConnectivityMonitor>() {
+        handle.wake.notify_one();
+    }
+}
+
+/// Whether the last probe found the host unreachable
+pub fn is_offline(app: &AppHandle) -> bool {
+    app.try_state::<ConnectivityMonitor>()
+        .is_some_and(|handle| handle.monitor.lock().unwrap().state() == Some(Connectivity::Offline))
+}
+
+/// Show the offline page in the main window
+pub async fn show_offline_page(app: &AppHandle) -> WrapperResult<()> {
+    if let Some(main_window) = app.get_webview_window("main") {
+        let url = Urls::OFFLINE_PAGE
+            .parse::<Url>()
+            .map_err(|e| WrapperError::InvalidUrl {
+                url: Urls::OFFLINE_PAGE.to_string(),
+                reason: e.to_string(),
+            })?;
+        app_state::update(app, |state| state.online = false);
+        main_window
+            .navigate(url)
+            .map_err(WrapperError::window(&main_window, "navigate"))?;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let _ = set_offline_state(&main_window, &settings::endpoints(app));
+    }
+    Ok(())
+}
+
+/// Load the chat page in the main window
+pub fn show_chat(app: &AppHandle) -> WrapperResult<()> {
+    if let Some(main_window) = app.get_webview_window("main") {
+        let url = settings::endpoints(app).chat();
+        main_window
+            .navigate(url)
+            .map_err(WrapperError::window(&main_window, "navigate"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    fn direct() -> ProxySettings {
+        ProxySettings {
+            mode: proxy::ProxyMode::None,
+            ..Default::default()
+        }
+    }
+
+    /// Local stand-in for the Kimi host answering one request with `status`
+    fn spawn_host(status: &'static str) -> (Url, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let n = stream.read(&mut buf).unwrap();
+            write!(stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status).unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_string()
+        });
+        (url, handle)
+    }
+
+    fn unreachable() -> Probe {
+        Probe::Unreachable {
+            message: "refused".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_probe_against_local_host() {
+        let (url, host) = spawn_host("200 OK");
+        assert!(matches!(
+            probe(&direct(), &url, TIMEOUT),
+            Probe::Reachable { .. }
+        ));
+        let request = host.join().unwrap();
+        assert!(request.starts_with("HEAD / HTTP/1.1\r\n"));
+        assert!(request.contains(&format!("Host: 127.0.0.1:{}", url.port().unwrap())));
+
+        let (url, host) = spawn_host("503 Service Unavailable");
+        assert_eq!(
+            probe(&direct(), &url, TIMEOUT),
+            Probe::ServerError { status: 503 }
+        );
+        host.join().unwrap();
+    }
+
+    #[test]
+    fn test_probe_unreachable_host() {
+        // Bind and drop a listener to get a port nothing listens on
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let url = Url::parse(&format!("http://127.0.0.1:{}/", port)).unwrap();
+        assert!(matches!(
+            probe(&direct(), &url, TIMEOUT),
+            Probe::Unreachable { .. }
+        ));
+    }
+
+    #[test]
+    fn test_failures_degrade_before_going_offline() {
+        let mut monitor = Monitor::default();
+        assert_eq!(
+            monitor.record(&Probe::Reachable { elapsed_ms: 40 }),
+            Connectivity::Online
+        );
+        for _ in 1..Timeouts::CONNECTIVITY_FAILURES_BEFORE_OFFLINE {
+            assert_eq!(monitor.record(&unreachable()), Connectivity::Degraded);
+        }
+        assert_eq!(monitor.record(&unreachable()), Connectivity::Offline);
+        assert_eq!(
+            monitor.record(&Probe::Reachable { elapsed_ms: 40 }),
+            Connectivity::Online
+        );
+
+        let slow = Probe::Reachable {
+            elapsed_ms: Timeouts::CONNECTIVITY_SLOW_PROBE + 1,
+        };
+        assert_eq!(monitor.record(&slow), Connectivity::Degraded);
+        assert_eq!(
+            monitor.record(&Probe::ServerError { status: 502 }),
+            Connectivity::Degraded
+        );
+    }
+
+    #[test]
+    fn test_first_failures_degrade_before_going_offline() {
+        let mut monitor = Monitor::default();
+        for _ in 1..Timeouts::CONNECTIVITY_FAILURES_BEFORE_OFFLINE {
+            assert_eq!(monitor.record(&unreachable()), Connectivity::Degraded);
+        }
+        assert_eq!(monitor.record(&unreachable()), Connectivity::Offline);
+    }
+
+    #[test]
+    fn test_offline_probes_back_off() {
+        let mut monitor = Monitor::default();
+        assert_eq!(
+            monitor.next_delay().as_millis() as u64,
+            Timeouts::CONNECTIVITY_PROBE_INTERVAL
+        );
+        monitor.record(&unreachable());
+        let mut delays = Vec::new();
+        for _ in 0..10 {
+            monitor.record(&unreachable());
+            delays.push(monitor.next_delay().as_millis() as u64);
+        }
+        assert!(delays.windows(2).all(|w| w[0] <= w[1]), "{:?}", delays);
+        assert_eq!(*delays.last().unwrap(), Timeouts::CONNECTIVITY_MAX_BACKOFF);
+
+        monitor.record(&Probe::Reachable { elapsed_ms: 10 });
+        assert_eq!(
+            monitor.next_delay().as_millis() as u64,
+            Timeouts::CONNECTIVITY_PROBE_INTERVAL
+        );
+    }
+
+    #[test]
+    fn test_navigation_on_state_changes() {
+        use Connectivity::*;
+        let cases = [
+            (None, Online, None),
+            (None, Offline, Some(Navigation::OfflinePage)),
+            (Some(Online), Degraded, None),
+            (Some(Degraded), Offline, Some(Navigation::OfflinePage)),
+            (Some(Offline), Offline, None),
+            (Some(Offline), Degraded, Some(Navigation::Chat)),
+            (Some(Offline), Online, Some(Navigation::Chat)),
+        ];
+        for (previous, next, expected) in cases {
+            assert_eq!(
+                navigation(previous, next, false),
+                expected,
+                "{:?} -> {:?}",
+                previous,
+                next
+            );
+            assert_eq!(navigation(previous, next, true), None);
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! `kimi://` links
+//!
+//! Supported forms:
+//!
+//! - `kimi://ask?q=<text>[&new=1]` opens the launcher with the text filled in
+//! - `kimi://open?path=/chat/<id>` opens a page of the configured Kimi site
+//! - `kimi://launcher` opens the launcher
+//!
+//! Links can come from any web page or document, so `ask` never sends the
+//! text by itself; the user confirms it in the launcher. On Linux and Windows
+//! the link is a command line argument, passed on by the single-instance
+//! plugin when Kimi is already running; on macOS it arrives as an open-URL
+//! event.
+
+use tauri::{AppHandle, Manager, Url};
+
+use crate::notifications;
+use crate::settings;
+use crate::wrappers::{
+    config::DeepLinks, emit_launcher_prefilled, emit_launcher_shown, Endpoints, WrapperError,
+    WrapperResult,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLink {
+    /// Fill in the launcher; `new_chat` falls back to the settings default
+    Ask {
+        message: String,
+        new_chat: Option<bool>,
+    },
+    /// Open a path on the Kimi site in the main window
+    Open {
+        path: String,
+    },
+    Launcher,
+}
+
+impl DeepLink {
+    pub fn parse(raw: &str) -> WrapperResult<Self> {
+        let invalid = |reason: &str| WrapperError::InvalidUrl {
+            url: raw.to_string(),
+            reason: reason.to_string(),
+        };
+        let url = Url::parse(raw.trim()).map_err(|e| invalid(&e.to_string()))?;
+        if url.scheme() != DeepLinks::SCHEME {
+            return Err(invalid("not a kimi:// link"));
+        }
+        if !matches!(url.path(), "" | "/") {
+            return Err(invalid("unexpected path"));
+        }
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+
+        match url.host_str().unwrap_or_default() {
+            "ask" => {
+                let message = param("q").unwrap_or_default().trim().to_string();
+                if message.is_empty() {
+                    return Err(invalid("missing q parameter"));
+                }
+                if message.chars().count() > DeepLinks::MAX_MESSAGE_CHARS {
+                    return Err(invalid("question is too long"));
+                }
+                let new_chat = match param("new").as_deref() {
+                    None => None,
+                    Some("1" | "true") => Some(true),
+                    Some("0" | "false") => Some(false),
+                    Some(_) => return Err(invalid("new must be 0 or 1")),
+                };
+                Ok(DeepLink::Ask { message, new_chat })
+            }
+            "open" => {
+                let path = param("path").unwrap_or_else(|| "/".to_string());
+                // "//host", "/\host" and "/<tab>/host" would resolve to
+                // another site; `handle` checks again against the configured origin
+                if !path.starts_with('/') || Endpoints::default().join(&path).is_none() {
+                    return Err(invalid("path must be a path on the Kimi site"));
+                }
+                Ok(DeepLink::Open { path })
+            }
+            "launcher" => Ok(DeepLink::Launcher),
+            "" => Err(invalid("missing action")),
+            action => Err(invalid(&format!("unknown action '{}'", action))),
+        }
+    }
+}
+
+/// The first `kimi://` link among command line arguments
+pub fn from_args<S: AsRef<str>>(args: &[S]) -> Option<&str> {
+    let prefix = format!("{}:", DeepLinks::SCHEME);
+    args.iter().map(AsRef::as_ref).find(|arg| {
+        arg.len() > prefix.len()
+            && arg
+                .get(..prefix.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(&prefix))
+    })
+}
+
+/// Parse a link and carry it out, logging links that are not valid
+pub fn handle(app: &AppHandle, raw: &str) {
+    let link = match DeepLink::parse(raw) {
+        Ok(link) => link,
+        Err(e) => {
+            eprintln!("Ignoring link: {}", e);
+            return;
+        }
+    };
+    match link {
+        DeepLink::Ask { message, new_chat } => {
+            show_launcher(app);
+            emit_launcher_prefilled(app, &message, new_chat);
+        }
+        DeepLink::Open { path } => match settings::endpoints(app).join(&path) {
+            Some(url) => notifications::open_conversation(app, Some(url)),
+            None => eprintln!("Ignoring link: {} leaves the Kimi site", path),
+        },
+        DeepLink::Launcher => {
+            show_launcher(app);
+            emit_launcher_shown(app);
+        }
+    }
+}
+
+fn show_launcher(app: &AppHandle) {
+    if let Some(launcher) = app.get_webview_window("launcher") {
+        let _ = launcher.center();
+        let _ = launcher.show();
+        let _ = launcher.set_focus();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_valid_links() {
+        let cases = [
+            (
+                "kimi://ask?q=What%20is%20Rust%3F&new=1",
+                DeepLink::Ask {
+                    message: "What is Rust?".to_string(),
+                    new_chat: Some(true),
+                },
+            ),
+            (
+                "kimi://ask/?q=hello+there",
+                DeepLink::Ask {
+                    message: "hello there".to_string(),
+                    new_chat: None,
+                },
+            ),
+            (
+                "kimi://open?path=/chat/abc123",
+                DeepLink::Open {
+                    path: "/chat/abc123".to_string(),
+                },
+            ),
+            ("KIMI://launcher", DeepLink::Launcher),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(DeepLink::parse(raw).unwrap(), expected, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_links() {
+        let long = format!(
+            "kimi://ask?q={}",
+            "a".repeat(DeepLinks::MAX_MESSAGE_CHARS + 1)
+        );
+        let cases = [
+            "https://www.kimi.com/",
+            "kimi://ask",
+            "kimi://ask?q=%20%20",
+            "kimi://ask?q=hi&new=yes",
+            "kimi://open?path=//evil.example/chat",
+            "kimi://open?path=/%5Cevil.example",
+            "kimi://open?path=https://evil.example",
+            "kimi://open?path=/%09/evil.example/x",
+            "kimi://open?path=/%0A/evil.example/x",
+            "kimi://open?path=%0D%0A//evil.example",
+            "kimi://launcher/extra",
+            "kimi://delete",
+            long.as_str(),
+        ];
+        for raw in cases {
+            assert!(DeepLink::parse(raw).is_err(), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn test_from_args() {
+        let args = ["/usr/bin/kimi", "--flag", "kimi://launcher"];
+        assert_eq!(from_args(&args), Some("kimi://launcher"));
+        assert_eq!(from_args(&["/usr/bin/kimi", "kimi:"]), None);
+        assert_eq!(from_args::<&str>(&[]), None);
+    }
+}
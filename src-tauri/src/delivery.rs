@@ -0,0 +1,501 @@
+//! Sending prompts into the Kimi page
+//!
+//! The injected script gives up after `injection_max_retries` attempts at
+//! finding the chat input, which mostly happens when the page was still
+//! loading or got stuck. Each failed send is retried by loading the target
+//! page again and injecting again, with growing delays. When all attempts
+//! fail the prompt is copied to the clipboard and kept, so the toast in the
+//! main window can send it again. Failed prompts are kept until each is
+//! resent or dismissed; the toast offers the newest one.
+//!
+//! An attempt the page never reported on is not retried: the prompt may
+//! have gone in, and sending it again would post it twice. It is kept with
+//! the failed prompts, for the user to resend after checking the page.
+//!
+//! Sends are serialized: the page only holds one message in its input.
+//!
+//! Prompts for the chat may go into the conversation already open; any
+//! other destination has its page opened first and waited for as its
+//! readiness says.
+//!
+//! The chat mode last picked in the launcher is applied on every attempt,
+//! before the message goes in, so queued prompts use the mode current when
+//! they are delivered.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::{AppHandle, Listener, Manager, Url, WebviewWindow};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tokio::sync::oneshot;
+
+use crate::app_state;
+use crate::history;
+use crate::settings;
+use crate::stats::{self, InjectResult};
+use crate::wrappers::{
+    chat_mode::ChatMode, config::Timeouts, destinations::SelectorProfile, submit_chat_message,
+    timing::TimingSettings, WrapperError, WrapperExt, WrapperResult,
+};
+
+/// When to retry a failed send
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts including the first one
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: Timeouts::DELIVERY_MAX_ATTEMPTS,
+            initial_delay: Duration::from_millis(Timeouts::DELIVERY_RETRY_DELAY),
+            max_delay: Duration::from_millis(Timeouts::DELIVERY_MAX_BACKOFF),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before attempt `attempt` (1-based), or `None` once attempts
+    /// are used up; the first attempt starts right away
+    pub fn delay_before(&self, attempt: u32) -> Option<Duration> {
+        match attempt {
+            0 => None,
+            _ if attempt > self.max_attempts => None,
+            1 => Some(Duration::ZERO),
+            _ => {
+                let doublings = (attempt - 2).min(16);
+                Some(
+                    self.initial_delay
+                        .saturating_mul(1 << doublings)
+                        .min(self.max_delay),
+                )
+            }
+        }
+    }
+}
+
+/// What the toast in the main window shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Sending,
+    Retrying { attempt: u32, max_attempts: u32 },
+    Failed,
+    Hidden,
+}
+
+impl Status {
+    fn name(&self) -> &'static str {
+        match self {
+            Status::Sending => "sending",
+            Status::Retrying { .. } => "retrying",
+            Status::Failed => "failed",
+            Status::Hidden => "hidden",
+        }
+    }
+
+    pub fn text(&self) -> String {
+        match self {
+            Status::Sending => "Sending…".to_string(),
+            Status::Retrying {
+                attempt,
+                max_attempts,
+            } => format!("Retrying… ({} of {})", attempt, max_attempts),
+            Status::Failed => "Failed – message copied to clipboard".to_string(),
+            Status::Hidden => String::new(),
+        }
+    }
+}
+
+/// A prompt whose send failed after all retries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedPrompt {
+    pub id: u64,
+    pub message: String,
+    pub new_chat: bool,
+    /// Id of the `Destination` it was sent to
+    pub destination: String,
+}
+
+/// Failed prompts waiting to be resent or dismissed, oldest first
+#[derive(Debug, Default)]
+pub struct FailedPrompts {
+    prompts: Vec<FailedPrompt>,
+    next_id: u64,
+}
+
+impl FailedPrompts {
+    /// Keep a prompt and return its id
+    pub fn add(&mut self, message: &str, new_chat: bool, destination: &str) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.prompts.push(FailedPrompt {
+            id,
+            message: message.to_string(),
+            new_chat,
+            destination: destination.to_string(),
+        });
+        id
+    }
+
+    pub fn take(&mut self, id: u64) -> Option<FailedPrompt> {
+        let index = self.prompts.iter().position(|p| p.id == id)?;
+        Some(self.prompts.remove(index))
+    }
+
+    pub fn latest(&self) -> Option<&FailedPrompt> {
+        self.prompts.last()
+    }
+
+    pub fn len(&self) -> usize {
+        self.prompts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prompts.is_empty()
+    }
+}
+
+#[derive(Default)]
+pub struct Delivery {
+    failed: Mutex<FailedPrompts>,
+    sending: tokio::sync::Mutex<()>,
+}
+
+/// Send `message` to the destination with id `destination`, retrying per
+/// `RetryPolicy::default()`
+///
+/// Returns `Ok(false)` when there is no main window to send to and an error
+/// once every attempt failed; the prompt is then kept with the failed prompts.
+pub async fn deliver(
+    app: &AppHandle,
+    message: &str,
+    new_chat: bool,
+    destination: &str,
+) -> WrapperResult<bool> {
+    let Some(main_window) = app.get_webview_window("main") else {
+        return Ok(false);
+    };
+    let delivery = app.state::<Delivery>();
+    let _sending = delivery.sending.lock().await;
+
+    main_window
+        .show()
+        .map_err(WrapperError::window(&main_window, "show"))?;
+    main_window
+        .set_focus()
+        .map_err(WrapperError::window(&main_window, "focus"))?;
+
+    let settings = settings::load(app).unwrap_or_default();
+    let endpoints = settings.endpoints();
+    let timing = settings.timing;
+    let mode = settings.chat_mode;
+    let destinations = settings.destinations();
+    let destination = destinations.get(destination);
+    // Only the chat can stay on the conversation already open
+    let open_page = new_chat || !destination.is_chat();
+    // Stored destinations were validated, so the chat is only a safeguard
+    let page = destination
+        .url(&endpoints)
+        .unwrap_or_else(|| endpoints.chat());
+    let target = if open_page {
+        page
+    } else {
+        current_kimi_page(&main_window).unwrap_or(page)
+    };
+    let policy = RetryPolicy::default();
+    let mut last_error = String::new();
+
+    let mut attempt = 1;
+    while let Some(delay) = policy.delay_before(attempt) {
+        let status = if attempt == 1 {
+            Status::Sending
+        } else {
+            Status::Retrying {
+                attempt,
+                max_attempts: policy.max_attempts,
+            }
+        };
+        if attempt > 1 {
+            show_status(&main_window, status);
+            tokio::time::sleep(delay).await;
+        }
+        // The first attempt stays on the current conversation unless asked
+        // otherwise; retries load the page again in case it is stuck
+        if attempt > 1 || open_page {
+            main_window
+                .navigate(target.clone())
+                .map_err(WrapperError::window(&main_window, "navigate"))?;
+            tokio::time::sleep(Duration::from_millis(destination.readiness.wait(&timing))).await;
+        } else {
+            tokio::time::sleep(Duration::from_millis(timing.window_visible_delay)).await;
+        }
+        show_status(&main_window, status);
+
+        set_chat_mode(app, &main_window, &mode, &timing).await;
+        match send_once(app, &main_window, message, &destination.selectors, &timing).await {
+            Ok(()) => {
+                // Earlier failures still wait for the user
+                show_failed(app, &main_window);
+                app_state::update(app, |state| {
+                    state.generating = true;
+                    state.unread = false;
+                });
+                history::prompt_sent(app, message, new_chat, &destination.id);
+                return Ok(true);
+            }
+            Err(Attempt::Failed(e)) => {
+                eprintln!("Send attempt {} failed: {}", attempt, e);
+                last_error = e;
+            }
+            Err(Attempt::Unknown(e)) => {
+                eprintln!("Send attempt {} has an unknown outcome: {}", attempt, e);
+                last_error = e;
+                break;
+            }
+        }
+        attempt += 1;
+    }
+
+    give_up(app, message, new_chat, &destination.id);
+    Err(WrapperError::DeliveryFailed {
+        attempts: attempt.min(policy.max_attempts),
+        message: last_error,
+    })
+}
+
+/// What `CHAT_MODE_JS` reports back
+#[derive(Debug, Default, serde::Deserialize)]
+struct ChatModeResult {
+    #[serde(default)]
+    missing: Vec<String>,
+}
+
+/// Switch the page's toggles to `mode` and wait until it reports back
+///
+/// A toggle the page does not have only gets logged: the prompt is still
+/// sent, in whatever mode the page is in.
+async fn set_chat_mode(
+    app: &AppHandle,
+    window: &WebviewWindow,
+    mode: &ChatMode,
+    timing: &TimingSettings,
+) {
+    if mode.toggles().is_empty() {
+        return;
+    }
+    let (tx, rx) = oneshot::channel();
+    let id = app.once("chat-mode-result", move |event| {
+        let result: ChatModeResult = serde_json::from_str(event.payload()).unwrap_or_default();
+        let _ = tx.send(result);
+    });
+    if let Err(e) = window.inject_chat_mode(mode, timing) {
+        app.unlisten(id);
+        eprintln!("Failed to set chat mode: {}", e);
+        return;
+    }
+    match tokio::time::timeout(Duration::from_millis(timing.injection_total), rx).await {
+        Ok(Ok(result)) if !result.missing.is_empty() => {
+            eprintln!("Chat mode toggles not found: {}", result.missing.join(", "));
+        }
+        Ok(_) => {}
+        Err(_) => {
+            app.unlisten(id);
+            eprintln!("The page did not report the chat mode");
+        }
+    }
+}
+
+/// Why an attempt did not confirm the send
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Attempt {
+    /// The prompt did not go in, so trying again is safe
+    Failed(String),
+    /// The page never reported back; the prompt may have been sent
+    Unknown(String),
+}
+
+/// Inject the message once and wait for the page to report the result
+async fn send_once(
+    app: &AppHandle,
+    window: &WebviewWindow,
+    message: &str,
+    selectors: &SelectorProfile,
+    timing: &TimingSettings,
+) -> Result<(), Attempt> {
+    let (tx, rx) = oneshot::channel();
+    let id = app.once("inject-result", move |event| {
+        let result: InjectResult = serde_json::from_str(event.payload()).unwrap_or_default();
+        let _ = tx.send(result);
+    });
+    if let Err(e) = submit_chat_message(window, message, selectors, timing) {
+        app.unlisten(id);
+        return Err(Attempt::Failed(e.to_string()));
+    }
+
+    let wait = Duration::from_millis(timing.injection_total + Timeouts::DELIVERY_RESULT_GRACE);
+    let result = match tokio::time::timeout(wait, rx).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => return Err(Attempt::Unknown("injection result was dropped".to_string())),
+        Err(_) => {
+            app.unlisten(id);
+            stats::record(
+                app,
+                &InjectResult {
+                    error: Some("the page did not report a result".to_string()),
+                    elapsed_ms: wait.as_millis() as u64,
+                    ..Default::default()
+                },
+            );
+            return Err(Attempt::Unknown(
+                "the page did not report a result".to_string(),
+            ));
+        }
+    };
+    stats::record(app, &result);
+    if result.success {
+        Ok(())
+    } else {
+        Err(Attempt::Failed(
+            result
+                .error
+                .unwrap_or_else(|| "injection failed".to_string()),
+        ))
+    }
+}
+
+fn give_up(app: &AppHandle, message: &str, new_chat: bool, destination: &str) {
+    if let Err(e) = app.clipboard().write_text(message.to_string()) {
+        eprintln!("Failed to copy message to clipboard: {}", e);
+    }
+    app.state::<Delivery>()
+        .failed
+        .lock()
+        .unwrap()
+        .add(message, new_chat, destination);
+    if let Some(main_window) = app.get_webview_window("main") {
+        show_failed(app, &main_window);
+    }
+}
+
+fn show_status(window: &WebviewWindow, status: Status) {
+    let _ = window.inject_delivery_toast(status.name(), &status.text(), None);
+}
+
+/// Offer the newest failed prompt in the toast, or hide it when none is left
+fn show_failed(app: &AppHandle, window: &WebviewWindow) {
+    let delivery = app.state::<Delivery>();
+    let (latest, count) = {
+        let failed = delivery.failed.lock().unwrap();
+        (failed.latest().map(|p| p.id), failed.len())
+    };
+    let Some(id) = latest else {
+        show_status(window, Status::Hidden);
+        return;
+    };
+    let mut text = Status::Failed.text();
+    if count > 1 {
+        text = format!("{} ({} more failed)", text, count - 1);
+    }
+    let _ = window.inject_delivery_toast(Status::Failed.name(), &text, Some(id));
+}
+
+fn current_kimi_page(window: &WebviewWindow) -> Option<Url> {
+    window.url().ok().filter(|url| {
+        matches!(url.scheme(), "http" | "https") && url.host_str() != Some("tauri.localhost")
+    })
+}
+
+/// Show the failed toast again after the main window loaded a page
+pub fn restore_toast(app: &AppHandle) {
+    let has_failed = app
+        .try_state::<Delivery>()
+        .is_some_and(|delivery| !delivery.failed.lock().unwrap().is_empty());
+    if let (true, Some(main_window)) = (has_failed, app.get_webview_window("main")) {
+        show_failed(app, &main_window);
+    }
+}
+
+/// Take the failed prompt with id `id` so it can be sent again
+pub fn take_failed(app: &AppHandle, id: u64) -> Option<FailedPrompt> {
+    app.state::<Delivery>().failed.lock().unwrap().take(id)
+}
+
+/// Drop the failed prompt with id `id` and offer the next one, if any
+pub fn dismiss_failed(app: &AppHandle, id: u64) {
+    take_failed(app, id);
+    if let Some(main_window) = app.get_webview_window("main") {
+        show_failed(app, &main_window);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delays_grow_and_stop() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            initial_delay: Duration::from_millis(1000),
+            max_delay: Duration::from_millis(3000),
+        };
+        let delays: Vec<Option<u128>> = (0..=6)
+            .map(|attempt| policy.delay_before(attempt).map(|d| d.as_millis()))
+            .collect();
+        assert_eq!(
+            delays,
+            vec![
+                None,
+                Some(0),
+                Some(1000),
+                Some(2000),
+                Some(3000),
+                Some(3000),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_policy_retries() {
+        let policy = RetryPolicy::default();
+        assert!(policy.max_attempts > 1);
+        assert!(policy.delay_before(policy.max_attempts).is_some());
+        assert!(policy.delay_before(policy.max_attempts + 1).is_none());
+    }
+
+    #[test]
+    fn test_status_text() {
+        assert_eq!(Status::Sending.text(), "Sending…");
+        let retrying = Status::Retrying {
+            attempt: 2,
+            max_attempts: 3,
+        };
+        assert_eq!(retrying.text(), "Retrying… (2 of 3)");
+        assert_eq!(
+            Status::Failed.text(),
+            "Failed – message copied to clipboard"
+        );
+    }
+
+    #[test]
+    fn test_failed_prompts_keep_every_failure() {
+        let mut failed = FailedPrompts::default();
+        let first = failed.add("first", true, "chat");
+        let second = failed.add("second", false, "chat");
+        assert_ne!(first, second);
+        assert_eq!(failed.len(), 2);
+        assert_eq!(failed.latest().unwrap().message, "second");
+
+        assert_eq!(failed.take(first).unwrap().message, "first");
+        assert!(failed.take(first).is_none());
+        assert_eq!(failed.latest().unwrap().id, second);
+        failed.take(second);
+        assert!(failed.is_empty());
+        // Ids are not reused once taken
+        assert_ne!(failed.add("third", true, "chat"), second);
+    }
+}
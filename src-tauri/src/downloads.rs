@@ -0,0 +1,530 @@
+//! Downloads started from the main window
+//!
+//! The webview's own download handling is replaced: files are saved to the
+//! configured directory under a name that never overwrites an existing file,
+//! progress is reported through `download-progress` events, and the most
+//! recent downloads are listed in the tray menu.
+//!
+//! Progress is read from the file on disk, or from the partial file the
+//! webview writes first. A download whose file grew and then stopped for
+//! `Timeouts::DOWNLOAD_STALL_TIMEOUT` counts as failed; one whose file was
+//! never seen only ends with the webview's `Finished` event.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tauri::webview::DownloadEvent;
+use tauri::{AppHandle, Emitter, Manager, Url, Webview};
+
+use crate::notifications;
+use crate::settings;
+use crate::tray;
+use crate::wrappers::{
+    config::{Timeouts, Tray},
+    WrapperError, WrapperResult,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct DownloadSettings {
+    /// Directory downloads are saved to; the system Downloads folder when unset
+    pub directory: Option<String>,
+}
+
+impl DownloadSettings {
+    pub fn validate(&self) -> WrapperResult<()> {
+        match self.directory.as_deref().map(str::trim) {
+            Some(dir) if !dir.is_empty() && !Path::new(dir).is_absolute() => {
+                Err(WrapperError::InvalidSettings {
+                    field: "downloads.directory".to_string(),
+                    message: "download directory must be an absolute path".to_string(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadState {
+    InProgress,
+    Completed,
+    Failed,
+}
+
+/// A download as reported to the frontend and listed in the tray
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DownloadEntry {
+    pub id: u64,
+    pub url: String,
+    pub file_name: String,
+    pub path: PathBuf,
+    pub state: DownloadState,
+    /// Bytes written to disk so far
+    pub received_bytes: u64,
+}
+
+/// Recent downloads, newest first, kept in app state
+#[derive(Default)]
+pub struct Downloads {
+    entries: Mutex<VecDeque<DownloadEntry>>,
+    next_id: AtomicU64,
+}
+
+impl Downloads {
+    fn start(&self, url: &Url, path: PathBuf) -> DownloadEntry {
+        let entry = DownloadEntry {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            url: url.to_string(),
+            file_name: display_name(&path),
+            path,
+            state: DownloadState::InProgress,
+            received_bytes: 0,
+        };
+        let mut entries = self.entries.lock().unwrap();
+        entries.push_front(entry.clone());
+        // Only drop finished entries so in-progress downloads stay tracked
+        while entries.len() > Tray::RECENT_DOWNLOADS {
+            match entries
+                .iter()
+                .rposition(|e| e.state != DownloadState::InProgress)
+            {
+                Some(index) => entries.remove(index),
+                None => break,
+            };
+        }
+        entry
+    }
+
+    /// Mark the oldest in-progress download of `url` as finished
+    fn finish(&self, url: &Url, path: Option<PathBuf>, success: bool) -> Option<DownloadEntry> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries
+            .iter_mut()
+            .rev()
+            .find(|e| e.state == DownloadState::InProgress && e.url == url.as_str())?;
+        if let Some(path) = path.filter(|p| !p.as_os_str().is_empty()) {
+            entry.file_name = display_name(&path);
+            entry.path = path;
+        }
+        entry.state = if success {
+            DownloadState::Completed
+        } else {
+            DownloadState::Failed
+        };
+        if let Ok(metadata) = std::fs::metadata(&entry.path) {
+            entry.received_bytes = metadata.len();
+        }
+        Some(entry.clone())
+    }
+
+    /// Mark an in-progress download as failed, such as one that stalled
+    fn fail(&self, id: u64) -> Option<DownloadEntry> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries
+            .iter_mut()
+            .find(|e| e.id == id && e.state == DownloadState::InProgress)?;
+        entry.state = DownloadState::Failed;
+        Some(entry.clone())
+    }
+
+    /// Record progress; returns the entry if it is still in progress and changed
+    fn set_received(&self, id: u64, bytes: u64) -> Option<DownloadEntry> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries
+            .iter_mut()
+            .find(|e| e.id == id && e.state == DownloadState::InProgress)?;
+        if entry.received_bytes == bytes {
+            return None;
+        }
+        entry.received_bytes = bytes;
+        Some(entry.clone())
+    }
+
+    fn is_active(&self, id: u64) -> bool {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|e| e.id == id && e.state == DownloadState::InProgress)
+    }
+
+    fn is_pending(&self, path: &Path) -> bool {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|e| e.state == DownloadState::InProgress && e.path == path)
+    }
+
+    pub fn get(&self, id: u64) -> Option<DownloadEntry> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|e| e.id == id)
+            .cloned()
+    }
+
+    /// Recent downloads, newest first
+    pub fn recent(&self) -> Vec<DownloadEntry> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "download".to_string())
+}
+
+/// Make a name suggested by the page or URL safe to use as a file name
+pub fn sanitize_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+    if cleaned.is_empty() {
+        return "download".to_string();
+    }
+    // Reserved device names are not valid file names on Windows
+    let stem = cleaned
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || (stem.len() == 4
+            && (stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.as_bytes()[3].is_ascii_digit());
+    if reserved {
+        format!("_{}", cleaned)
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// Pick the file name for a download: the webview's suggestion, else the last
+/// URL path segment
+pub fn file_name_for(url: &Url, suggested: &Path) -> String {
+    let suggested = suggested
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .filter(|n| !n.is_empty());
+    let from_url = || {
+        url.path_segments()
+            .and_then(|mut segments| segments.next_back().map(str::to_string))
+            .filter(|segment| !segment.is_empty())
+    };
+    sanitize_file_name(&suggested.or_else(from_url).unwrap_or_default())
+}
+
+/// First path in `dir` named `name`, `name (1)`, `name (2)`, ... that is not taken
+pub fn unique_path(dir: &Path, name: &str, taken: impl Fn(&Path) -> bool) -> PathBuf {
+    let candidate = dir.join(name);
+    if !taken(&candidate) {
+        return candidate;
+    }
+    let (stem, ext) = match name.rfind('.') {
+        Some(i) if i > 0 => (&name[..i], &name[i..]),
+        _ => (name, ""),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, ext)))
+        .find(|path| !taken(path))
+        .expect("unbounded range yields a free name")
+}
+
+/// Suffixes of the partial file a webview writes before renaming it, such
+/// as WebView2's `.crdownload`
+const PARTIAL_SUFFIXES: [&str; 2] = [".crdownload", ".wkdownload"];
+
+/// Bytes written so far for a download to `path`, looking at its partial
+/// file too; `None` while neither is on disk
+pub fn written_bytes(path: &Path) -> Option<u64> {
+    let size = |path: &Path| {
+        std::fs::metadata(path)
+            .ok()
+            .filter(|m| m.is_file())
+            .map(|m| m.len())
+    };
+    PARTIAL_SUFFIXES
+        .iter()
+        .filter_map(|suffix| {
+            let mut partial = path.as_os_str().to_owned();
+            partial.push(suffix);
+            size(Path::new(&partial))
+        })
+        .chain(size(path))
+        .max()
+}
+
+/// Directory new downloads are saved to
+pub fn directory(app: &AppHandle) -> WrapperResult<PathBuf> {
+    let configured = settings::load(app)
+        .unwrap_or_default()
+        .downloads
+        .directory
+        .map(|dir| dir.trim().to_string())
+        .filter(|dir| !dir.is_empty());
+    match configured {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => app.path().download_dir().map_err(WrapperError::from),
+    }
+}
+
+/// Download handler for the main window's webview
+///
+/// Always lets the webview perform the transfer, after redirecting it to a
+/// collision-safe path in the configured directory.
+pub fn handle(webview: Webview, event: DownloadEvent<'_>) -> bool {
+    let app = webview.app_handle();
+    match event {
+        DownloadEvent::Requested { url, destination } => {
+            match prepare_destination(app, &url, destination) {
+                Ok(path) => *destination = path,
+                Err(e) => eprintln!("Falling back to default download location: {}", e),
+            }
+            let entry = app.state::<Downloads>().start(&url, destination.clone());
+            emit_progress(app, &entry);
+            refresh_tray(app);
+            watch_progress(app.clone(), entry.id);
+        }
+        DownloadEvent::Finished { url, path, success } => {
+            if let Some(entry) = app.state::<Downloads>().finish(&url, path, success) {
+                finished(app, &entry);
+            }
+        }
+        _ => {}
+    }
+    true
+}
+
+fn prepare_destination(app: &AppHandle, url: &Url, suggested: &Path) -> WrapperResult<PathBuf> {
+    let dir = directory(app)?;
+    std::fs::create_dir_all(&dir).map_err(|e| WrapperError::InvalidSettings {
+        field: "downloads.directory".to_string(),
+        message: format!("cannot create {}: {}", dir.display(), e),
+    })?;
+    let downloads = app.state::<Downloads>();
+    let name = file_name_for(url, suggested);
+    Ok(unique_path(&dir, &name, |p| {
+        p.exists() || downloads.is_pending(p)
+    }))
+}
+
+fn emit_progress(app: &AppHandle, entry: &DownloadEntry) {
+    let _ = app.emit("download-progress", entry);
+}
+
+/// Only starts and finishes change the tray menu; progress goes out as events
+fn refresh_tray(app: &AppHandle) {
+    if let Err(e) = tray::refresh(app) {
+        eprintln!("Failed to refresh tray menu: {}", e);
+    }
+}
+
+fn finished(app: &AppHandle, entry: &DownloadEntry) {
+    emit_progress(app, entry);
+    refresh_tray(app);
+    announce(app, entry);
+}
+
+/// Poll the size of the file on disk until the download finishes or stalls
+fn watch_progress(app: AppHandle, id: u64) {
+    std::thread::spawn(move || {
+        let interval = Duration::from_millis(Timeouts::DOWNLOAD_PROGRESS_INTERVAL);
+        let stall_timeout = Duration::from_millis(Timeouts::DOWNLOAD_STALL_TIMEOUT);
+        let downloads = app.state::<Downloads>();
+        let mut last_progress = Instant::now();
+        // Without a file to watch, only the webview knows how it is going
+        let mut observed = false;
+        while downloads.is_active(id) {
+            std::thread::sleep(interval);
+            let Some(path) = downloads.get(id).map(|e| e.path) else {
+                break;
+            };
+            let bytes = written_bytes(&path).unwrap_or(0);
+            observed |= bytes > 0;
+            if let Some(entry) = downloads.set_received(id, bytes) {
+                last_progress = Instant::now();
+                emit_progress(&app, &entry);
+            } else if observed && last_progress.elapsed() >= stall_timeout {
+                // The webview never reported the end of this download
+                if let Some(entry) = downloads.fail(id) {
+                    finished(&app, &entry);
+                }
+                break;
+            }
+        }
+    });
+}
+
+fn announce(app: &AppHandle, entry: &DownloadEntry) {
+    match entry.state {
+        DownloadState::Completed => {
+            let path = entry.path.clone();
+            notifications::show(
+                app,
+                "Download complete",
+                &entry.file_name,
+                Some(Box::new(move |_app: &AppHandle| reveal(&path))),
+            );
+        }
+        DownloadState::Failed => {
+            notifications::show(app, "Download failed", &entry.file_name, None);
+        }
+        DownloadState::InProgress => {}
+    }
+}
+
+/// Show a downloaded file in the system file manager
+pub fn reveal(path: &Path) {
+    let result = if path.exists() {
+        tauri_plugin_opener::reveal_item_in_dir(path)
+    } else {
+        match path.parent() {
+            Some(dir) => tauri_plugin_opener::open_path(dir, None::<&str>),
+            None => return,
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to show download: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_sanitize_file_name() {
+        assert_eq!(sanitize_file_name("slides.pptx"), "slides.pptx");
+        assert_eq!(sanitize_file_name("../../etc/passwd"), "_.._etc_passwd");
+        assert_eq!(sanitize_file_name("a\\b:c*?.txt"), "a_b_c__.txt");
+        assert_eq!(sanitize_file_name("line\nbreak.md"), "line_break.md");
+        assert_eq!(sanitize_file_name("  ..  "), "download");
+        assert_eq!(sanitize_file_name(""), "download");
+        assert_eq!(sanitize_file_name("con.txt"), "_con.txt");
+        assert_eq!(sanitize_file_name("COM1"), "_COM1");
+        assert_eq!(sanitize_file_name("console.log"), "console.log");
+    }
+
+    #[test]
+    fn test_file_name_for_prefers_suggestion() {
+        let url = Url::parse("https://www.kimi.com/api/file/report.pdf?sig=1").unwrap();
+        assert_eq!(
+            file_name_for(&url, Path::new("/tmp/Summary.docx")),
+            "Summary.docx"
+        );
+        assert_eq!(file_name_for(&url, Path::new("")), "report.pdf");
+
+        let url = Url::parse("https://www.kimi.com/").unwrap();
+        assert_eq!(file_name_for(&url, Path::new("")), "download");
+    }
+
+    #[test]
+    fn test_unique_path_avoids_collisions() {
+        let dir = Path::new("/downloads");
+        let taken: HashSet<PathBuf> = ["slides.pptx", "slides (1).pptx", "notes", ".env"]
+            .iter()
+            .map(|n| dir.join(n))
+            .collect();
+        let is_taken = |p: &Path| taken.contains(p);
+
+        assert_eq!(
+            unique_path(dir, "image.png", is_taken),
+            dir.join("image.png")
+        );
+        assert_eq!(
+            unique_path(dir, "slides.pptx", is_taken),
+            dir.join("slides (2).pptx")
+        );
+        assert_eq!(unique_path(dir, "notes", is_taken), dir.join("notes (1)"));
+        assert_eq!(unique_path(dir, ".env", is_taken), dir.join(".env (1)"));
+    }
+
+    #[test]
+    fn test_downloads_track_state() {
+        let downloads = Downloads::default();
+        let url = Url::parse("https://www.kimi.com/file/a.pdf").unwrap();
+        let entry = downloads.start(&url, PathBuf::from("/downloads/a.pdf"));
+        assert!(downloads.is_pending(Path::new("/downloads/a.pdf")));
+
+        assert!(downloads.set_received(entry.id, 10).is_some());
+        assert!(downloads.set_received(entry.id, 10).is_none());
+
+        let finished = downloads.finish(&url, None, true).unwrap();
+        assert_eq!(finished.state, DownloadState::Completed);
+        assert_eq!(finished.path, PathBuf::from("/downloads/a.pdf"));
+        assert!(!downloads.is_active(entry.id));
+        assert!(downloads.finish(&url, None, true).is_none());
+    }
+
+    #[test]
+    fn test_written_bytes_reads_partial_file() {
+        let dir = std::env::temp_dir().join(format!("kimi-downloads-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.pdf");
+        assert_eq!(written_bytes(&path), None);
+
+        std::fs::write(dir.join("a.pdf.crdownload"), [0u8; 5]).unwrap();
+        assert_eq!(written_bytes(&path), Some(5));
+        std::fs::remove_file(dir.join("a.pdf.crdownload")).unwrap();
+        std::fs::write(&path, [0u8; 7]).unwrap();
+        assert_eq!(written_bytes(&path), Some(7));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_downloads_fail_stalled_entry() {
+        let downloads = Downloads::default();
+        let url = Url::parse("https://www.kimi.com/file/a.pdf").unwrap();
+        let entry = downloads.start(&url, PathBuf::from("/downloads/a.pdf"));
+
+        let failed = downloads.fail(entry.id).unwrap();
+        assert_eq!(failed.state, DownloadState::Failed);
+        assert!(!downloads.is_active(entry.id));
+        assert!(downloads.fail(entry.id).is_none());
+        // A late finish no longer finds it
+        assert!(downloads.finish(&url, None, true).is_none());
+    }
+
+    #[test]
+    fn test_downloads_keep_in_progress_entries() {
+        let downloads = Downloads::default();
+        let url = Url::parse("https://www.kimi.com/file/a.pdf").unwrap();
+        let first = downloads.start(&url, PathBuf::from("/downloads/first.pdf"));
+        for i in 0..Tray::RECENT_DOWNLOADS + 2 {
+            let other = Url::parse(&format!("https://www.kimi.com/file/{}.pdf", i)).unwrap();
+            downloads.start(&other, PathBuf::from(format!("/downloads/{}.pdf", i)));
+            downloads.finish(&other, None, true);
+        }
+        let recent = downloads.recent();
+        assert_eq!(recent.len(), Tray::RECENT_DOWNLOADS);
+        assert!(recent.iter().any(|e| e.id == first.id));
+    }
+
+    #[test]
+    fn test_download_settings_validate() {
+        assert!(DownloadSettings::default().validate().is_ok());
+        let relative = DownloadSettings {
+            directory: Some("Downloads/kimi".to_string()),
+        };
+        assert!(relative.validate().is_err());
+        let blank = DownloadSettings {
+            directory: Some("  ".to_string()),
+        };
+        assert!(blank.validate().is_ok());
+    }
+}
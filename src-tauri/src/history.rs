@@ -0,0 +1,191 @@
+//! Recently opened conversations and recently sent launcher prompts
+//!
+//! Kept in memory only and listed in the tray menu. Entries carry ids that
+//! stay stable while the lists change, so a menu item built earlier still
+//! refers to the entry it was built for.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Manager};
+
+use crate::app_state;
+use crate::notifications::{conversation_title, conversation_url, PageInfo};
+use crate::settings;
+use crate::tray;
+use crate::wrappers::config::{Tray, Urls};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentConversation {
+    pub id: u64,
+    pub title: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentPrompt {
+    pub id: u64,
+    pub message: String,
+    pub new_chat: bool,
+    /// Id of the `Destination` it was sent to
+    pub destination: String,
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    conversations: VecDeque<RecentConversation>,
+    prompts: VecDeque<RecentPrompt>,
+    next_id: u64,
+}
+
+impl History {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Move a conversation to the front; returns whether the list changed
+    pub fn record_conversation(&mut self, title: &str, url: &str) -> bool {
+        if let Some(front) = self.conversations.front() {
+            if front.url == url && front.title == title {
+                return false;
+            }
+        }
+        self.conversations.retain(|c| c.url != url);
+        let id = self.next_id();
+        self.conversations.push_front(RecentConversation {
+            id,
+            title: title.to_string(),
+            url: url.to_string(),
+        });
+        self.conversations.truncate(Tray::RECENT_CONVERSATIONS);
+        true
+    }
+
+    /// Move a prompt to the front, replacing an earlier copy of the same text
+    pub fn record_prompt(&mut self, message: &str, new_chat: bool, destination: &str) {
+        let message = message.trim();
+        if message.is_empty() {
+            return;
+        }
+        self.prompts.retain(|p| p.message != message);
+        let id = self.next_id();
+        self.prompts.push_front(RecentPrompt {
+            id,
+            message: message.to_string(),
+            new_chat,
+            destination: destination.to_string(),
+        });
+        self.prompts.truncate(Tray::RECENT_PROMPTS);
+    }
+
+    pub fn conversations(&self) -> impl Iterator<Item = &RecentConversation> {
+        self.conversations.iter()
+    }
+
+    pub fn prompts(&self) -> impl Iterator<Item = &RecentPrompt> {
+        self.prompts.iter()
+    }
+
+    pub fn conversation(&self, id: u64) -> Option<&RecentConversation> {
+        self.conversations.iter().find(|c| c.id == id)
+    }
+
+    pub fn prompt(&self, id: u64) -> Option<&RecentPrompt> {
+        self.prompts.iter().find(|p| p.id == id)
+    }
+}
+
+/// Record the page reported by the conversation observer
+pub fn conversation_changed(app: &AppHandle, page: PageInfo) {
+    let Some(url) = conversation_url(&page.url, &settings::endpoints(app)) else {
+        return;
+    };
+    let title = conversation_title(&page.title);
+    app_state::update(app, |state| state.set_page(url.as_str(), title.clone()));
+
+    if !url.path().starts_with(Urls::CONVERSATION_PATH_PREFIX) {
+        return;
+    }
+    let changed = app
+        .state::<Mutex<History>>()
+        .lock()
+        .unwrap()
+        .record_conversation(
+            title.as_deref().unwrap_or("Untitled conversation"),
+            url.as_str(),
+        );
+    if changed {
+        refresh_tray(app);
+    }
+}
+
+/// Remember a prompt sent from the launcher
+pub fn prompt_sent(app: &AppHandle, message: &str, new_chat: bool, destination: &str) {
+    if let Some(history) = app.try_state::<Mutex<History>>() {
+        history
+            .lock()
+            .unwrap()
+            .record_prompt(message, new_chat, destination);
+        refresh_tray(app);
+    }
+}
+
+fn refresh_tray(app: &AppHandle) {
+    if let Err(e) = tray::refresh(app) {
+        eprintln!("Failed to refresh tray menu: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversations_are_deduplicated_by_url() {
+        let mut history = History::default();
+        assert!(history.record_conversation("A", "https://www.kimi.com/chat/a"));
+        assert!(history.record_conversation("B", "https://www.kimi.com/chat/b"));
+        assert!(!history.record_conversation("B", "https://www.kimi.com/chat/b"));
+        // A title change for the same URL is recorded and moves it to the front
+        assert!(history.record_conversation("A renamed", "https://www.kimi.com/chat/a"));
+
+        let titles: Vec<&str> = history.conversations().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, vec!["A renamed", "B"]);
+    }
+
+    #[test]
+    fn test_lists_are_bounded() {
+        let mut history = History::default();
+        for i in 0..Tray::RECENT_CONVERSATIONS + 3 {
+            history
+                .record_conversation(&i.to_string(), &format!("https://www.kimi.com/chat/{}", i));
+            history.record_prompt(&format!("prompt {}", i), true, "chat");
+        }
+        assert_eq!(history.conversations().count(), Tray::RECENT_CONVERSATIONS);
+        assert_eq!(history.prompts().count(), Tray::RECENT_PROMPTS);
+        let newest = Tray::RECENT_CONVERSATIONS + 2;
+        assert_eq!(
+            history.conversations().next().unwrap().title,
+            newest.to_string()
+        );
+    }
+
+    #[test]
+    fn test_prompt_ids_stay_stable() {
+        let mut history = History::default();
+        history.record_prompt("  summarize this  ", false, "claw");
+        let id = history.prompts().next().unwrap().id;
+        history.record_prompt("translate", true, "chat");
+
+        let prompt = history.prompt(id).unwrap();
+        assert_eq!(prompt.message, "summarize this");
+        assert!(prompt.destination == "claw" && !prompt.new_chat);
+
+        history.record_prompt("summarize this", true, "chat");
+        assert!(history.prompt(id).is_none());
+        assert_eq!(history.prompts().count(), 2);
+        history.record_prompt("   ", true, "chat");
+        assert_eq!(history.prompts().count(), 2);
+    }
+}
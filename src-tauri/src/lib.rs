@@ -1,9 +1,8 @@
 #[cfg(target_os = "macos")]
 use tauri::TitleBarStyle;
 use tauri::{
-    menu::{Menu, MenuItem, PredefinedMenuItem},
-    tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    AppHandle, Listener, Manager, RunEvent,
+    webview::PageLoadEvent, AppHandle, Listener, Manager, RunEvent, WebviewWindow,
+    WebviewWindowBuilder,
 };
 
 #[cfg(target_os = "macos")]
@@ -17,53 +16,79 @@ use objc::{msg_send, sel, sel_impl};
 
 use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutState};
 
+mod app_state;
+mod close_behavior;
+mod connectivity;
+mod deep_link;
+mod delivery;
+mod downloads;
+mod external_url;
+mod history;
+mod links;
+mod notification_policy;
+mod notifications;
+mod outbox;
+mod proxy;
+mod settings;
+mod startup;
+mod stats;
+mod tray;
 mod wrappers;
-use wrappers::{WrapperExt, apply_all_wrappers, submit_chat_message, set_offline_state, emit_launcher_shown, emit_settings_changed};
-use wrappers::config::Urls;
-
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct AppSettings {
-    pub new_chat_default: bool,
-    pub notifications_enabled: bool,
-}
-
-impl Default for AppSettings {
-    fn default() -> Self {
-        Self {
-            new_chat_default: true,
-            notifications_enabled: true,
-        }
-    }
-}
+mod zoom;
+use external_url::validate_external_url;
+use links::LinkAction;
+use proxy::{ConnectionReport, ProxyMode, ProxySettings};
+pub use settings::AppSettings;
+use wrappers::{
+    apply_all_wrappers, chat_mode::ChatMode, destinations::Destination, emit_launcher_shown,
+    emit_settings_changed, CommandResult, WrapperError, WrapperExt, WrapperResult,
+};
+use zoom::ZoomAction;
 
 #[tauri::command]
-async fn hide_launcher(app: AppHandle) -> Result<(), String> {
+async fn hide_launcher(app: AppHandle) -> CommandResult<()> {
     if let Some(launcher) = app.get_webview_window("launcher") {
-        launcher.hide().map_err(|e| e.to_string())?;
+        launcher
+            .hide()
+            .map_err(WrapperError::window(&launcher, "hide"))?;
     }
     Ok(())
 }
 
 #[tauri::command]
-async fn show_launcher(app: AppHandle) -> Result<(), String> {
+async fn show_launcher(app: AppHandle) -> CommandResult<()> {
     if let Some(launcher) = app.get_webview_window("launcher") {
-        launcher.center().map_err(|e| e.to_string())?;
-        launcher.show().map_err(|e| e.to_string())?;
-        launcher.set_focus().map_err(|e| e.to_string())?;
+        launcher
+            .center()
+            .map_err(WrapperError::window(&launcher, "center"))?;
+        launcher
+            .show()
+            .map_err(WrapperError::window(&launcher, "show"))?;
+        launcher
+            .set_focus()
+            .map_err(WrapperError::window(&launcher, "focus"))?;
     }
     Ok(())
 }
 
 #[tauri::command]
-async fn toggle_launcher(app: AppHandle) -> Result<(), String> {
+async fn toggle_launcher(app: AppHandle) -> CommandResult<()> {
     if let Some(launcher) = app.get_webview_window("launcher") {
         let is_visible = launcher.is_visible().unwrap_or(false);
         if is_visible {
-            launcher.hide().map_err(|e| e.to_string())?;
+            launcher
+                .hide()
+                .map_err(WrapperError::window(&launcher, "hide"))?;
         } else {
-            launcher.center().map_err(|e| e.to_string())?;
-            launcher.show().map_err(|e| e.to_string())?;
-            launcher.set_focus().map_err(|e| e.to_string())?;
+            launcher
+                .center()
+                .map_err(WrapperError::window(&launcher, "center"))?;
+            launcher
+                .show()
+                .map_err(WrapperError::window(&launcher, "show"))?;
+            launcher
+                .set_focus()
+                .map_err(WrapperError::window(&launcher, "focus"))?;
             emit_launcher_shown(&app);
         }
     }
@@ -71,213 +96,287 @@ async fn toggle_launcher(app: AppHandle) -> Result<(), String> {
 }
 
 #[tauri::command]
-async fn show_main_window(app: AppHandle) -> Result<(), String> {
+async fn show_main_window(app: AppHandle) -> CommandResult<()> {
     if let Some(main_window) = app.get_webview_window("main") {
-        main_window.show().map_err(|e| e.to_string())?;
-        main_window.set_focus().map_err(|e| e.to_string())?;
+        main_window
+            .show()
+            .map_err(WrapperError::window(&main_window, "show"))?;
+        main_window
+            .set_focus()
+            .map_err(WrapperError::window(&main_window, "focus"))?;
     }
     Ok(())
 }
 
 #[tauri::command]
-async fn navigate_to_offline(app: AppHandle) -> Result<(), String> {
-    if let Some(main_window) = app.get_webview_window("main") {
-        let url = "tauri://localhost/index.html"
-            .parse::<tauri::Url>()
-            .map_err(|e| e.to_string())?;
-        main_window.navigate(url).map_err(|e| e.to_string())?;
-        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
-        let _ = set_offline_state(&main_window);
-    }
-    Ok(())
+async fn navigate_to_offline(app: AppHandle) -> CommandResult<()> {
+    Ok(connectivity::show_offline_page(&app).await?)
 }
 
 #[tauri::command]
-async fn navigate_to_chat(app: AppHandle) -> Result<(), String> {
-    if let Some(main_window) = app.get_webview_window("main") {
-        let url = Urls::CHAT.parse::<tauri::Url>().map_err(|e| e.to_string())?;
-        main_window.navigate(url).map_err(|e| e.to_string())?;
-    }
-    Ok(())
+async fn navigate_to_chat(app: AppHandle) -> CommandResult<()> {
+    // While the monitor sees Kimi as unreachable, probe first; it loads the
+    // chat itself once the probe succeeds. A system proxy the probe cannot
+    // see may still reach Kimi, so there the page gets to try.
+    let system_proxy = settings::load(&app).unwrap_or_default().proxy.mode == ProxyMode::System;
+    if connectivity::is_offline(&app) && !system_proxy {
+        connectivity::check_now(&app);
+        return Ok(());
+    }
+    Ok(connectivity::show_chat(&app)?)
 }
 
 #[tauri::command]
-async fn submit_message(app: AppHandle, message: String, new_chat: bool, bot_mode: bool) -> Result<(), String> {
-    use wrappers::config::Timeouts;
-    
-    if let Some(launcher) = app.get_webview_window("launcher") {
-        launcher.hide().map_err(|e| e.to_string())?;
-    }
+fn check_connectivity(app: AppHandle) {
+    connectivity::check_now(&app);
+}
 
-    if let Some(main_window) = app.get_webview_window("main") {
-        main_window.show().map_err(|e| e.to_string())?;
-        main_window.set_focus().map_err(|e| e.to_string())?;
-
-        if bot_mode {
-            let url = Urls::BOT.parse::<tauri::Url>().map_err(|e| e.to_string())?;
-            main_window.navigate(url).map_err(|e| e.to_string())?;
-            tokio::time::sleep(std::time::Duration::from_millis(Timeouts::BOT_PAGE_LOAD_WAIT)).await;
-        } else if new_chat {
-            let url = Urls::CHAT.parse::<tauri::Url>().map_err(|e| e.to_string())?;
-            main_window.navigate(url).map_err(|e| e.to_string())?;
-            tokio::time::sleep(std::time::Duration::from_millis(Timeouts::PAGE_LOAD_WAIT)).await;
-        } else {
-            tokio::time::sleep(std::time::Duration::from_millis(Timeouts::WINDOW_VISIBLE_DELAY)).await;
+#[tauri::command]
+async fn submit_message(
+    app: AppHandle,
+    message: String,
+    new_chat: bool,
+    destination: String,
+    chat_mode: ChatMode,
+) -> CommandResult<()> {
+    // Remembered for the next launcher and used by delivery, also for
+    // prompts already waiting in the outbox
+    let settings = match settings::update(&app, |s| s.chat_mode = chat_mode) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Failed to remember chat mode: {}", e);
+            settings::load(&app).unwrap_or_default()
         }
+    };
+    // A prefix typed before the message wins over the picked destination
+    let destinations = settings.destinations();
+    let (destination, message) = match destinations.split_prefix(&message) {
+        Some((prefixed, rest)) => (prefixed.id.as_str(), rest),
+        None => (destination.as_str(), message.as_str()),
+    };
+    Ok(submit(&app, message, new_chat, destination).await?)
+}
+
+#[tauri::command]
+async fn get_destinations(app: AppHandle) -> Vec<Destination> {
+    settings::load(&app)
+        .unwrap_or_default()
+        .destinations()
+        .all()
+        .to_vec()
+}
 
-        let _ = submit_chat_message(&main_window, &message);
+/// Show the main window and send `message` to Kimi, as the launcher does
+///
+/// While Kimi is offline, or earlier prompts are still waiting, the message
+/// goes to the outbox instead. Otherwise this returns once the send started:
+/// with retries it can take longer than callers wait, and the toast in the
+/// main window reports how it went.
+pub(crate) async fn submit(
+    app: &AppHandle,
+    message: &str,
+    new_chat: bool,
+    destination: &str,
+) -> WrapperResult<()> {
+    if let Some(launcher) = app.get_webview_window("launcher") {
+        launcher
+            .hide()
+            .map_err(WrapperError::window(&launcher, "hide"))?;
+    }
+
+    if outbox::must_queue(app) {
+        outbox::queue(app, message, new_chat, destination)?;
+        if let Some(main_window) = app.get_webview_window("main") {
+            main_window
+                .show()
+                .map_err(WrapperError::window(&main_window, "show"))?;
+            main_window
+                .set_focus()
+                .map_err(WrapperError::window(&main_window, "focus"))?;
+        }
+        outbox::deliver_pending(app);
+        return Ok(());
     }
 
+    let app = app.clone();
+    let message = message.to_string();
+    let destination = destination.to_string();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = delivery::deliver(&app, &message, new_chat, &destination).await {
+            eprintln!("Failed to send prompt: {}", e);
+        }
+    });
     Ok(())
 }
 
 #[tauri::command]
-async fn get_settings(app: AppHandle) -> Result<AppSettings, String> {
-    use tauri_plugin_store::StoreExt;
-    let store = app.store("settings.json").map_err(|e| e.to_string())?;
-    let settings = match store.get("app_settings") {
-        Some(value) => serde_json::from_value(value).unwrap_or_default(),
-        None => AppSettings::default(),
-    };
-    Ok(settings)
+async fn get_outbox(app: AppHandle) -> Vec<outbox::OutboxEntry> {
+    app.state::<outbox::Outbox>().entries()
 }
 
 #[tauri::command]
-async fn save_settings(app: AppHandle, settings: AppSettings) -> Result<(), String> {
-    use tauri_plugin_store::StoreExt;
-    let store = app.store("settings.json").map_err(|e| e.to_string())?;
-    let value = serde_json::to_value(&settings).map_err(|e| e.to_string())?;
-    store.set("app_settings", value);
-    store.save().map_err(|e| e.to_string())?;
-    emit_settings_changed(&app, &settings);
-    Ok(())
+async fn discard_outbox_entry(app: AppHandle, id: u64) {
+    outbox::discard(&app, id);
 }
 
 #[tauri::command]
-async fn show_settings(app: AppHandle) -> Result<(), String> {
-    if let Some(settings) = app.get_webview_window("settings") {
-        settings.show().map_err(|e| e.to_string())?;
-        settings.set_focus().map_err(|e| e.to_string())?;
+async fn resend_failed_prompt(app: AppHandle, id: u64) -> CommandResult<()> {
+    match delivery::take_failed(&app, id) {
+        Some(prompt) => {
+            Ok(submit(&app, &prompt.message, prompt.new_chat, &prompt.destination).await?)
+        }
+        None => Ok(()),
     }
-    Ok(())
 }
 
-/// Validates that a URL is safe to open externally
-/// Only allows http, https, and mailto schemes
-fn validate_external_url(url: &str) -> Result<(), String> {
-    // Check for javascript: URLs (XSS prevention)
-    if url.trim().to_lowercase().starts_with("javascript:") {
-        return Err("JavaScript URLs are not allowed".to_string());
-    }
+#[tauri::command]
+async fn dismiss_failed_prompt(app: AppHandle, id: u64) {
+    delivery::dismiss_failed(&app, id);
+}
 
-    // Check for data: URLs (potential security risk)
-    if url.trim().to_lowercase().starts_with("data:") {
-        return Err("Data URLs are not allowed".to_string());
-    }
+#[tauri::command]
+async fn get_injection_stats(app: AppHandle) -> stats::StatsReport {
+    stats::report(&app)
+}
 
-    // Check for file: URLs (local file access)
-    if url.trim().to_lowercase().starts_with("file:") {
-        return Err("File URLs are not allowed".to_string());
+#[tauri::command]
+async fn reset_injection_stats(app: AppHandle) {
+    stats::reset(&app);
+}
+
+#[tauri::command]
+async fn get_settings(app: AppHandle) -> CommandResult<AppSettings> {
+    let mut settings = settings::load(&app)?;
+    // The login entry may have been removed outside Kimi
+    if let Some(enabled) = startup::is_enabled(&app) {
+        settings.startup.open_at_login = enabled;
     }
+    Ok(settings)
+}
 
-    // Check for other dangerous schemes
-    let dangerous_schemes = ["vbscript:", "mhtml:", "x-javascript:"];
-    let url_lower = url.trim().to_lowercase();
-    for scheme in &dangerous_schemes {
-        if url_lower.starts_with(scheme) {
-            return Err(format!("{} URLs are not allowed", scheme.trim_end_matches(':')));
+#[tauri::command]
+async fn save_settings(app: AppHandle, mut settings: AppSettings) -> CommandResult<()> {
+    let stored = settings::load(&app).unwrap_or_default();
+    let previous = stored.endpoints();
+    settings.keep_runtime_state(&stored);
+    settings.validate()?;
+    startup::apply(&app, &settings.startup)?;
+    settings::store(&app, &settings)?;
+    emit_settings_changed(&app, &settings);
+
+    let endpoints = settings.endpoints();
+    if endpoints != previous {
+        if let Some(main_window) = app.get_webview_window("main") {
+            main_window
+                .navigate(endpoints.chat())
+                .map_err(WrapperError::window(&main_window, "navigate"))?;
         }
     }
+    Ok(())
+}
 
-    // Allow http, https, and mailto
-    let allowed_schemes = ["http://", "https://", "mailto:"];
-    let has_allowed_scheme = allowed_schemes
-        .iter()
-        .any(|scheme| url_lower.starts_with(scheme));
+#[tauri::command]
+async fn test_proxy_connection(
+    app: AppHandle,
+    proxy: ProxySettings,
+) -> CommandResult<ConnectionReport> {
+    use wrappers::config::Timeouts;
 
-    if !has_allowed_scheme {
-        return Err(
-            "URL must use http://, https://, or mailto: scheme".to_string(),
-        );
+    let target = settings::endpoints(&app).chat();
+    let timeout = std::time::Duration::from_millis(Timeouts::PROXY_TEST);
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        proxy::test_connection(&proxy, &target, timeout)
+    })
+    .await
+    .map_err(|e| WrapperError::SystemError {
+        operation: "test the connection".to_string(),
+        message: e.to_string(),
+    })??;
+    Ok(report)
+}
+
+#[tauri::command]
+async fn choose_download_directory(app: AppHandle) -> CommandResult<Option<String>> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let mut dialog = app.dialog().file().set_title("Choose download folder");
+    if let Ok(dir) = downloads::directory(&app) {
+        dialog = dialog.set_directory(dir);
     }
+    let Some(folder) = dialog.blocking_pick_folder() else {
+        return Ok(None);
+    };
+    let path = folder.into_path().map_err(|e| WrapperError::SystemError {
+        operation: "read the chosen folder".to_string(),
+        message: e.to_string(),
+    })?;
+    Ok(Some(path.to_string_lossy().into_owned()))
+}
 
-    Ok(())
+#[tauri::command]
+fn quit_app(app: AppHandle) {
+    app.exit(0);
 }
 
 #[tauri::command]
-async fn open_external_link(url: String) -> Result<(), String> {
-    // Validate URL before opening
-    validate_external_url(&url)?;
+async fn adjust_zoom(window: WebviewWindow, action: ZoomAction) -> CommandResult<f64> {
+    Ok(zoom::adjust(&window, action)?)
+}
 
-    tauri_plugin_opener::open_url(&url, None::<&str>).map_err(|e| e.to_string())
+#[tauri::command]
+async fn show_settings(app: AppHandle) -> CommandResult<()> {
+    if let Some(settings) = app.get_webview_window("settings") {
+        settings
+            .show()
+            .map_err(WrapperError::window(&settings, "show"))?;
+        settings
+            .set_focus()
+            .map_err(WrapperError::window(&settings, "focus"))?;
+    }
+    Ok(())
 }
 
-fn setup_tray(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
-    let show_item = MenuItem::with_id(app, "show", "Show Kimi", true, None::<&str>)?;
-    let launcher_item = MenuItem::with_id(app, "launcher", "Quick Ask...", true, None::<&str>)?;
-    let separator1 = PredefinedMenuItem::separator(app)?;
-    let settings_item = MenuItem::with_id(app, "settings", "Settings...", true, None::<&str>)?;
-    let separator2 = PredefinedMenuItem::separator(app)?;
-    let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-
-    let menu = Menu::with_items(
-        app,
-        &[
-            &show_item,
-            &launcher_item,
-            &separator1,
-            &settings_item,
-            &separator2,
-            &quit_item,
-        ],
-    )?;
-
-    let _tray = TrayIconBuilder::new()
-        .icon(app.default_window_icon().unwrap().clone())
-        .menu(&menu)
-        .show_menu_on_left_click(false)
-        .on_menu_event(|app, event| match event.id.as_ref() {
-            "show" => {
-                if let Some(window) = app.get_webview_window("main") {
-                    let _ = window.show();
-                    let _ = window.set_focus();
-                }
-            }
-            "launcher" => {
-                if let Some(launcher) = app.get_webview_window("launcher") {
-                    let _ = launcher.center();
-                    let _ = launcher.show();
-                    let _ = launcher.set_focus();
-                }
-            }
-            "settings" => {
-                if let Some(settings) = app.get_webview_window("settings") {
-                    let _ = settings.show();
-                    let _ = settings.set_focus();
-                }
-            }
-            "quit" => {
-                app.exit(0);
-            }
-            _ => {}
-        })
-        .on_tray_icon_event(|tray, event| {
-            if let TrayIconEvent::Click {
-                button: MouseButton::Left,
-                button_state: MouseButtonState::Up,
-                ..
-            } = event
-            {
-                let app = tray.app_handle();
-                if let Some(window) = app.get_webview_window("main") {
-                    let _ = window.show();
-                    let _ = window.set_focus();
-                }
-            }
-        })
-        .build(app)?;
+#[tauri::command]
+async fn open_external_link(app: AppHandle, url: String) -> CommandResult<()> {
+    let settings = settings::load(&app).unwrap_or_default();
+    let routing = &settings.link_routing;
 
-    Ok(())
+    // Validate URL before opening
+    let validated = validate_external_url(&url, routing.strip_tracking_params)?;
+
+    // `Ask` is confirmed in Rust by `links::dispatch`, never by the caller
+    let action = links::route(&validated.url, &settings.endpoints(), routing);
+    if let (Some(warning), LinkAction::Browser | LinkAction::AppWindow) =
+        (&validated.warning, action)
+    {
+        // Only the dialog's own callback opens the link after this
+        links::confirm_open(&app, &validated.url, warning.clone());
+        return Ok(());
+    }
+    Ok(links::dispatch(&app, validated.url, action)?)
+}
+
+/// Create the main window from its config entry, applying the proxy settings
+/// and configured Kimi origin
+///
+/// The window is marked `"create": false` in `tauri.conf.json` because proxy
+/// options can only be set before its webview exists.
+fn create_main_window(app: &AppHandle) -> Result<WebviewWindow, Box<dyn std::error::Error>> {
+    let mut config = app
+        .config()
+        .app
+        .windows
+        .iter()
+        .find(|w| w.label == "main")
+        .ok_or("main window config missing")?
+        .clone();
+    let settings = settings::load(app).unwrap_or_default();
+    let chat_url = settings.endpoints().chat();
+    config.url = tauri::WebviewUrl::External(chat_url.clone());
+
+    let builder = WebviewWindowBuilder::from_config(app, &config)?.on_download(downloads::handle);
+    let window = proxy::configure(app, builder, &settings.proxy, &chat_url).build()?;
+    Ok(window)
 }
 
 fn setup_global_shortcut(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
@@ -303,6 +402,30 @@ fn setup_global_shortcut(app: &AppHandle) -> Result<(), Box<dyn std::error::Erro
     Ok(())
 }
 
+/// Handle `kimi://` links opened while running and the one Kimi was started with
+fn setup_deep_links(app: &AppHandle) {
+    use tauri_plugin_deep_link::DeepLinkExt;
+
+    // Installed builds register the scheme when bundled; this covers
+    // development builds and AppImages on Linux and Windows
+    #[cfg(any(target_os = "linux", windows))]
+    if let Err(e) = app.deep_link().register_all() {
+        eprintln!("Failed to register kimi:// links: {}", e);
+    }
+
+    let app_handle = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        for url in event.urls() {
+            deep_link::handle(&app_handle, url.as_str());
+        }
+    });
+
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(link) = deep_link::from_args(&args) {
+        deep_link::handle(app, link);
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -311,27 +434,103 @@ pub fn run() {
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_store::Builder::default().build())
-        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(
+            tauri_plugin_autostart::Builder::new()
+                .arg(startup::AUTOSTART_ARG)
+                .build(),
+        )
+        .plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
+            if let Some(link) = deep_link::from_args(&args) {
+                deep_link::handle(app, link);
+                return;
+            }
             if let Some(main_window) = app.get_webview_window("main") {
                 let _ = main_window.show();
                 let _ = main_window.set_focus();
             }
         }))
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .invoke_handler(tauri::generate_handler![
             hide_launcher,
             show_launcher,
             toggle_launcher,
             show_main_window,
             submit_message,
+            get_destinations,
             navigate_to_chat,
             navigate_to_offline,
+            check_connectivity,
             get_settings,
             save_settings,
             show_settings,
             open_external_link,
+            test_proxy_connection,
+            choose_download_directory,
+            adjust_zoom,
+            quit_app,
+            get_outbox,
+            discard_outbox_entry,
+            resend_failed_prompt,
+            dismiss_failed_prompt,
+            get_injection_stats,
+            reset_injection_stats,
         ])
+        .on_page_load(|webview, payload| {
+            if webview.label() != "main" {
+                // The quit keys the close hint mentions work in every local page
+                let local = matches!(webview.label(), "launcher" | "settings");
+                if local && payload.event() == PageLoadEvent::Finished {
+                    if let Some(window) = webview.app_handle().get_webview_window(webview.label()) {
+                        let _ = window.inject_quit_shortcut();
+                    }
+                }
+                return;
+            }
+            let url = payload.url();
+            let is_kimi_page = matches!(url.scheme(), "http" | "https")
+                && url.host_str() != Some("tauri.localhost");
+            if payload.event() == PageLoadEvent::Started {
+                // Navigating away stops the response watcher
+                app_state::update(webview.app_handle(), |state| state.generating = false);
+                return;
+            }
+            app_state::update(webview.app_handle(), |state| {
+                state.online = is_kimi_page;
+                if is_kimi_page {
+                    state.set_page(url.as_str(), None);
+                }
+            });
+            if let Some(main_window) = webview.app_handle().get_webview_window("main") {
+                let settings = settings::load(webview.app_handle()).unwrap_or_default();
+                apply_all_wrappers(&main_window, &settings);
+                let _ = zoom::apply_saved(&main_window);
+                let _ = main_window.inject_zoom_shortcuts();
+                let _ = main_window.inject_quit_shortcut();
+            }
+            if is_kimi_page {
+                delivery::restore_toast(webview.app_handle());
+                outbox::deliver_pending(webview.app_handle());
+            }
+        })
         .setup(|app| {
-            if let Err(e) = setup_tray(app.handle()) {
+            app.manage(std::sync::Mutex::new(app_state::AppState::default()));
+            app.manage(downloads::Downloads::default());
+            app.manage(std::sync::Mutex::new(history::History::default()));
+            app.manage(outbox::load(app.handle()));
+            app.manage(connectivity::ConnectivityMonitor::default());
+            app.manage(delivery::Delivery::default());
+            app.manage(stats::load(app.handle()));
+
+            if let Err(e) = create_main_window(app.handle()) {
+                eprintln!("Failed to create main window: {}", e);
+            }
+            let startup_settings = settings::load(app.handle()).unwrap_or_default().startup;
+            startup::show_main_window(app.handle(), &startup_settings);
+            startup::refresh(app.handle());
+
+            if let Err(e) = tray::setup(app.handle()) {
                 eprintln!("Failed to setup tray: {}", e);
             }
 
@@ -339,54 +538,57 @@ pub fn run() {
                 eprintln!("Failed to setup global shortcut: {}", e);
             }
 
+            setup_deep_links(app.handle());
+            connectivity::spawn(app.handle());
+
             if let Some(main_window) = app.get_webview_window("main") {
                 let app_handle = app.handle().clone();
-                main_window.on_window_event(move |event| {
-                    if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-                        api.prevent_close();
-                        if let Some(window) = app_handle.get_webview_window("main") {
-                            let _ = window.hide();
-                        }
+                main_window.on_window_event(move |event| match event {
+                    tauri::WindowEvent::CloseRequested { api, .. } => {
+                        close_behavior::close_requested(&app_handle, api);
                     }
+                    tauri::WindowEvent::Focused(true) => {
+                        app_state::update(&app_handle, |state| state.unread = false);
+                    }
+                    _ => {}
                 });
             }
 
             {
                 let app_handle = app.handle().clone();
-                app.listen("response-complete", move |_event| {
-                    let notifications_enabled = {
-                        use tauri_plugin_store::StoreExt;
-                        app_handle
-                            .store("settings.json")
-                            .ok()
-                            .and_then(|store| store.get("app_settings"))
-                            .and_then(|v| serde_json::from_value::<AppSettings>(v).ok())
-                            .map(|s| s.notifications_enabled)
-                            .unwrap_or(true)
-                    };
-
-                    if !notifications_enabled {
-                        return;
-                    }
-
-                    let is_focused = app_handle
+                app.listen("response-complete", move |event| {
+                    let payload: notifications::PageInfo =
+                        serde_json::from_str(event.payload()).unwrap_or_default();
+                    let focused = app_handle
                         .get_webview_window("main")
                         .and_then(|w| w.is_focused().ok())
                         .unwrap_or(false);
-
-                    if !is_focused {
-                        use tauri_plugin_notification::NotificationExt;
-                        let _ = app_handle
-                            .notification()
-                            .builder()
-                            .title("Kimi")
-                            .body("Response ready")
-                            .show();
-
-                        if let Some(window) = app_handle.get_webview_window("main") {
-                            let _ = window.show();
+                    app_state::update(&app_handle, |state| {
+                        state.generating = false;
+                        state.unread = !focused;
+                        if !payload.url.is_empty() {
+                            state.set_page(
+                                &payload.url,
+                                notifications::conversation_title(&payload.title),
+                            );
                         }
-                    }
+                    });
+                    notifications::response_complete(&app_handle, payload);
+                });
+            }
+
+            {
+                let app_handle = app.handle().clone();
+                app.listen("conversation-changed", move |event| {
+                    let payload = serde_json::from_str(event.payload()).unwrap_or_default();
+                    history::conversation_changed(&app_handle, payload);
+                });
+            }
+
+            {
+                let app_handle = app.handle().clone();
+                app.listen("response-watch-ended", move |_event| {
+                    app_state::update(&app_handle, |state| state.generating = false);
                 });
             }
 
@@ -425,14 +627,10 @@ pub fn run() {
                 }
             }
 
+            #[cfg(target_os = "macos")]
             if let Some(main_window) = app.get_webview_window("main") {
-                apply_all_wrappers(&main_window);
-                
-                #[cfg(target_os = "macos")]
-                {
-                    let _ = main_window.set_title_bar_style(TitleBarStyle::Overlay);
-                    let _ = main_window.inject_titlebar_styles();
-                }
+                let _ = main_window.set_title_bar_style(TitleBarStyle::Overlay);
+                let _ = main_window.inject_titlebar_styles();
             }
 
             Ok(())
@@ -453,8 +651,8 @@ pub fn run() {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use wrappers::{escape_js, build_js};
     use wrappers::config::{Selectors, Timeouts, Urls};
+    use wrappers::{build_js, js_json_literal};
 
     #[test]
     fn test_app_settings_default() {
@@ -471,6 +669,7 @@ mod tests {
         let settings = AppSettings {
             new_chat_default: false,
             notifications_enabled: true,
+            ..Default::default()
         };
         let json = serde_json::to_value(&settings).unwrap();
         let deserialized: AppSettings = serde_json::from_value(json).unwrap();
@@ -489,53 +688,57 @@ mod tests {
     }
 
     #[test]
-    fn test_escape_js_escapes_backticks() {
-        let escaped = escape_js("code `inline` here");
-        assert!(escaped.contains(r"\`inline\`"));
-        assert!(!escaped.contains("code `inline` here"));
-    }
-
-    #[test]
-    fn test_escape_js_escapes_backslashes() {
-        let escaped = escape_js(r"path\to\file");
-        assert!(escaped.contains(r"path\\to\\file"));
+    fn test_unparsable_stored_settings_are_an_error() {
+        assert!(settings::parse(None).is_ok());
+        let stored = serde_json::to_value(AppSettings::default()).unwrap();
+        assert!(settings::parse(Some(stored.clone())).is_ok());
+
+        let mut broken = stored;
+        broken["new_chat_default"] = serde_json::json!("yes");
+        let err = settings::parse(Some(broken)).unwrap_err();
+        assert!(
+            matches!(err, WrapperError::StoreError { ref operation, .. } if operation == "read")
+        );
     }
 
     #[test]
-    fn test_escape_js_escapes_dollar_signs() {
-        let escaped = escape_js("cost is $100");
-        assert!(escaped.contains(r"cost is \$100"));
+    fn test_js_json_literal_quotes_json() {
+        let literal = js_json_literal(&serde_json::json!({ "message": "say \"hi\"" })).unwrap();
+        assert_eq!(literal, r#""{\"message\":\"say \\\"hi\\\"\"}""#);
     }
 
     #[test]
-    fn test_escape_js_escapes_newlines() {
-        let escaped = escape_js("line1\nline2\rline3");
-        assert!(escaped.contains(r"line1\nline2\rline3"));
+    fn test_js_json_literal_escapes_script_end_and_line_separators() {
+        let literal = js_json_literal("</script>\u{2028}\u{2029}").unwrap();
+        assert_eq!(literal, r#""\"\u003c/script>\u2028\u2029\"""#);
     }
 
     #[test]
-    fn test_escape_js_handles_empty_string() {
-        let escaped = escape_js("");
-        assert_eq!(escaped, "");
+    fn test_js_json_literal_handles_empty_string() {
+        assert_eq!(js_json_literal("").unwrap(), r#""\"\"""#);
     }
 
     #[test]
     fn test_build_js_replaces_placeholders() {
         let template = "Hello {{name}}, you are {{age}} years old";
-        let result = build_js(template, &[("name", "Alice"), ("age", "30")]);
+        let result = build_js("test", template, &[("name", "Alice"), ("age", "30")]).unwrap();
         assert_eq!(result, "Hello Alice, you are 30 years old");
     }
 
     #[test]
-    fn test_build_js_handles_missing_placeholders() {
+    fn test_build_js_rejects_missing_placeholders() {
         let template = "Hello {{name}}";
-        let result = build_js(template, &[]);
-        assert_eq!(result, "Hello {{name}}");
+        assert!(build_js("test", template, &[]).is_err());
     }
 
     #[test]
     fn test_inject_message_js_simple() {
-        let js = build_js(wrappers::INJECT_MESSAGE_JS, &[("message", "Hello world")]);
+        use wrappers::params::TemplateParams;
+
+        let timing = wrappers::timing::TimingSettings::default();
+        let js = wrappers::params::InjectMessage::new("Hello world", &timing)
+            .render()
+            .unwrap();
         assert!(js.contains("Hello world"));
         assert!(js.contains("emitResult"));
         assert!(js.contains("findTextarea"));
@@ -551,13 +754,22 @@ mod tests {
     #[test]
     fn test_titlebar_overlap_js_is_valid() {
         assert!(!wrappers::TITLEBAR_OVERLAP_JS.is_empty());
-        assert!(wrappers::TITLEBAR_OVERLAP_JS.contains("{{style_id}}"));
+        assert!(wrappers::TITLEBAR_OVERLAP_JS.contains("JSON.parse({{params}})"));
+        assert!(wrappers::TITLEBAR_OVERLAP_JS.contains("params.style_id"));
     }
 
     #[test]
     fn test_connectivity_js_is_valid() {
         assert!(!wrappers::CONNECTIVITY_JS.is_empty());
-        assert!(wrappers::CONNECTIVITY_JS.contains("serviceWorker"));
+        assert!(wrappers::CONNECTIVITY_JS.contains("check_connectivity"));
+    }
+
+    #[test]
+    fn test_delivery_toast_js_offers_resend() {
+        assert!(wrappers::DELIVERY_TOAST_JS.contains("JSON.parse({{params}})"));
+        assert!(wrappers::DELIVERY_TOAST_JS.contains("params.status"));
+        assert!(wrappers::DELIVERY_TOAST_JS.contains("resend_failed_prompt"));
+        assert!(wrappers::DELIVERY_TOAST_JS.contains("dismiss_failed_prompt"));
     }
 
     #[test]
@@ -569,7 +781,10 @@ mod tests {
     #[test]
     fn test_config_selectors() {
         assert_eq!(Selectors::CHAT_INPUT, ".chat-input-editor");
-        assert_eq!(Selectors::SEND_BUTTON, ".send-button-container:not(.disabled)");
+        assert_eq!(
+            Selectors::SEND_BUTTON,
+            ".send-button-container:not(.disabled)"
+        );
     }
 
     #[test]
@@ -580,61 +795,11 @@ mod tests {
 
     #[test]
     fn test_config_urls() {
-        assert_eq!(Urls::CHAT, "https://www.kimi.com/");
-        assert!(Urls::CHAT.starts_with("https://"));
-    }
-
-    #[test]
-    fn test_validate_external_url_accepts_https() {
-        assert!(validate_external_url("https://example.com").is_ok());
-    }
-
-    #[test]
-    fn test_validate_external_url_accepts_http() {
-        assert!(validate_external_url("http://example.com").is_ok());
-    }
-
-    #[test]
-    fn test_validate_external_url_accepts_mailto() {
-        assert!(validate_external_url("mailto:test@example.com").is_ok());
-    }
-
-    #[test]
-    fn test_validate_external_url_rejects_javascript() {
-        let result = validate_external_url("javascript:alert('xss')");
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("JavaScript"));
-    }
-
-    #[test]
-    fn test_validate_external_url_rejects_data_url() {
-        let result = validate_external_url("data:text/html,<script>alert('xss')</script>");
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Data URLs"));
-    }
-
-    #[test]
-    fn test_validate_external_url_rejects_file_url() {
-        let result = validate_external_url("file:///etc/passwd");
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("File URLs"));
-    }
-
-    #[test]
-    fn test_validate_external_url_rejects_vbscript() {
-        let result = validate_external_url("vbscript:msgbox('test')");
-        assert!(result.is_err());
-    }
-
-    #[test]
-    fn test_validate_external_url_rejects_relative_url() {
-        let result = validate_external_url("/path/to/page");
-        assert!(result.is_err());
-    }
-
-    #[test]
-    fn test_validate_external_url_case_insensitive() {
-        assert!(validate_external_url("HTTPS://EXAMPLE.COM").is_ok());
-        assert!(validate_external_url("JAVASCRIPT:alert(1)").is_err());
+        assert_eq!(Urls::DEFAULT_ORIGIN, "https://www.kimi.com");
+        assert_eq!(
+            wrappers::Endpoints::default().chat().as_str(),
+            "https://www.kimi.com/"
+        );
+        assert!(Urls::DEFAULT_ORIGIN.starts_with("https://"));
     }
 }
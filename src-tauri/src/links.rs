@@ -0,0 +1,394 @@
+//! Rule-based routing for links leaving the Kimi page
+//!
+//! Both intercepted clicks and `open_external_link` go through `route`, which
+//! evaluates user rules first, then the first-party domains of the configured
+//! origin, then the default action.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use tauri::{AppHandle, Manager, Url, WebviewUrl, WebviewWindowBuilder};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::proxy;
+use crate::settings;
+use crate::wrappers::{Endpoints, WrapperError, WrapperResult};
+
+/// What to do with a link
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkAction {
+    /// Navigate the main window
+    InApp,
+    /// Open in the default browser
+    Browser,
+    /// Open in a separate app window
+    AppWindow,
+    /// Refuse to open
+    Block,
+    /// Ask before opening in the browser
+    Ask,
+}
+
+/// A user rule: `pattern` is `host[/path]` or `mailto:`
+///
+/// A host matches itself and its subdomains (`example.com` matches
+/// `docs.example.com` but not `example.com.evil.io`); a leading `*.` restricts
+/// the match to subdomains. An optional path matches whole path segments.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LinkRule {
+    pub pattern: String,
+    pub action: LinkAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct LinkRoutingSettings {
+    pub rules: Vec<LinkRule>,
+    /// Action for links matching no rule and no first-party domain
+    pub default_action: LinkAction,
+    /// Remove `utm_*` and similar tracking parameters before opening
+    pub strip_tracking_params: bool,
+}
+
+impl Default for LinkRoutingSettings {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            default_action: LinkAction::Browser,
+            strip_tracking_params: false,
+        }
+    }
+}
+
+impl LinkRoutingSettings {
+    /// Validate the rule patterns before they are saved
+    pub fn validate(&self) -> WrapperResult<()> {
+        for rule in &self.rules {
+            let pattern = rule.pattern.trim();
+            let host = pattern.split('/').next().unwrap_or_default();
+            if pattern == "mailto:" {
+                continue;
+            }
+            if host.trim_start_matches("*.").is_empty()
+                || pattern.chars().any(char::is_whitespace)
+                || host.contains(':')
+            {
+                return Err(WrapperError::InvalidSettings {
+                    field: "link_routing.rules".to_string(),
+                    message: format!("invalid link pattern '{}'", rule.pattern),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Hosts that user rules refer to; the injected interceptor routes clicks
+    /// on these through Rust even when they are first-party
+    pub fn rule_hosts(&self) -> Vec<String> {
+        self.rules
+            .iter()
+            .map(|rule| rule.pattern.trim())
+            .filter(|pattern| *pattern != "mailto:")
+            .filter_map(|pattern| pattern.split('/').next())
+            .map(|host| ascii_host(host.trim_start_matches("*.")))
+            .collect()
+    }
+}
+
+/// `host` as the page and `Url` report it: lowercase, and punycode for IDNs
+fn ascii_host(host: &str) -> String {
+    let host = host.trim();
+    idna::domain_to_ascii(host).unwrap_or_else(|_| host.to_ascii_lowercase())
+}
+
+/// Whether `host` is `domain` or one of its subdomains
+pub fn host_matches(host: &str, domain: &str) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+impl LinkRule {
+    pub fn matches(&self, url: &Url) -> bool {
+        let pattern = self.pattern.trim().to_ascii_lowercase();
+        if pattern == "mailto:" {
+            return url.scheme() == "mailto";
+        }
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+
+        let (host_pattern, path_prefix) = match pattern.find('/') {
+            Some(i) => (&pattern[..i], Some(pattern[i..].trim_end_matches('/'))),
+            None => (pattern.as_str(), None),
+        };
+        let host_ok = match host_pattern.strip_prefix("*.") {
+            Some(domain) => {
+                let domain = ascii_host(domain);
+                host_matches(host, &domain) && !host_matches(&domain, host)
+            }
+            None => host_matches(host, &ascii_host(host_pattern)),
+        };
+        let path_ok = match path_prefix {
+            None | Some("") => true,
+            Some(prefix) => {
+                let path = url.path().to_ascii_lowercase();
+                path == prefix || path.starts_with(&format!("{}/", prefix))
+            }
+        };
+        host_ok && path_ok
+    }
+}
+
+/// Decide what to do with `url`
+pub fn route(url: &Url, endpoints: &Endpoints, settings: &LinkRoutingSettings) -> LinkAction {
+    if let Some(rule) = settings.rules.iter().find(|rule| rule.matches(url)) {
+        return rule.action;
+    }
+    if url.scheme() == "mailto" {
+        return LinkAction::Browser;
+    }
+    let first_party = url.host_str().is_some_and(|host| {
+        endpoints
+            .first_party_domains()
+            .iter()
+            .any(|domain| host_matches(host, domain))
+    });
+    if first_party {
+        LinkAction::InApp
+    } else {
+        settings.default_action
+    }
+}
+
+static APP_WINDOW_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Carry out a routing decision
+pub fn dispatch(app: &AppHandle, url: Url, action: LinkAction) -> WrapperResult<()> {
+    match action {
+        LinkAction::InApp => {
+            let window =
+                app.get_webview_window("main")
+                    .ok_or_else(|| WrapperError::WindowNotFound {
+                        window_label: "main".to_string(),
+                    })?;
+            window.navigate(url)?;
+            Ok(())
+        }
+        LinkAction::Browser => open_in_browser(&url),
+        LinkAction::AppWindow => {
+            let label = format!(
+                "link-{}",
+                APP_WINDOW_COUNTER.fetch_add(1, Ordering::Relaxed)
+            );
+            let title = url.host_str().unwrap_or("Kimi").to_string();
+            let proxy_settings = settings::load(app).unwrap_or_default().proxy;
+            let builder = WebviewWindowBuilder::new(app, label, WebviewUrl::External(url.clone()))
+                .title(title)
+                .inner_size(1000.0, 700.0);
+            proxy::configure(app, builder, &proxy_settings, &url).build()?;
+            Ok(())
+        }
+        LinkAction::Block => Err(WrapperError::InvalidUrl {
+            url: url.to_string(),
+            reason: "blocked by link rule".to_string(),
+        }),
+        LinkAction::Ask => {
+            confirm_open(
+                app,
+                &url,
+                format!("Open this link in your browser?\n\n{}", url),
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Ask the user before opening `url` in the browser
+pub fn confirm_open(app: &AppHandle, url: &Url, message: String) {
+    let url = url.clone();
+    app.dialog()
+        .message(message)
+        .title("Open link")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Open".to_string(),
+            "Cancel".to_string(),
+        ))
+        .show(move |confirmed| {
+            if confirmed {
+                if let Err(e) = open_in_browser(&url) {
+                    eprintln!("Failed to open link: {}", e);
+                }
+            }
+        });
+}
+
+fn open_in_browser(url: &Url) -> WrapperResult<()> {
+    tauri_plugin_opener::open_url(url.as_str(), None::<&str>).map_err(|e| {
+        WrapperError::InvalidUrl {
+            url: url.to_string(),
+            reason: e.to_string(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, action: LinkAction) -> LinkRule {
+        LinkRule {
+            pattern: pattern.to_string(),
+            action,
+        }
+    }
+
+    fn route_str(url: &str, settings: &LinkRoutingSettings) -> LinkAction {
+        route(&Url::parse(url).unwrap(), &Endpoints::default(), settings)
+    }
+
+    #[test]
+    fn test_host_matches_on_label_boundary() {
+        assert!(host_matches("kimi.com", "kimi.com"));
+        assert!(host_matches("www.kimi.com", "kimi.com"));
+        assert!(host_matches("WWW.Kimi.com.", "kimi.com"));
+        assert!(!host_matches("kimi.com.evil.io", "kimi.com"));
+        assert!(!host_matches("notkimi.com", "kimi.com"));
+    }
+
+    #[test]
+    fn test_route_first_party_stays_in_app() {
+        let settings = LinkRoutingSettings::default();
+        assert_eq!(
+            route_str("https://www.kimi.com/chat/1", &settings),
+            LinkAction::InApp
+        );
+        assert_eq!(
+            route_str("https://statics.moonshot.cn/a.png", &settings),
+            LinkAction::InApp
+        );
+    }
+
+    #[test]
+    fn test_route_lookalike_domain_is_external() {
+        let settings = LinkRoutingSettings::default();
+        assert_eq!(
+            route_str("https://kimi.com.evil.io/", &settings),
+            LinkAction::Browser
+        );
+        assert_eq!(
+            route_str("https://evil.io/?q=kimi.com", &settings),
+            LinkAction::Browser
+        );
+    }
+
+    #[test]
+    fn test_route_default_action_and_mailto() {
+        let settings = LinkRoutingSettings {
+            rules: vec![],
+            default_action: LinkAction::Ask,
+            ..Default::default()
+        };
+        assert_eq!(
+            route_str("https://example.com/", &settings),
+            LinkAction::Ask
+        );
+        assert_eq!(
+            route_str("mailto:a@example.com", &settings),
+            LinkAction::Browser
+        );
+    }
+
+    #[test]
+    fn test_route_user_rules_take_precedence() {
+        let settings = LinkRoutingSettings {
+            rules: vec![
+                rule("kimi.com/share", LinkAction::Browser),
+                rule("*.example.com", LinkAction::AppWindow),
+                rule("tracker.io", LinkAction::Block),
+                rule("mailto:", LinkAction::Block),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            route_str("https://www.kimi.com/share/abc", &settings),
+            LinkAction::Browser
+        );
+        assert_eq!(
+            route_str("https://www.kimi.com/sharex", &settings),
+            LinkAction::InApp
+        );
+        assert_eq!(
+            route_str("https://docs.example.com/", &settings),
+            LinkAction::AppWindow
+        );
+        assert_eq!(
+            route_str("https://example.com/", &settings),
+            LinkAction::Browser
+        );
+        assert_eq!(
+            route_str("http://ads.tracker.io/x", &settings),
+            LinkAction::Block
+        );
+        assert_eq!(
+            route_str("mailto:a@example.com", &settings),
+            LinkAction::Block
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_patterns() {
+        for pattern in ["", "*.", "exa mple.com", "https://example.com"] {
+            let settings = LinkRoutingSettings {
+                rules: vec![rule(pattern, LinkAction::Block)],
+                ..Default::default()
+            };
+            assert!(
+                settings.validate().is_err(),
+                "{:?} should be rejected",
+                pattern
+            );
+        }
+        let settings = LinkRoutingSettings {
+            rules: vec![
+                rule("example.com/docs", LinkAction::Block),
+                rule("mailto:", LinkAction::Ask),
+            ],
+            ..Default::default()
+        };
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn test_rule_hosts() {
+        let settings = LinkRoutingSettings {
+            rules: vec![
+                rule("kimi.com/share", LinkAction::Browser),
+                rule("*.Example.com", LinkAction::AppWindow),
+                rule("mailto:", LinkAction::Block),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(settings.rule_hosts(), vec!["kimi.com", "example.com"]);
+    }
+
+    #[test]
+    fn test_unicode_rule_hosts_are_punycode() {
+        let settings = LinkRoutingSettings {
+            rules: vec![
+                rule(" bücher.de/shop", LinkAction::Browser),
+                rule("*.Bücher.example ", LinkAction::AppWindow),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.rule_hosts(),
+            vec!["xn--bcher-kva.de", "xn--bcher-kva.example"]
+        );
+        // `Url` reports the host in punycode too
+        let url = Url::parse("https://bücher.de/shop/item").unwrap();
+        assert_eq!(url.host_str(), Some("xn--bcher-kva.de"));
+        assert!(settings.rules[0].matches(&url));
+    }
+}
@@ -0,0 +1,316 @@
+//! When notifications may be shown: quiet hours, snooze and window state
+//!
+//! Everything here is pure so the schedule logic can be tested with fixed
+//! clocks; `notifications` applies the policy before building a notification.
+
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Timelike, Weekday};
+
+use crate::wrappers::{WrapperError, WrapperResult};
+
+/// Which main window states allow a response notification
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyWhen {
+    /// The main window is hidden, minimized or not focused
+    #[default]
+    Unfocused,
+    /// The main window is hidden or minimized
+    Hidden,
+    /// Regardless of the main window
+    Always,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Day {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl From<Weekday> for Day {
+    fn from(day: Weekday) -> Self {
+        match day {
+            Weekday::Mon => Day::Mon,
+            Weekday::Tue => Day::Tue,
+            Weekday::Wed => Day::Wed,
+            Weekday::Thu => Day::Thu,
+            Weekday::Fri => Day::Fri,
+            Weekday::Sat => Day::Sat,
+            Weekday::Sun => Day::Sun,
+        }
+    }
+}
+
+/// A recurring quiet period such as 22:00-07:00 on weekdays
+///
+/// A period whose end is before its start runs past midnight and belongs to
+/// the day it starts on. Equal start and end mean the whole day.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct QuietHours {
+    /// Local start time, `HH:MM`
+    pub start: String,
+    /// Local end time, `HH:MM`, exclusive
+    pub end: String,
+    /// Days the period starts on; every day when empty
+    #[serde(default)]
+    pub days: Vec<Day>,
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+
+fn minutes(time: NaiveTime) -> u32 {
+    time.hour() * 60 + time.minute()
+}
+
+impl QuietHours {
+    fn starts_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&Day::from(day))
+    }
+
+    /// Whether a local date and time falls inside this period
+    pub fn contains(&self, weekday: Weekday, time: NaiveTime) -> bool {
+        let (Some(start), Some(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+            return false;
+        };
+        let (start, end, now) = (minutes(start), minutes(end), minutes(time));
+        if start == end {
+            self.starts_on(weekday)
+        } else if start < end {
+            self.starts_on(weekday) && now >= start && now < end
+        } else {
+            (self.starts_on(weekday) && now >= start)
+                || (self.starts_on(weekday.pred()) && now < end)
+        }
+    }
+}
+
+/// Visibility of the main window when a notification is considered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowState {
+    pub visible: bool,
+    pub minimized: bool,
+    pub focused: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct NotificationPolicy {
+    pub notify_when: NotifyWhen,
+    pub quiet_hours: Vec<QuietHours>,
+    /// Unix time in seconds until which notifications are muted
+    pub snoozed_until: Option<i64>,
+}
+
+impl NotificationPolicy {
+    pub fn validate(&self) -> WrapperResult<()> {
+        for period in &self.quiet_hours {
+            if parse_time(&period.start).is_none() || parse_time(&period.end).is_none() {
+                return Err(WrapperError::InvalidSettings {
+                    field: "notification_policy.quiet_hours".to_string(),
+                    message: format!(
+                        "invalid quiet hours '{}-{}', expected HH:MM-HH:MM",
+                        period.start, period.end
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_snoozed<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> bool {
+        self.snoozed_until
+            .is_some_and(|until| now.timestamp() < until)
+    }
+
+    /// Whether any notification should be held back at `now`
+    pub fn is_quiet<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> bool {
+        let local = now.naive_local();
+        self.is_snoozed(now)
+            || self
+                .quiet_hours
+                .iter()
+                .any(|period| period.contains(local.weekday(), local.time()))
+    }
+
+    /// Whether a response notification should be shown at `now`
+    pub fn allows<Tz: TimeZone>(&self, now: &DateTime<Tz>, window: WindowState) -> bool {
+        if self.is_quiet(now) {
+            return false;
+        }
+        let hidden = !window.visible || window.minimized;
+        match self.notify_when {
+            NotifyWhen::Always => true,
+            NotifyWhen::Hidden => hidden,
+            NotifyWhen::Unfocused => hidden || !window.focused,
+        }
+    }
+
+    /// Mute notifications for `duration` from `now`
+    pub fn snooze<Tz: TimeZone>(&mut self, now: &DateTime<Tz>, duration: Duration) {
+        self.snoozed_until = Some((now.clone() + duration).timestamp());
+    }
+
+    pub fn resume(&mut self) {
+        self.snoozed_until = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+
+    fn period(start: &str, end: &str, days: &[Day]) -> QuietHours {
+        QuietHours {
+            start: start.to_string(),
+            end: end.to_string(),
+            days: days.to_vec(),
+        }
+    }
+
+    fn at(weekday: Weekday, time: &str) -> (Weekday, NaiveTime) {
+        (weekday, parse_time(time).unwrap())
+    }
+
+    /// 2024-01-01 was a Monday
+    fn local(day: u32, time: &str) -> DateTime<FixedOffset> {
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+        let (h, m) = time.split_once(':').unwrap();
+        offset
+            .with_ymd_and_hms(2024, 1, day, h.parse().unwrap(), m.parse().unwrap(), 0)
+            .unwrap()
+    }
+
+    const BACKGROUND: WindowState = WindowState {
+        visible: false,
+        minimized: false,
+        focused: false,
+    };
+
+    #[test]
+    fn test_same_day_period() {
+        let lunch = period("12:00", "13:30", &[]);
+        let cases = [
+            (at(Weekday::Mon, "11:59"), false),
+            (at(Weekday::Mon, "12:00"), true),
+            (at(Weekday::Sun, "13:29"), true),
+            (at(Weekday::Mon, "13:30"), false),
+        ];
+        for ((day, time), expected) in cases {
+            assert_eq!(lunch.contains(day, time), expected, "{} {}", day, time);
+        }
+    }
+
+    #[test]
+    fn test_period_past_midnight_belongs_to_start_day() {
+        let weeknights = period(
+            "22:00",
+            "07:00",
+            &[Day::Mon, Day::Tue, Day::Wed, Day::Thu, Day::Fri],
+        );
+        let cases = [
+            (at(Weekday::Mon, "21:59"), false),
+            (at(Weekday::Mon, "22:00"), true),
+            (at(Weekday::Tue, "06:59"), true),
+            (at(Weekday::Tue, "07:00"), false),
+            // Friday night continues into Saturday morning
+            (at(Weekday::Sat, "03:00"), true),
+            (at(Weekday::Sat, "22:30"), false),
+            // Sunday night is not included, so Monday morning is not quiet
+            (at(Weekday::Mon, "03:00"), false),
+        ];
+        for ((day, time), expected) in cases {
+            assert_eq!(weeknights.contains(day, time), expected, "{} {}", day, time);
+        }
+    }
+
+    #[test]
+    fn test_equal_start_and_end_is_whole_day() {
+        let weekend = period("00:00", "00:00", &[Day::Sat, Day::Sun]);
+        assert!(weekend.contains(Weekday::Sat, parse_time("00:00").unwrap()));
+        assert!(weekend.contains(Weekday::Sun, parse_time("23:59").unwrap()));
+        assert!(!weekend.contains(Weekday::Mon, parse_time("12:00").unwrap()));
+    }
+
+    #[test]
+    fn test_quiet_hours_use_local_time() {
+        let policy = NotificationPolicy {
+            quiet_hours: vec![period("22:00", "07:00", &[])],
+            ..Default::default()
+        };
+        // 23:00 at UTC+8 is 15:00 UTC
+        assert!(policy.is_quiet(&local(1, "23:00")));
+        assert!(!policy.is_quiet(&local(1, "23:00").with_timezone(&Utc)));
+        assert!(!policy.allows(&local(2, "06:30"), BACKGROUND));
+        assert!(policy.allows(&local(2, "07:00"), BACKGROUND));
+    }
+
+    #[test]
+    fn test_snooze_expires() {
+        let mut policy = NotificationPolicy::default();
+        let now = local(3, "10:00");
+        policy.snooze(&now, Duration::hours(1));
+        assert!(!policy.allows(&local(3, "10:59"), BACKGROUND));
+        assert!(policy.allows(&local(3, "11:00"), BACKGROUND));
+        policy.snooze(&now, Duration::hours(1));
+        policy.resume();
+        assert!(policy.allows(&now, BACKGROUND));
+    }
+
+    #[test]
+    fn test_window_rules() {
+        let now = local(4, "12:00");
+        let unfocused = WindowState {
+            visible: true,
+            minimized: false,
+            focused: false,
+        };
+        let minimized = WindowState {
+            visible: true,
+            minimized: true,
+            focused: false,
+        };
+        let focused = WindowState {
+            visible: true,
+            minimized: false,
+            focused: true,
+        };
+        let cases = [
+            (NotifyWhen::Unfocused, [true, true, true, false]),
+            (NotifyWhen::Hidden, [true, false, true, false]),
+            (NotifyWhen::Always, [true, true, true, true]),
+        ];
+        for (notify_when, expected) in cases {
+            let policy = NotificationPolicy {
+                notify_when,
+                ..Default::default()
+            };
+            let actual =
+                [BACKGROUND, unfocused, minimized, focused].map(|w| policy.allows(&now, w));
+            assert_eq!(actual, expected, "{:?}", notify_when);
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_times() {
+        for (start, end) in [("22:00", "7"), ("25:00", "07:00"), ("", "")] {
+            let policy = NotificationPolicy {
+                quiet_hours: vec![period(start, end, &[])],
+                ..Default::default()
+            };
+            assert!(policy.validate().is_err(), "{}-{}", start, end);
+        }
+        let policy = NotificationPolicy {
+            quiet_hours: vec![period("22:00", "07:00", &[Day::Sat])],
+            ..Default::default()
+        };
+        assert!(policy.validate().is_ok());
+    }
+}
@@ -0,0 +1,251 @@
+//! Desktop notifications that can react to being clicked
+//!
+//! The notification plugin does not report clicks on desktop platforms, so on
+//! Linux and the BSDs notifications are sent over D-Bus with a default action
+//! instead. Elsewhere they fall back to the plugin and clicks are ignored.
+//!
+//! Every notification is subject to the quiet hours and snooze of the
+//! `NotificationPolicy`.
+
+use chrono::{Duration, Local};
+use tauri::{AppHandle, Manager, Url, UserAttentionType};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::links::host_matches;
+use crate::notification_policy::{NotificationPolicy, WindowState};
+use crate::settings;
+use crate::tray;
+use crate::wrappers::Endpoints;
+
+/// Called with the app handle when the user clicks the notification
+pub type OnClick = Box<dyn FnOnce(&AppHandle) + Send>;
+
+/// What happens to the main window when a response finishes in the background
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseWindowAction {
+    /// Bring the window up without focusing it
+    Show,
+    /// Request attention, e.g. flash the taskbar entry
+    #[default]
+    Flash,
+    /// Leave the window alone
+    None,
+}
+
+/// Payload of the `response-complete` and `conversation-changed` events
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct PageInfo {
+    /// `document.title` of the conversation page
+    pub title: String,
+    /// `location.href` of the conversation page
+    pub url: String,
+}
+
+/// Strip the site name Kimi appends to page titles
+pub fn conversation_title(page_title: &str) -> Option<String> {
+    let title = page_title.trim();
+    let title = [" - Kimi", " | Kimi", " – Kimi"]
+        .iter()
+        .find_map(|suffix| title.strip_suffix(suffix))
+        .unwrap_or(title)
+        .trim();
+    (!title.is_empty() && !title.eq_ignore_ascii_case("kimi")).then(|| title.to_string())
+}
+
+/// The conversation URL, if it points at the configured Kimi site
+pub fn conversation_url(raw: &str, endpoints: &Endpoints) -> Option<Url> {
+    let url = Url::parse(raw).ok()?;
+    let host = url.host_str()?;
+    let first_party = matches!(url.scheme(), "http" | "https")
+        && endpoints
+            .first_party_domains()
+            .iter()
+            .any(|domain| host_matches(host, domain));
+    first_party.then_some(url)
+}
+
+/// Announce a finished response unless the main window is focused
+pub fn response_complete(app: &AppHandle, payload: PageInfo) {
+    let settings = settings::load(app).unwrap_or_default();
+    if !settings.notifications_enabled {
+        return;
+    }
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let state = WindowState {
+        visible: window.is_visible().unwrap_or(false),
+        minimized: window.is_minimized().unwrap_or(false),
+        focused: window.is_focused().unwrap_or(false),
+    };
+    if !settings.notification_policy.allows(&Local::now(), state) {
+        return;
+    }
+
+    let title = conversation_title(&payload.title).unwrap_or_else(|| "Kimi".to_string());
+    let target = conversation_url(&payload.url, &settings.endpoints());
+    show(
+        app,
+        &title,
+        "Response ready",
+        Some(Box::new(move |app: &AppHandle| {
+            open_conversation(app, target)
+        })),
+    );
+
+    match settings.response_window {
+        ResponseWindowAction::Show => {
+            let _ = window.show();
+        }
+        ResponseWindowAction::Flash => {
+            let _ = window.request_user_attention(Some(UserAttentionType::Informational));
+        }
+        ResponseWindowAction::None => {}
+    }
+}
+
+/// Focus the main window on a conversation
+pub fn open_conversation(app: &AppHandle, url: Option<Url>) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    if let Some(url) = url {
+        if window.url().ok().as_ref() != Some(&url) {
+            let _ = window.navigate(url);
+        }
+    }
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.set_focus();
+}
+
+/// Mute all notifications for `duration`
+pub fn snooze(app: &AppHandle, duration: Duration) {
+    update_policy(app, |policy| policy.snooze(&Local::now(), duration));
+}
+
+/// End a snooze early
+pub fn resume(app: &AppHandle) {
+    update_policy(app, |policy| policy.resume());
+}
+
+fn update_policy(app: &AppHandle, f: impl FnOnce(&mut NotificationPolicy)) {
+    if let Err(e) = settings::update(app, |s| f(&mut s.notification_policy)) {
+        eprintln!("Failed to update notification policy: {}", e);
+    }
+    if let Err(e) = tray::refresh(app) {
+        eprintln!("Failed to refresh tray menu: {}", e);
+    }
+}
+
+/// Show a notification, running `on_click` if the user clicks it
+///
+/// Nothing is shown while notifications are turned off, during quiet hours
+/// or while notifications are snoozed.
+pub fn show(app: &AppHandle, title: &str, body: &str, on_click: Option<OnClick>) {
+    let settings = settings::load(app).unwrap_or_default();
+    if !settings.notifications_enabled || settings.notification_policy.is_quiet(&Local::now()) {
+        return;
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd"
+    ))]
+    let on_click = match show_xdg(app, title, body, on_click) {
+        Ok(()) => return,
+        Err(e) => {
+            eprintln!("D-Bus notification failed, falling back: {}", e);
+            None::<OnClick>
+        }
+    };
+    // Clicks on plugin notifications are not reported on this platform
+    drop(on_click);
+
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        eprintln!("Failed to show notification: {}", e);
+    }
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd"
+))]
+fn show_xdg(
+    app: &AppHandle,
+    title: &str,
+    body: &str,
+    on_click: Option<OnClick>,
+) -> Result<(), notify_rust::error::Error> {
+    let mut notification = notify_rust::Notification::new();
+    notification.appname("Kimi").summary(title).body(body);
+    if on_click.is_some() {
+        notification.action("default", "Open");
+    }
+    let handle = notification.show()?;
+
+    if let Some(on_click) = on_click {
+        let app = app.clone();
+        std::thread::spawn(move || {
+            handle.wait_for_action(|action| {
+                if action == "default" {
+                    on_click(&app);
+                }
+            });
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversation_title() {
+        assert_eq!(
+            conversation_title("Trip plan - Kimi").as_deref(),
+            Some("Trip plan")
+        );
+        assert_eq!(
+            conversation_title("  Rust help | Kimi ").as_deref(),
+            Some("Rust help")
+        );
+        assert_eq!(
+            conversation_title("Untitled draft").as_deref(),
+            Some("Untitled draft")
+        );
+        assert_eq!(conversation_title("Kimi"), None);
+        assert_eq!(conversation_title(""), None);
+    }
+
+    #[test]
+    fn test_conversation_url_stays_first_party() {
+        let endpoints = Endpoints::default();
+        assert_eq!(
+            conversation_url("https://www.kimi.com/chat/abc", &endpoints).map(String::from),
+            Some("https://www.kimi.com/chat/abc".to_string())
+        );
+        assert!(conversation_url("https://kimi.com.evil.io/chat/abc", &endpoints).is_none());
+        assert!(conversation_url("javascript:alert(1)", &endpoints).is_none());
+        assert!(conversation_url("", &endpoints).is_none());
+    }
+
+    #[test]
+    fn test_response_window_action_serde() {
+        assert_eq!(
+            serde_json::to_string(&ResponseWindowAction::None).unwrap(),
+            "\"none\""
+        );
+        let action: ResponseWindowAction = serde_json::from_str("\"show\"").unwrap();
+        assert_eq!(action, ResponseWindowAction::Show);
+    }
+}
@@ -0,0 +1,283 @@
+//! Launcher prompts submitted while Kimi is offline
+//!
+//! Prompts are kept in their own store file so they survive a restart, and
+//! are delivered one at a time through the normal submit path once a Kimi
+//! page loads again. Each delivery waits for the previous response to finish,
+//! since the page does not accept a new message while generating.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+use crate::app_state::AppState;
+use crate::delivery;
+use crate::settings;
+use crate::tray;
+use crate::wrappers::{
+    config::{Storage, Timeouts},
+    WrapperError, WrapperResult,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct OutboxEntry {
+    pub id: u64,
+    pub message: String,
+    pub new_chat: bool,
+    /// Id of the `Destination` the prompt goes to
+    pub destination: String,
+    /// Unix time in seconds when the prompt was queued
+    pub queued_at: i64,
+}
+
+/// Queued prompts, oldest first
+#[derive(Debug, Default)]
+pub struct Outbox {
+    entries: Mutex<VecDeque<OutboxEntry>>,
+    delivering: AtomicBool,
+}
+
+impl Outbox {
+    fn from_entries(entries: Vec<OutboxEntry>) -> Self {
+        Self {
+            entries: Mutex::new(entries.into()),
+            delivering: AtomicBool::new(false),
+        }
+    }
+
+    /// Append a prompt; fails when the outbox is full
+    pub fn push(
+        &self,
+        message: &str,
+        new_chat: bool,
+        destination: &str,
+        queued_at: i64,
+    ) -> WrapperResult<OutboxEntry> {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= Storage::OUTBOX_MAX_ENTRIES {
+            return Err(WrapperError::StoreError {
+                operation: "queue".to_string(),
+                message: format!(
+                    "the outbox already holds {} prompts",
+                    Storage::OUTBOX_MAX_ENTRIES
+                ),
+            });
+        }
+        let id = entries.iter().map(|e| e.id).max().unwrap_or(0) + 1;
+        let entry = OutboxEntry {
+            id,
+            message: message.to_string(),
+            new_chat,
+            destination: destination.to_string(),
+            queued_at,
+        };
+        entries.push_back(entry.clone());
+        Ok(entry)
+    }
+
+    pub fn remove(&self, id: u64) -> bool {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|e| e.id != id);
+        entries.len() != before
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    pub fn front(&self) -> Option<OutboxEntry> {
+        self.entries.lock().unwrap().front().cloned()
+    }
+
+    pub fn entries(&self) -> Vec<OutboxEntry> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().unwrap().is_empty()
+    }
+}
+
+/// Load queued prompts from the previous session
+pub fn load(app: &AppHandle) -> Outbox {
+    let entries = app
+        .store(Storage::OUTBOX_FILE)
+        .ok()
+        .and_then(|store| store.get(Storage::OUTBOX_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default();
+    Outbox::from_entries(entries)
+}
+
+fn save(app: &AppHandle, outbox: &Outbox) -> WrapperResult<()> {
+    let store = app
+        .store(Storage::OUTBOX_FILE)
+        .map_err(|e| WrapperError::StoreError {
+            operation: "open".to_string(),
+            message: e.to_string(),
+        })?;
+    store.set(Storage::OUTBOX_KEY, serde_json::to_value(outbox.entries())?);
+    store.save().map_err(|e| WrapperError::StoreError {
+        operation: "save".to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// Persist the outbox and tell the offline page and tray about the change
+fn changed(app: &AppHandle, outbox: &Outbox) {
+    if let Err(e) = save(app, outbox) {
+        eprintln!("Failed to save outbox: {}", e);
+    }
+    let _ = app.emit("outbox-changed", outbox.entries());
+    if let Err(e) = tray::refresh(app) {
+        eprintln!("Failed to refresh tray menu: {}", e);
+    }
+}
+
+/// Keep a prompt for delivery once Kimi is reachable
+pub fn queue(
+    app: &AppHandle,
+    message: &str,
+    new_chat: bool,
+    destination: &str,
+) -> WrapperResult<()> {
+    let outbox = app.state::<Outbox>();
+    outbox.push(
+        message,
+        new_chat,
+        destination,
+        chrono::Utc::now().timestamp(),
+    )?;
+    changed(app, &outbox);
+    Ok(())
+}
+
+pub fn discard(app: &AppHandle, id: u64) {
+    let outbox = app.state::<Outbox>();
+    if outbox.remove(id) {
+        changed(app, &outbox);
+    }
+}
+
+pub fn discard_all(app: &AppHandle) {
+    let outbox = app.state::<Outbox>();
+    if !outbox.is_empty() {
+        outbox.clear();
+        changed(app, &outbox);
+    }
+}
+
+/// Start delivering queued prompts in order, unless a delivery is running
+pub fn deliver_pending(app: &AppHandle) {
+    let Some(outbox) = app.try_state::<Outbox>() else {
+        return;
+    };
+    if outbox.is_empty() || outbox.delivering.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        deliver_all(&app).await;
+        app.state::<Outbox>()
+            .delivering
+            .store(false, Ordering::SeqCst);
+    });
+}
+
+async fn deliver_all(app: &AppHandle) {
+    let outbox = app.state::<Outbox>();
+    while let Some(entry) = outbox.front() {
+        if !is_online(app) {
+            return;
+        }
+        match delivery::deliver(app, &entry.message, entry.new_chat, &entry.destination).await {
+            Ok(true) => {
+                outbox.remove(entry.id);
+                changed(app, &outbox);
+                wait_for_response(app).await;
+            }
+            Ok(false) => return,
+            Err(e) => {
+                // The prompt is kept with the failed prompts, resent from the toast
+                eprintln!("Failed to deliver queued prompt: {}", e);
+                outbox.remove(entry.id);
+                changed(app, &outbox);
+                return;
+            }
+        }
+    }
+}
+
+/// Wait until the response watcher reports the response finished or gave up
+async fn wait_for_response(app: &AppHandle) {
+    let timing = settings::load(app).map(|s| s.timing).unwrap_or_default();
+    let limit = Duration::from_millis(timing.response_watch_limit());
+    let started = Instant::now();
+    while is_generating(app) && started.elapsed() < limit {
+        tokio::time::sleep(Duration::from_millis(Timeouts::OUTBOX_POLL_INTERVAL)).await;
+    }
+}
+
+fn is_online(app: &AppHandle) -> bool {
+    app.try_state::<Mutex<AppState>>()
+        .is_some_and(|state| state.lock().unwrap().online)
+}
+
+fn is_generating(app: &AppHandle) -> bool {
+    app.try_state::<Mutex<AppState>>()
+        .is_some_and(|state| state.lock().unwrap().generating)
+}
+
+/// Whether a prompt submitted now has to wait in the outbox, either because
+/// Kimi is offline or because earlier prompts are still queued
+pub fn must_queue(app: &AppHandle) -> bool {
+    !is_online(app)
+        || app
+            .try_state::<Outbox>()
+            .is_some_and(|outbox| !outbox.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_keep_order_and_unique_ids() {
+        let outbox = Outbox::default();
+        let first = outbox.push("first", true, "chat", 1).unwrap();
+        let second = outbox.push("second", false, "chat", 2).unwrap();
+        assert_ne!(first.id, second.id);
+        assert_eq!(outbox.front().unwrap().message, "first");
+
+        assert!(outbox.remove(first.id));
+        assert!(!outbox.remove(first.id));
+        let third = outbox.push("third", true, "claw", 3).unwrap();
+        let messages: Vec<String> = outbox.entries().into_iter().map(|e| e.message).collect();
+        assert_eq!(messages, vec!["second", "third"]);
+        assert!(third.id > second.id);
+    }
+
+    #[test]
+    fn test_outbox_is_bounded() {
+        let outbox = Outbox::default();
+        for i in 0..Storage::OUTBOX_MAX_ENTRIES {
+            outbox.push(&i.to_string(), true, "chat", 0).unwrap();
+        }
+        assert!(outbox.push("one more", true, "chat", 0).is_err());
+        outbox.clear();
+        assert!(outbox.is_empty());
+    }
+
+    #[test]
+    fn test_entries_round_trip_through_json() {
+        let outbox = Outbox::default();
+        outbox.push("hello", false, "claw", 1_700_000_000).unwrap();
+        let json = serde_json::to_value(outbox.entries()).unwrap();
+        let restored: Vec<OutboxEntry> = serde_json::from_value(json).unwrap();
+        assert_eq!(Outbox::from_entries(restored).entries(), outbox.entries());
+    }
+}
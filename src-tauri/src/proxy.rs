@@ -0,0 +1,570 @@
+//! Proxy configuration for the main window's webview
+//!
+//! The proxy is applied when the main window is created, so changes take
+//! effect after a restart. Windows opened for links get the same settings.
+//! `test_connection` checks reachability of the chat host through the
+//! configured proxy by opening a tunnel (HTTP `CONNECT` or SOCKS5) without
+//! involving the webview.
+//!
+//! On Windows the settings become WebView2 browser arguments, bypass list
+//! included. Webviews sharing a data directory must all use the same
+//! arguments, so windows showing Kimi then keep their data in
+//! `WEBVIEW_DATA_DIR`, away from the local pages. Elsewhere the webview
+//! only takes a proxy URL: `None` behaves like `System`, and the bypass list
+//! only decides whether the chat host goes through the proxy at all.
+
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Runtime, Url, WebviewWindowBuilder};
+
+use crate::wrappers::{WrapperError, WrapperResult};
+
+/// How the main window connects to the network
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyMode {
+    /// Direct connection. On Linux and macOS the webview exposes no override
+    /// for the platform proxy, so this only differs from `System` on Windows
+    /// and in the connection test.
+    None,
+    /// Use the platform proxy configuration
+    #[default]
+    System,
+    /// Use `ProxySettings::url`, except for hosts in the bypass list
+    Manual,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ProxySettings {
+    pub mode: ProxyMode,
+    /// `http://host:port` or `socks5://host:port`
+    pub url: String,
+    /// Hosts reached directly: `example.com`, `*.example.com`, `.example.com` or `<local>`
+    pub bypass: Vec<String>,
+}
+
+/// Data directory, under the app's local data, of windows showing Kimi
+/// while a proxy mode other than `System` is in effect on Windows
+#[cfg(windows)]
+pub const WEBVIEW_DATA_DIR: &str = "webview-proxy";
+
+/// Arguments WebView2 gets by default; setting any replaces them
+const DEFAULT_BROWSER_ARGS: &str = "--disable-features=msWebOOUI,msPdfOOUI,msSmartScreenProtection";
+
+/// Result of a successful connection test
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ConnectionReport {
+    /// Proxy the connection went through, `None` for a direct connection
+    pub proxy: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+impl ProxySettings {
+    /// Parse and check the manual proxy URL
+    pub fn proxy_url(&self) -> WrapperResult<Url> {
+        let invalid = |message: &str| WrapperError::InvalidSettings {
+            field: "proxy.url".to_string(),
+            message: message.to_string(),
+        };
+        let url = Url::parse(self.url.trim()).map_err(|e| invalid(&e.to_string()))?;
+        if !matches!(url.scheme(), "http" | "socks5") {
+            return Err(invalid("proxy URL must use http:// or socks5://"));
+        }
+        if url.host_str().is_none() || url.port_or_known_default().is_none() {
+            return Err(invalid("proxy URL must include a host and port"));
+        }
+        Ok(url)
+    }
+
+    /// Validate the settings before they are saved
+    pub fn validate(&self) -> WrapperResult<()> {
+        if self.mode == ProxyMode::Manual {
+            self.proxy_url()?;
+        }
+        if let Some(entry) = self.bypass.iter().find(|entry| {
+            entry
+                .trim()
+                .contains(|c: char| c.is_whitespace() || c == ';')
+        }) {
+            return Err(WrapperError::InvalidSettings {
+                field: "proxy.bypass".to_string(),
+                message: format!("bypass entry \"{}\" must be a single host pattern", entry),
+            });
+        }
+        Ok(())
+    }
+
+    /// WebView2 browser arguments for these settings, `None` for `System`
+    pub fn browser_args(&self) -> WrapperResult<Option<String>> {
+        match self.mode {
+            ProxyMode::System => Ok(None),
+            ProxyMode::None => Ok(Some(format!("{} --no-proxy-server", DEFAULT_BROWSER_ARGS))),
+            ProxyMode::Manual => {
+                let proxy = self.proxy_url()?;
+                let mut args = format!(
+                    "{} --proxy-server={}://{}:{}",
+                    DEFAULT_BROWSER_ARGS,
+                    proxy.scheme(),
+                    proxy.host_str().unwrap_or_default(),
+                    proxy.port_or_known_default().unwrap_or(1080)
+                );
+                let bypass: Vec<&str> = self
+                    .bypass
+                    .iter()
+                    .map(|entry| entry.trim())
+                    .filter(|entry| !entry.is_empty())
+                    .collect();
+                if !bypass.is_empty() {
+                    args.push_str(&format!(" --proxy-bypass-list={}", bypass.join(";")));
+                }
+                Ok(Some(args))
+            }
+        }
+    }
+
+    /// Proxy to use when connecting to `target`, `None` for a direct connection
+    ///
+    /// `System` mode resolves through the usual `*_proxy` environment variables.
+    pub fn resolve(&self, target: &Url) -> WrapperResult<Option<Url>> {
+        let host = target.host_str().unwrap_or_default();
+        match self.mode {
+            ProxyMode::None => Ok(None),
+            ProxyMode::Manual if is_bypassed(host, &self.bypass) => Ok(None),
+            ProxyMode::Manual => self.proxy_url().map(Some),
+            ProxyMode::System => {
+                let no_proxy = env_var(&["no_proxy", "NO_PROXY"])
+                    .map(|v| {
+                        v.split(',')
+                            .map(|s| s.trim().to_string())
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                if is_bypassed(host, &no_proxy) {
+                    return Ok(None);
+                }
+                let names: &[&str] = if target.scheme() == "https" {
+                    &["https_proxy", "HTTPS_PROXY", "all_proxy", "ALL_PROXY"]
+                } else {
+                    &["http_proxy", "HTTP_PROXY", "all_proxy", "ALL_PROXY"]
+                };
+                Ok(env_var(names).and_then(|v| Url::parse(&v).ok()))
+            }
+        }
+    }
+}
+
+fn env_var(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|v| !v.trim().is_empty())
+}
+
+/// Whether `host` matches an entry of a bypass list
+pub fn is_bypassed(host: &str, bypass: &[String]) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    bypass.iter().any(|entry| {
+        let entry = entry.trim().to_ascii_lowercase();
+        if entry.is_empty() {
+            false
+        } else if entry == "*" {
+            true
+        } else if entry == "<local>" {
+            !host.contains('.')
+        } else if let Some(suffix) = entry.strip_prefix("*.").or_else(|| entry.strip_prefix('.')) {
+            host == suffix || host.ends_with(&format!(".{}", suffix))
+        } else {
+            host == entry
+        }
+    })
+}
+
+/// Apply the proxy settings to the builder of a window showing `url`
+#[cfg(windows)]
+pub fn configure<'a, R: Runtime, M: tauri::Manager<R>>(
+    app: &AppHandle<R>,
+    builder: WebviewWindowBuilder<'a, R, M>,
+    settings: &ProxySettings,
+    _url: &Url,
+) -> WebviewWindowBuilder<'a, R, M> {
+    use tauri::Manager;
+
+    let args = match settings.browser_args() {
+        Ok(Some(args)) => args,
+        Ok(None) => return builder,
+        Err(e) => {
+            eprintln!("Ignoring proxy settings: {}", e);
+            return builder;
+        }
+    };
+    match app.path().app_local_data_dir() {
+        Ok(dir) => builder
+            .data_directory(dir.join(WEBVIEW_DATA_DIR))
+            .additional_browser_args(&args),
+        Err(e) => {
+            eprintln!("Ignoring proxy settings, no data directory: {}", e);
+            builder
+        }
+    }
+}
+
+/// Apply the proxy settings to the builder of a window showing `url`
+#[cfg(not(windows))]
+pub fn configure<'a, R: Runtime, M: tauri::Manager<R>>(
+    _app: &AppHandle<R>,
+    builder: WebviewWindowBuilder<'a, R, M>,
+    settings: &ProxySettings,
+    url: &Url,
+) -> WebviewWindowBuilder<'a, R, M> {
+    match settings.mode {
+        ProxyMode::System | ProxyMode::None => builder,
+        ProxyMode::Manual => match settings.resolve(url) {
+            Ok(Some(proxy)) => builder.proxy_url(proxy),
+            Ok(None) => builder,
+            Err(e) => {
+                eprintln!("Ignoring proxy settings: {}", e);
+                builder
+            }
+        },
+    }
+}
+
+/// Check that `target` is reachable with the given settings
+pub fn test_connection(
+    settings: &ProxySettings,
+    target: &Url,
+    timeout: Duration,
+) -> WrapperResult<ConnectionReport> {
+    let started = Instant::now();
+    let (stream, proxy) = open_stream(settings, target, timeout)?;
+    let _ = stream.shutdown(Shutdown::Both);
+
+    Ok(ConnectionReport {
+        proxy: proxy.map(|p| p.to_string()),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Open a TCP stream to `target`'s host and port, tunnelled through the
+/// proxy the settings resolve to; returns the stream and that proxy
+pub fn open_stream(
+    settings: &ProxySettings,
+    target: &Url,
+    timeout: Duration,
+) -> WrapperResult<(TcpStream, Option<Url>)> {
+    let host = target.host_str().ok_or_else(|| WrapperError::InvalidUrl {
+        url: target.to_string(),
+        reason: "missing host".to_string(),
+    })?;
+    let port = target.port_or_known_default().unwrap_or(443);
+    let proxy = settings.resolve(target)?;
+
+    let stream = match &proxy {
+        None => connect(host, port, timeout)?,
+        Some(proxy) => {
+            let proxy_host = proxy.host_str().unwrap_or_default();
+            let proxy_port = proxy.port_or_known_default().unwrap_or(1080);
+            let mut stream = connect(proxy_host, proxy_port, timeout)?;
+            if proxy.scheme() == "socks5" {
+                socks5_connect(&mut stream, host, port)?;
+            } else {
+                http_connect(&mut stream, host, port)?;
+            }
+            stream
+        }
+    };
+    Ok((stream, proxy))
+}
+
+fn connection_failed(message: impl Into<String>) -> WrapperError {
+    WrapperError::ConnectionFailed {
+        message: message.into(),
+    }
+}
+
+fn connect(host: &str, port: u16, timeout: Duration) -> WrapperResult<TcpStream> {
+    let addrs = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| connection_failed(format!("cannot resolve {}: {}", host, e)))?;
+    let mut last_error = None;
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => {
+                let _ = stream.set_read_timeout(Some(timeout));
+                let _ = stream.set_write_timeout(Some(timeout));
+                return Ok(stream);
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(connection_failed(match last_error {
+        Some(e) => format!("cannot connect to {}:{}: {}", host, port, e),
+        None => format!("no address found for {}", host),
+    }))
+}
+
+fn io_failed(e: std::io::Error) -> WrapperError {
+    connection_failed(e.to_string())
+}
+
+fn http_connect(stream: &mut TcpStream, host: &str, port: u16) -> WrapperResult<()> {
+    write!(
+        stream,
+        "CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n\r\n",
+        host = host,
+        port = port
+    )
+    .map_err(io_failed)?;
+
+    let mut response = Vec::new();
+    let mut buf = [0u8; 512];
+    while !response.windows(4).any(|w| w == b"\r\n\r\n") && response.len() < 8192 {
+        let n = stream.read(&mut buf).map_err(io_failed)?;
+        if n == 0 {
+            break;
+        }
+        response.extend_from_slice(&buf[..n]);
+    }
+
+    let status_line = String::from_utf8_lossy(&response)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+    match status {
+        "200" => Ok(()),
+        "407" => Err(connection_failed("proxy requires authentication (407)")),
+        "" => Err(connection_failed("proxy closed the connection")),
+        _ => Err(connection_failed(format!(
+            "proxy refused tunnel: {}",
+            status_line
+        ))),
+    }
+}
+
+fn socks5_connect(stream: &mut TcpStream, host: &str, port: u16) -> WrapperResult<()> {
+    // Greeting: version 5, one method, "no authentication"
+    stream.write_all(&[0x05, 0x01, 0x00]).map_err(io_failed)?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).map_err(io_failed)?;
+    if reply != [0x05, 0x00] {
+        return Err(connection_failed(
+            "SOCKS5 proxy requires unsupported authentication",
+        ));
+    }
+
+    let host_bytes = host.as_bytes();
+    let host_len = u8::try_from(host_bytes.len())
+        .map_err(|_| connection_failed("host name too long for SOCKS5"))?;
+    let mut request = vec![0x05, 0x01, 0x00, 0x03, host_len];
+    request.extend_from_slice(host_bytes);
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).map_err(io_failed)?;
+
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).map_err(io_failed)?;
+    if header[1] != 0x00 {
+        return Err(connection_failed(format!(
+            "SOCKS5 proxy refused connection (code {})",
+            header[1]
+        )));
+    }
+    let addr_len = match header[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).map_err(io_failed)?;
+            len[0] as usize
+        }
+        _ => return Err(connection_failed("malformed SOCKS5 reply")),
+    };
+    let mut rest = vec![0u8; addr_len + 2];
+    stream.read_exact(&mut rest).map_err(io_failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn manual(url: &str, bypass: &[&str]) -> ProxySettings {
+        ProxySettings {
+            mode: ProxyMode::Manual,
+            url: url.to_string(),
+            bypass: bypass.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    /// Minimal HTTP proxy stand-in answering one CONNECT with `status`
+    fn spawn_http_proxy(status: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let n = stream.read(&mut buf).unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]).to_string();
+            write!(stream, "HTTP/1.1 {}\r\n\r\n", status).unwrap();
+            request
+        });
+        (format!("http://{}", addr), handle)
+    }
+
+    #[test]
+    fn test_default_mode_is_system() {
+        assert_eq!(ProxySettings::default().mode, ProxyMode::System);
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_manual_url() {
+        assert!(manual("http://127.0.0.1:8080", &[]).validate().is_ok());
+        assert!(manual("socks5://proxy.corp:1080", &[]).validate().is_ok());
+        for url in ["", "proxy.corp:8080", "ftp://proxy.corp:21"] {
+            match manual(url, &[]).validate() {
+                Err(WrapperError::InvalidSettings { field, .. }) => assert_eq!(field, "proxy.url"),
+                other => panic!("expected InvalidSettings for {:?}, got {:?}", url, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_ignores_url_outside_manual_mode() {
+        let settings = ProxySettings {
+            mode: ProxyMode::None,
+            url: "not a url".to_string(),
+            bypass: vec![],
+        };
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_bypass_lists_in_one_entry() {
+        assert!(manual("http://proxy:3128", &["*.corp", "<local>"])
+            .validate()
+            .is_ok());
+        for entry in ["a.corp b.corp", "a.corp;b.corp"] {
+            let err = manual("http://proxy:3128", &[entry])
+                .validate()
+                .unwrap_err();
+            assert!(matches!(
+                err,
+                WrapperError::InvalidSettings { ref field, .. } if field == "proxy.bypass"
+            ));
+        }
+    }
+
+    #[test]
+    fn test_browser_args() {
+        assert_eq!(ProxySettings::default().browser_args().unwrap(), None);
+        let direct = ProxySettings {
+            mode: ProxyMode::None,
+            ..Default::default()
+        };
+        assert!(direct
+            .browser_args()
+            .unwrap()
+            .unwrap()
+            .ends_with(" --no-proxy-server"));
+
+        let args = manual("socks5://proxy:1080", &[" *.corp ", "", "<local>"])
+            .browser_args()
+            .unwrap()
+            .unwrap();
+        assert!(args.starts_with(DEFAULT_BROWSER_ARGS));
+        assert!(args.contains(" --proxy-server=socks5://proxy:1080"));
+        assert!(args.ends_with(" --proxy-bypass-list=*.corp;<local>"));
+        let args = manual("http://proxy", &[]).browser_args().unwrap().unwrap();
+        assert!(args.ends_with(" --proxy-server=http://proxy:80"));
+        assert!(manual("ftp://proxy:21", &[]).browser_args().is_err());
+    }
+
+    #[test]
+    fn test_is_bypassed_patterns() {
+        let list: Vec<String> = ["kimi.com", "*.moonshot.cn", ".corp", "<local>"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert!(is_bypassed("kimi.com", &list));
+        assert!(is_bypassed("KIMI.com.", &list));
+        assert!(!is_bypassed("www.kimi.com", &list));
+        assert!(is_bypassed("statics.moonshot.cn", &list));
+        assert!(is_bypassed("moonshot.cn", &list));
+        assert!(!is_bypassed("moonshot.cn.evil.io", &list));
+        assert!(is_bypassed("git.corp", &list));
+        assert!(is_bypassed("intranet", &list));
+    }
+
+    #[test]
+    fn test_resolve_manual_honors_bypass() {
+        let target = Url::parse("https://www.kimi.com/").unwrap();
+        let proxied = manual("http://127.0.0.1:3128", &[])
+            .resolve(&target)
+            .unwrap();
+        assert_eq!(proxied.unwrap().as_str(), "http://127.0.0.1:3128/");
+        let bypassed = manual("http://127.0.0.1:3128", &["*.kimi.com"])
+            .resolve(&target)
+            .unwrap();
+        assert!(bypassed.is_none());
+    }
+
+    #[test]
+    fn test_connection_through_http_proxy() {
+        let (proxy, handle) = spawn_http_proxy("200 Connection established");
+        let target = Url::parse("https://www.kimi.com/").unwrap();
+        let report =
+            test_connection(&manual(&proxy, &[]), &target, Duration::from_secs(5)).unwrap();
+        assert!(report.proxy.unwrap().starts_with(&proxy));
+        let request = handle.join().unwrap();
+        assert!(request.starts_with("CONNECT www.kimi.com:443 HTTP/1.1\r\n"));
+    }
+
+    #[test]
+    fn test_connection_reports_proxy_auth_required() {
+        let (proxy, handle) = spawn_http_proxy("407 Proxy Authentication Required");
+        let target = Url::parse("https://www.kimi.com/").unwrap();
+        let err =
+            test_connection(&manual(&proxy, &[]), &target, Duration::from_secs(5)).unwrap_err();
+        assert!(err.to_string().contains("407"));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_connection_through_socks5_proxy() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut greeting = [0u8; 3];
+            stream.read_exact(&mut greeting).unwrap();
+            stream.write_all(&[0x05, 0x00]).unwrap();
+            let mut header = [0u8; 5];
+            stream.read_exact(&mut header).unwrap();
+            let mut host = vec![0u8; header[4] as usize + 2];
+            stream.read_exact(&mut host).unwrap();
+            stream
+                .write_all(&[0x05, 0x00, 0x00, 0x01, 127, 0, 0, 1, 0x01, 0xbb])
+                .unwrap();
+            String::from_utf8_lossy(&host[..host.len() - 2]).to_string()
+        });
+        let target = Url::parse("https://www.kimi.com/").unwrap();
+        let settings = manual(&format!("socks5://{}", addr), &[]);
+        assert!(test_connection(&settings, &target, Duration::from_secs(5)).is_ok());
+        assert_eq!(handle.join().unwrap(), "www.kimi.com");
+    }
+
+    #[test]
+    fn test_connection_fails_when_proxy_unreachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let target = Url::parse("https://www.kimi.com/").unwrap();
+        let settings = manual(&format!("http://{}", addr), &[]);
+        assert!(test_connection(&settings, &target, Duration::from_secs(2)).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+//! Persisted application settings
+
+use std::collections::BTreeMap;
+
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+use crate::close_behavior::CloseBehavior;
+use crate::downloads::DownloadSettings;
+use crate::links::LinkRoutingSettings;
+use crate::notification_policy::NotificationPolicy;
+use crate::notifications::ResponseWindowAction;
+use crate::proxy::ProxySettings;
+use crate::startup::StartupSettings;
+use crate::wrappers::{
+    chat_mode::ChatMode,
+    config::{Storage, Urls},
+    destinations::{self, Destination, Destinations},
+    timing::TimingSettings,
+    Endpoints, WrapperError, WrapperResult,
+};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AppSettings {
+    pub new_chat_default: bool,
+    pub notifications_enabled: bool,
+    /// What happens to the main window when a response finishes in the background
+    #[serde(default)]
+    pub response_window: ResponseWindowAction,
+    /// Quiet hours, snooze and window rules applied before notifying
+    #[serde(default)]
+    pub notification_policy: NotificationPolicy,
+    /// Zoom factor per window label; windows without an entry use 1.0
+    #[serde(default)]
+    pub zoom_levels: BTreeMap<String, f64>,
+    /// Network proxy for the main window, applied on next start
+    #[serde(default)]
+    pub proxy: ProxySettings,
+    /// Kimi origin all chat URLs and first-party domains are derived from
+    #[serde(default = "default_origin")]
+    pub kimi_origin: String,
+    /// Rules deciding where clicked and opened links go
+    #[serde(default)]
+    pub link_routing: LinkRoutingSettings,
+    /// Where files downloaded from the main window are saved
+    #[serde(default)]
+    pub downloads: DownloadSettings,
+    /// Launch at login and whether the main window starts hidden
+    #[serde(default)]
+    pub startup: StartupSettings,
+    /// What the main window's close button does
+    #[serde(default)]
+    pub close_behavior: CloseBehavior,
+    /// Whether the notification explaining hide-to-tray was shown
+    #[serde(default)]
+    pub close_hint_shown: bool,
+    /// Waits used when sending prompts and watching responses
+    #[serde(default)]
+    pub timing: TimingSettings,
+    /// Reasoning and web search picked in the launcher
+    #[serde(default)]
+    pub chat_mode: ChatMode,
+    /// Kimi agents or pages added to the built-in chat and Kimi Claw
+    #[serde(default)]
+    pub destinations: Vec<Destination>,
+}
+
+fn default_origin() -> String {
+    Urls::DEFAULT_ORIGIN.to_string()
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            new_chat_default: true,
+            notifications_enabled: true,
+            response_window: ResponseWindowAction::default(),
+            notification_policy: NotificationPolicy::default(),
+            zoom_levels: BTreeMap::new(),
+            proxy: ProxySettings::default(),
+            kimi_origin: default_origin(),
+            link_routing: LinkRoutingSettings::default(),
+            downloads: DownloadSettings::default(),
+            startup: StartupSettings::default(),
+            close_behavior: CloseBehavior::default(),
+            close_hint_shown: false,
+            timing: TimingSettings::default(),
+            chat_mode: ChatMode::default(),
+            destinations: Vec::new(),
+        }
+    }
+}
+
+impl AppSettings {
+    /// Check settings that cannot be expressed through their types alone
+    pub fn validate(&self) -> WrapperResult<()> {
+        Endpoints::parse(&self.kimi_origin)?;
+        self.proxy.validate()?;
+        self.link_routing.validate()?;
+        self.notification_policy.validate()?;
+        self.downloads.validate()?;
+        self.timing.validate()?;
+        destinations::validate(&self.destinations)
+    }
+
+    /// Carry over values changed from Rust rather than the settings window,
+    /// so saving a form loaded earlier does not revert them
+    pub fn keep_runtime_state(&mut self, stored: &AppSettings) {
+        self.zoom_levels = stored.zoom_levels.clone();
+        self.notification_policy.snoozed_until = stored.notification_policy.snoozed_until;
+        self.close_hint_shown = stored.close_hint_shown;
+        self.chat_mode = stored.chat_mode;
+    }
+
+    /// Endpoints for the configured origin, or the default origin if it is invalid
+    pub fn endpoints(&self) -> Endpoints {
+        Endpoints::parse(&self.kimi_origin).unwrap_or_default()
+    }
+
+    /// Built-in destinations and the ones added here
+    pub fn destinations(&self) -> Destinations {
+        Destinations::new(&self.destinations)
+    }
+}
+
+/// Load settings from the store, falling back to defaults for unreadable values
+pub fn load(app: &AppHandle) -> WrapperResult<AppSettings> {
+    let store = app
+        .store(Storage::SETTINGS_FILE)
+        .map_err(|e| WrapperError::StoreError {
+            operation: "open".to_string(),
+            message: e.to_string(),
+        })?;
+    Ok(parse(store.get(Storage::SETTINGS_KEY)).unwrap_or_default())
+}
+
+/// Settings from the stored value, defaults when nothing is stored yet
+///
+/// Fails when a value is stored but does not parse, such as one written by a
+/// newer version.
+pub(crate) fn parse(value: Option<serde_json::Value>) -> WrapperResult<AppSettings> {
+    match value {
+        Some(value) => serde_json::from_value(value).map_err(|e| WrapperError::StoreError {
+            operation: "read".to_string(),
+            message: e.to_string(),
+        }),
+        None => Ok(AppSettings::default()),
+    }
+}
+
+/// Endpoints for the currently stored origin
+pub fn endpoints(app: &AppHandle) -> Endpoints {
+    load(app).map(|s| s.endpoints()).unwrap_or_default()
+}
+
+/// Write settings to the store and flush it to disk
+pub fn store(app: &AppHandle, settings: &AppSettings) -> WrapperResult<()> {
+    settings.validate()?;
+    let store = app
+        .store(Storage::SETTINGS_FILE)
+        .map_err(|e| WrapperError::StoreError {
+            operation: "open".to_string(),
+            message: e.to_string(),
+        })?;
+    store.set(Storage::SETTINGS_KEY, serde_json::to_value(settings)?);
+    store.save().map_err(|e| WrapperError::StoreError {
+        operation: "save".to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// Load, modify and write back settings in one step
+///
+/// Unlike `load` this does not fall back to defaults: writing those back
+/// would replace every stored setting because one of them did not parse.
+pub fn update<F>(app: &AppHandle, f: F) -> WrapperResult<AppSettings>
+where
+    F: FnOnce(&mut AppSettings),
+{
+    let settings_store =
+        app.store(Storage::SETTINGS_FILE)
+            .map_err(|e| WrapperError::StoreError {
+                operation: "open".to_string(),
+                message: e.to_string(),
+            })?;
+    let mut settings = parse(settings_store.get(Storage::SETTINGS_KEY))?;
+    f(&mut settings);
+    store(app, &settings)?;
+    Ok(settings)
+}
@@ -0,0 +1,110 @@
+//! Launching Kimi at login and how it appears when started
+//!
+//! The login entry itself (an XDG autostart file on Linux, a launch agent on
+//! macOS, a registry value on Windows) is owned by the autostart plugin; the
+//! stored `open_at_login` flag only records what the user asked for, so the
+//! entry is checked and corrected whenever settings are saved.
+//!
+//! The entry starts Kimi with [`AUTOSTART_ARG`], which is how a start at
+//! login is told apart from one by hand.
+
+use tauri::{AppHandle, Manager};
+use tauri_plugin_autostart::ManagerExt;
+
+use crate::wrappers::{WrapperError, WrapperResult};
+
+/// Argument the login entry passes to Kimi
+pub const AUTOSTART_ARG: &str = "--autostart";
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct StartupSettings {
+    /// Start Kimi when the user logs in
+    pub open_at_login: bool,
+    /// Keep the main window hidden when started at login; the tray and
+    /// launcher shortcut are available either way
+    pub start_hidden: bool,
+}
+
+impl Default for StartupSettings {
+    fn default() -> Self {
+        Self {
+            open_at_login: false,
+            start_hidden: true,
+        }
+    }
+}
+
+/// Whether a login entry is currently installed
+pub fn is_enabled(app: &AppHandle) -> Option<bool> {
+    app.autolaunch().is_enabled().ok()
+}
+
+/// Install or remove the login entry to match `settings`
+pub fn apply(app: &AppHandle, settings: &StartupSettings) -> WrapperResult<()> {
+    let autolaunch = app.autolaunch();
+    if autolaunch.is_enabled().ok() == Some(settings.open_at_login) {
+        return Ok(());
+    }
+    let result = if settings.open_at_login {
+        autolaunch.enable()
+    } else {
+        autolaunch.disable()
+    };
+    result.map_err(|e| WrapperError::SystemError {
+        operation: "update open at login".to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// Rewrite an installed login entry, so one from an older version also
+/// passes [`AUTOSTART_ARG`]
+pub fn refresh(app: &AppHandle) {
+    let autolaunch = app.autolaunch();
+    if autolaunch.is_enabled().unwrap_or(false) {
+        if let Err(e) = autolaunch.enable() {
+            eprintln!("Failed to refresh open at login: {}", e);
+        }
+    }
+}
+
+/// Whether `args`, the process arguments, come from the login entry
+pub fn is_login_launch<I, S>(args: I) -> bool
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    args.into_iter().any(|arg| arg.as_ref() == AUTOSTART_ARG)
+}
+
+/// Show the main window once it exists, unless Kimi started at login and
+/// should stay in the tray
+pub fn show_main_window(app: &AppHandle, settings: &StartupSettings) {
+    if settings.start_hidden && is_login_launch(std::env::args()) {
+        return;
+    }
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_start_hidden_by_default() {
+        let settings: StartupSettings = serde_json::from_str("{\"open_at_login\": true}").unwrap();
+        assert!(settings.open_at_login);
+        assert!(settings.start_hidden);
+        assert!(!StartupSettings::default().open_at_login);
+    }
+
+    #[test]
+    fn test_login_launch_needs_autostart_arg() {
+        assert!(is_login_launch(["kimi", AUTOSTART_ARG]));
+        assert!(!is_login_launch(["kimi"]));
+        assert!(!is_login_launch(["kimi", "kimi://open?path=/chat"]));
+    }
+}
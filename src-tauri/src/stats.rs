@@ -0,0 +1,295 @@
+//! Local statistics about sending prompts into the Kimi page
+//!
+//! Every injection attempt reports which of the `Selectors` fallbacks found
+//! the chat input and send button. The totals stay on this machine, in their
+//! own store file, and are shown in the settings window. They show which
+//! fallbacks are actually needed, and give a warning once the primary
+//! `CHAT_INPUT` selector keeps missing, which usually means Kimi changed its
+//! page.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+use crate::wrappers::{
+    config::{Selectors, Storage},
+    WrapperError, WrapperResult,
+};
+
+/// Name the injected script reports when `Selectors::CHAT_INPUT` matched
+const PRIMARY_INPUT: &str = "chat_input";
+/// Prefix of the names reported when a destination's own input selector
+/// matched; those pages are not expected to have Kimi's input
+const CUSTOM_INPUT_PREFIX: &str = "custom_input";
+/// Start of the error the injected script reports when no input matched
+const INPUT_NOT_FOUND: &str = "Could not find chat input";
+
+/// Outcome of one injection, as reported by the page in `inject-result`
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default)]
+pub struct InjectResult {
+    pub success: bool,
+    pub error: Option<String>,
+    /// Name of the input selector that matched, if any
+    pub input_selector: Option<String>,
+    /// Name of the send button selector that matched, or `enter_key`
+    pub send_selector: Option<String>,
+    /// In-page retries while looking for the input
+    pub retries: u32,
+    /// Time from injection to submit or failure
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct InjectionStats {
+    pub attempts: u64,
+    pub successes: u64,
+    /// In-page retries summed over all attempts
+    pub retries: u64,
+    /// Time to submit summed over successful attempts
+    pub submit_ms: u64,
+    /// Attempts per matching input selector; `none` when nothing matched
+    pub input_selectors: BTreeMap<String, u64>,
+    /// Successful attempts per send button selector
+    pub send_selectors: BTreeMap<String, u64>,
+    /// Attempts in a row where the primary input selector did not match
+    pub primary_input_misses: u32,
+    /// Unix time in seconds of the latest attempt
+    pub last_attempt_at: Option<i64>,
+}
+
+impl InjectionStats {
+    pub fn record(&mut self, result: &InjectResult, at: i64) {
+        self.attempts += 1;
+        self.retries += u64::from(result.retries);
+        self.last_attempt_at = Some(at);
+
+        let input = result.input_selector.as_deref().unwrap_or("none");
+        *self.input_selectors.entry(input.to_string()).or_default() += 1;
+        // Only a page that looked for the input and found none missed; an
+        // attempt the page never reported on says nothing either way
+        let not_found = result
+            .error
+            .as_deref()
+            .is_some_and(|e| e.starts_with(INPUT_NOT_FOUND));
+        match result.input_selector.as_deref() {
+            Some(PRIMARY_INPUT) => self.primary_input_misses = 0,
+            Some(name) if name.starts_with(CUSTOM_INPUT_PREFIX) => {}
+            Some(_) => self.primary_input_misses += 1,
+            None if not_found => self.primary_input_misses += 1,
+            None => {}
+        }
+
+        if result.success {
+            self.successes += 1;
+            self.submit_ms += result.elapsed_ms;
+            if let Some(send) = &result.send_selector {
+                *self.send_selectors.entry(send.clone()).or_default() += 1;
+            }
+        }
+    }
+
+    pub fn average_submit_ms(&self) -> Option<u64> {
+        self.submit_ms.checked_div(self.successes)
+    }
+
+    /// Warning once the primary input selector missed too often in a row
+    pub fn warning(&self) -> Option<String> {
+        (self.primary_input_misses >= Selectors::PRIMARY_INPUT_MISS_WARNING).then(|| {
+            format!(
+                "The chat input selector \"{}\" did not match the last {} sends; Kimi may have changed its page",
+                Selectors::CHAT_INPUT,
+                self.primary_input_misses
+            )
+        })
+    }
+}
+
+/// Stats with the derived values the settings window shows
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StatsReport {
+    #[serde(flatten)]
+    pub stats: InjectionStats,
+    pub average_submit_ms: Option<u64>,
+    pub warning: Option<String>,
+}
+
+impl From<InjectionStats> for StatsReport {
+    fn from(stats: InjectionStats) -> Self {
+        Self {
+            average_submit_ms: stats.average_submit_ms(),
+            warning: stats.warning(),
+            stats,
+        }
+    }
+}
+
+/// Load the stats collected so far
+pub fn load(app: &AppHandle) -> Mutex<InjectionStats> {
+    let stats = app
+        .store(Storage::STATS_FILE)
+        .ok()
+        .and_then(|store| store.get(Storage::STATS_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default();
+    Mutex::new(stats)
+}
+
+fn save(app: &AppHandle, stats: &InjectionStats) -> WrapperResult<()> {
+    let store = app
+        .store(Storage::STATS_FILE)
+        .map_err(|e| WrapperError::StoreError {
+            operation: "open".to_string(),
+            message: e.to_string(),
+        })?;
+    store.set(Storage::STATS_KEY, serde_json::to_value(stats)?);
+    store.save().map_err(|e| WrapperError::StoreError {
+        operation: "save".to_string(),
+        message: e.to_string(),
+    })
+}
+
+fn changed(app: &AppHandle, stats: InjectionStats) {
+    if let Err(e) = save(app, &stats) {
+        eprintln!("Failed to save injection stats: {}", e);
+    }
+    let _ = app.emit("injection-stats-changed", StatsReport::from(stats));
+}
+
+/// Add one injection attempt to the stats
+pub fn record(app: &AppHandle, result: &InjectResult) {
+    let Some(state) = app.try_state::<Mutex<InjectionStats>>() else {
+        return;
+    };
+    let stats = {
+        let mut stats = state.lock().unwrap();
+        stats.record(result, chrono::Utc::now().timestamp());
+        stats.clone()
+    };
+    if stats.primary_input_misses == Selectors::PRIMARY_INPUT_MISS_WARNING {
+        if let Some(warning) = stats.warning() {
+            eprintln!("{}", warning);
+        }
+    }
+    changed(app, stats);
+}
+
+pub fn report(app: &AppHandle) -> StatsReport {
+    app.state::<Mutex<InjectionStats>>()
+        .lock()
+        .unwrap()
+        .clone()
+        .into()
+}
+
+pub fn reset(app: &AppHandle) {
+    let state = app.state::<Mutex<InjectionStats>>();
+    *state.lock().unwrap() = InjectionStats::default();
+    changed(app, InjectionStats::default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sent(input: &str, send: &str, retries: u32, elapsed_ms: u64) -> InjectResult {
+        InjectResult {
+            success: true,
+            input_selector: Some(input.to_string()),
+            send_selector: Some(send.to_string()),
+            retries,
+            elapsed_ms,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_record_aggregates_attempts() {
+        let mut stats = InjectionStats::default();
+        stats.record(&sent("chat_input", "send_button", 0, 400), 1);
+        stats.record(&sent("textarea_any", "enter_key", 3, 1200), 2);
+        stats.record(
+            &InjectResult {
+                error: Some("Could not find chat input".to_string()),
+                retries: 15,
+                elapsed_ms: 8000,
+                ..Default::default()
+            },
+            3,
+        );
+
+        assert_eq!(stats.attempts, 3);
+        assert_eq!(stats.successes, 2);
+        assert_eq!(stats.retries, 18);
+        assert_eq!(stats.average_submit_ms(), Some(800));
+        assert_eq!(stats.input_selectors.get("chat_input"), Some(&1));
+        assert_eq!(stats.input_selectors.get("textarea_any"), Some(&1));
+        assert_eq!(stats.input_selectors.get("none"), Some(&1));
+        assert_eq!(stats.send_selectors.get("enter_key"), Some(&1));
+        assert_eq!(stats.last_attempt_at, Some(3));
+    }
+
+    #[test]
+    fn test_warns_when_primary_input_keeps_missing() {
+        let mut stats = InjectionStats::default();
+        assert_eq!(stats.average_submit_ms(), None);
+        for _ in 1..Selectors::PRIMARY_INPUT_MISS_WARNING {
+            stats.record(&sent("textarea_any", "send_button", 0, 100), 0);
+        }
+        assert!(stats.warning().is_none());
+        stats.record(&sent("contenteditable", "send_button", 0, 100), 0);
+        assert!(stats.warning().unwrap().contains(Selectors::CHAT_INPUT));
+
+        stats.record(&sent("chat_input", "send_button", 0, 100), 0);
+        assert!(stats.warning().is_none());
+    }
+
+    #[test]
+    fn test_destination_selectors_are_not_primary_misses() {
+        let mut stats = InjectionStats::default();
+        for _ in 0..Selectors::PRIMARY_INPUT_MISS_WARNING {
+            stats.record(&sent("custom_input_1", "custom_send_1", 0, 100), 0);
+        }
+        assert_eq!(stats.primary_input_misses, 0);
+        assert_eq!(
+            stats.input_selectors.get("custom_input_1").copied(),
+            Some(Selectors::PRIMARY_INPUT_MISS_WARNING.into())
+        );
+        assert!(stats.warning().is_none());
+    }
+
+    #[test]
+    fn test_unreported_attempts_are_not_primary_misses() {
+        let mut stats = InjectionStats::default();
+        let unreported = InjectResult {
+            error: Some("the page did not report a result".to_string()),
+            ..Default::default()
+        };
+        stats.record(&unreported, 0);
+        assert_eq!(stats.primary_input_misses, 0);
+
+        let not_found = InjectResult {
+            error: Some(format!("{} after 15 attempts", INPUT_NOT_FOUND)),
+            ..Default::default()
+        };
+        stats.record(&not_found, 0);
+        assert_eq!(stats.primary_input_misses, 1);
+    }
+
+    #[test]
+    fn test_inject_result_from_page_payload() {
+        let result: InjectResult = serde_json::from_str(
+            r#"{"success":true,"error":null,"input_selector":"chat_input","send_selector":"send_button","retries":2,"elapsed_ms":950}"#,
+        )
+        .unwrap();
+        assert_eq!(result, sent("chat_input", "send_button", 2, 950));
+        // Fields the script did not get to are left at their defaults
+        let result: InjectResult =
+            serde_json::from_str(r#"{"success":false,"error":"timed out"}"#).unwrap();
+        assert!(!result.success);
+        assert_eq!(result.input_selector, None);
+    }
+}
@@ -0,0 +1,466 @@
+//! System tray icon and its menu
+//!
+//! The menu is rebuilt by `refresh` whenever its dynamic parts change; the
+//! icon and tooltip follow the `AppState` through `show_state`.
+
+use tauri::{
+    image::Image,
+    menu::{IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
+    tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
+    AppHandle, Manager, Wry,
+};
+
+use chrono::{Duration, Local};
+
+use crate::app_state::{Activity, AppState};
+use crate::downloads::{self, DownloadEntry, DownloadState, Downloads};
+use crate::history::History;
+use crate::notifications;
+use crate::outbox::{self, Outbox};
+use crate::settings;
+use crate::wrappers::config::Tray;
+
+const DOWNLOAD_ITEM_PREFIX: &str = "download:";
+const CONVERSATION_ITEM_PREFIX: &str = "conversation:";
+const PROMPT_ITEM_PREFIX: &str = "prompt:";
+
+/// Badge colors drawn over the app icon
+const GENERATING_BADGE: [u8; 3] = [0xF5, 0xA6, 0x23];
+const UNREAD_BADGE: [u8; 3] = [0xE5, 0x39, 0x35];
+
+pub fn setup(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+    let menu = build_menu(app)?;
+
+    let _tray = TrayIconBuilder::with_id(Tray::ID)
+        .icon(app.default_window_icon().unwrap().clone())
+        .tooltip(AppState::default().tooltip())
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| match event.id.as_ref() {
+            "show" => {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+            }
+            "launcher" => {
+                if let Some(launcher) = app.get_webview_window("launcher") {
+                    let _ = launcher.center();
+                    let _ = launcher.show();
+                    let _ = launcher.set_focus();
+                }
+            }
+            "settings" => {
+                if let Some(settings) = app.get_webview_window("settings") {
+                    let _ = settings.show();
+                    let _ = settings.set_focus();
+                }
+            }
+            "downloads-folder" => {
+                if let Ok(dir) = downloads::directory(app) {
+                    let _ = tauri_plugin_opener::open_path(dir, None::<&str>);
+                }
+            }
+            "outbox-send" => {
+                // Loading a Kimi page delivers the outbox once it succeeds
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.navigate(settings::endpoints(app).chat());
+                }
+            }
+            "outbox-clear" => outbox::discard_all(app),
+            "mute-1h" => notifications::snooze(app, Duration::hours(1)),
+            "mute-8h" => notifications::snooze(app, Duration::hours(8)),
+            "unmute" => notifications::resume(app),
+            "quit" => {
+                app.exit(0);
+            }
+            id => {
+                if let Some(id) = item_id(id, DOWNLOAD_ITEM_PREFIX) {
+                    if let Some(entry) = app.state::<Downloads>().get(id) {
+                        downloads::reveal(&entry.path);
+                    }
+                } else if let Some(id) = item_id(id, CONVERSATION_ITEM_PREFIX) {
+                    let url = history(app, |h| h.conversation(id).map(|c| c.url.clone())).flatten();
+                    if let Some(url) = url.and_then(|url| url.parse().ok()) {
+                        notifications::open_conversation(app, Some(url));
+                    }
+                } else if let Some(id) = item_id(id, PROMPT_ITEM_PREFIX) {
+                    if let Some(prompt) = history(app, |h| h.prompt(id).cloned()).flatten() {
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            if let Err(e) = crate::submit(
+                                &app,
+                                &prompt.message,
+                                prompt.new_chat,
+                                &prompt.destination,
+                            )
+                            .await
+                            {
+                                eprintln!("Failed to resend prompt: {}", e);
+                            }
+                        });
+                    }
+                }
+            }
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                let app = tray.app_handle();
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+            }
+        })
+        .build(app)?;
+
+    Ok(())
+}
+
+/// Update the icon and tooltip to reflect `state`
+pub fn show_state(app: &AppHandle, state: &AppState) -> tauri::Result<()> {
+    let (Some(tray), Some(icon)) = (app.tray_by_id(Tray::ID), app.default_window_icon()) else {
+        return Ok(());
+    };
+    let (width, height) = (icon.width(), icon.height());
+    let rgba = match state.activity() {
+        Activity::Idle => icon.rgba().to_vec(),
+        Activity::Generating => with_badge(icon.rgba(), width, height, GENERATING_BADGE),
+        Activity::Unread => with_badge(icon.rgba(), width, height, UNREAD_BADGE),
+        Activity::Offline => dimmed(icon.rgba()),
+    };
+    tray.set_icon(Some(Image::new_owned(rgba, width, height)))?;
+    tray.set_tooltip(Some(state.tooltip()))
+}
+
+/// Rebuild the tray menu from current app state
+pub fn refresh(app: &AppHandle) -> tauri::Result<()> {
+    if let Some(tray) = app.tray_by_id(Tray::ID) {
+        tray.set_menu(Some(build_menu(app)?))?;
+    }
+    Ok(())
+}
+
+fn build_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let show_item = MenuItem::with_id(app, "show", "Show Kimi", true, None::<&str>)?;
+    let launcher_item = MenuItem::with_id(app, "launcher", "Quick Ask...", true, None::<&str>)?;
+    let separator1 = PredefinedMenuItem::separator(app)?;
+    let conversations_menu = build_conversations_menu(app)?;
+    let prompts_menu = build_prompts_menu(app)?;
+    let outbox_menu = build_outbox_menu(app)?;
+    let downloads_menu = build_downloads_menu(app)?;
+    let mute_menu = build_mute_menu(app)?;
+    let settings_item = MenuItem::with_id(app, "settings", "Settings...", true, None::<&str>)?;
+    let separator2 = PredefinedMenuItem::separator(app)?;
+    let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+
+    let mut items: Vec<&dyn IsMenuItem<Wry>> = vec![
+        &show_item,
+        &launcher_item,
+        &separator1,
+        &conversations_menu,
+        &prompts_menu,
+    ];
+    if let Some(outbox_menu) = &outbox_menu {
+        items.push(outbox_menu);
+    }
+    items.extend([
+        &downloads_menu as &dyn IsMenuItem<Wry>,
+        &mute_menu,
+        &settings_item,
+        &separator2,
+        &quit_item,
+    ]);
+    Menu::with_items(app, &items)
+}
+
+/// Prompts waiting to be sent, or `None` when the outbox is empty
+fn build_outbox_menu(app: &AppHandle) -> tauri::Result<Option<Submenu<Wry>>> {
+    let entries = app
+        .try_state::<Outbox>()
+        .map(|outbox| outbox.entries())
+        .unwrap_or_default();
+    if entries.is_empty() {
+        return Ok(None);
+    }
+
+    let mut items: Vec<Box<dyn IsMenuItem<Wry>>> = Vec::new();
+    for entry in &entries {
+        items.push(Box::new(MenuItem::new(
+            app,
+            menu_label(&entry.message),
+            false,
+            None::<&str>,
+        )?));
+    }
+    items.push(Box::new(PredefinedMenuItem::separator(app)?));
+    items.push(Box::new(MenuItem::with_id(
+        app,
+        "outbox-send",
+        "Try Sending Now",
+        true,
+        None::<&str>,
+    )?));
+    items.push(Box::new(MenuItem::with_id(
+        app,
+        "outbox-clear",
+        "Discard All",
+        true,
+        None::<&str>,
+    )?));
+
+    let refs: Vec<&dyn IsMenuItem<Wry>> = items.iter().map(|item| item.as_ref()).collect();
+    let title = format!("Outbox ({})", entries.len());
+    Submenu::with_items(app, title, true, &refs).map(Some)
+}
+
+fn item_id(id: &str, prefix: &str) -> Option<u64> {
+    id.strip_prefix(prefix).and_then(|id| id.parse().ok())
+}
+
+fn history<T>(app: &AppHandle, f: impl FnOnce(&History) -> T) -> Option<T> {
+    let history = app.try_state::<std::sync::Mutex<History>>()?;
+    let history = history.lock().unwrap();
+    Some(f(&history))
+}
+
+/// Submenu of `(id, label)` items, or a disabled placeholder when empty
+fn build_list_menu(
+    app: &AppHandle,
+    title: &str,
+    empty: &str,
+    entries: Vec<(String, String)>,
+) -> tauri::Result<Submenu<Wry>> {
+    let mut items: Vec<Box<dyn IsMenuItem<Wry>>> = Vec::new();
+    if entries.is_empty() {
+        items.push(Box::new(MenuItem::new(app, empty, false, None::<&str>)?));
+    }
+    for (id, label) in entries {
+        items.push(Box::new(MenuItem::with_id(
+            app,
+            id,
+            label,
+            true,
+            None::<&str>,
+        )?));
+    }
+    let refs: Vec<&dyn IsMenuItem<Wry>> = items.iter().map(|item| item.as_ref()).collect();
+    Submenu::with_items(app, title, true, &refs)
+}
+
+fn build_conversations_menu(app: &AppHandle) -> tauri::Result<Submenu<Wry>> {
+    let entries = history(app, |h| {
+        h.conversations()
+            .map(|c| {
+                (
+                    format!("{}{}", CONVERSATION_ITEM_PREFIX, c.id),
+                    menu_label(&c.title),
+                )
+            })
+            .collect()
+    })
+    .unwrap_or_default();
+    build_list_menu(app, "Recent Conversations", "No conversations yet", entries)
+}
+
+fn build_prompts_menu(app: &AppHandle) -> tauri::Result<Submenu<Wry>> {
+    let entries = history(app, |h| {
+        h.prompts()
+            .map(|p| {
+                (
+                    format!("{}{}", PROMPT_ITEM_PREFIX, p.id),
+                    menu_label(&p.message),
+                )
+            })
+            .collect()
+    })
+    .unwrap_or_default();
+    build_list_menu(app, "Resend Prompt", "No prompts yet", entries)
+}
+
+fn build_downloads_menu(app: &AppHandle) -> tauri::Result<Submenu<Wry>> {
+    let recent = app
+        .try_state::<Downloads>()
+        .map(|downloads| downloads.recent())
+        .unwrap_or_default();
+
+    let mut items: Vec<Box<dyn IsMenuItem<Wry>>> = Vec::new();
+    if recent.is_empty() {
+        items.push(Box::new(MenuItem::new(
+            app,
+            "No downloads yet",
+            false,
+            None::<&str>,
+        )?));
+    }
+    for entry in &recent {
+        items.push(Box::new(MenuItem::with_id(
+            app,
+            format!("{}{}", DOWNLOAD_ITEM_PREFIX, entry.id),
+            download_label(entry),
+            entry.state != DownloadState::Failed,
+            None::<&str>,
+        )?));
+    }
+    items.push(Box::new(PredefinedMenuItem::separator(app)?));
+    items.push(Box::new(MenuItem::with_id(
+        app,
+        "downloads-folder",
+        "Open Downloads Folder",
+        true,
+        None::<&str>,
+    )?));
+
+    let refs: Vec<&dyn IsMenuItem<Wry>> = items.iter().map(|item| item.as_ref()).collect();
+    Submenu::with_items(app, "Recent Downloads", true, &refs)
+}
+
+fn build_mute_menu(app: &AppHandle) -> tauri::Result<Submenu<Wry>> {
+    let policy = settings::load(app).unwrap_or_default().notification_policy;
+    let snoozed = policy.is_snoozed(&Local::now());
+
+    let status = match policy.snoozed_until.filter(|_| snoozed) {
+        Some(until) => chrono::DateTime::from_timestamp(until, 0)
+            .map(|t| format!("Muted until {}", t.with_timezone(&Local).format("%H:%M")))
+            .unwrap_or_else(|| "Muted".to_string()),
+        None => "Notifications on".to_string(),
+    };
+    let status_item = MenuItem::new(app, status, false, None::<&str>)?;
+    let separator = PredefinedMenuItem::separator(app)?;
+    let hour_item = MenuItem::with_id(app, "mute-1h", "Mute for 1 Hour", true, None::<&str>)?;
+    let eight_hours_item =
+        MenuItem::with_id(app, "mute-8h", "Mute for 8 Hours", true, None::<&str>)?;
+    let resume_item =
+        MenuItem::with_id(app, "unmute", "Resume Notifications", snoozed, None::<&str>)?;
+
+    Submenu::with_items(
+        app,
+        "Do Not Disturb",
+        true,
+        &[
+            &status_item,
+            &separator,
+            &hour_item,
+            &eight_hours_item,
+            &resume_item,
+        ],
+    )
+}
+
+fn download_label(entry: &DownloadEntry) -> String {
+    match entry.state {
+        DownloadState::InProgress => {
+            format!(
+                "{} ({})",
+                entry.file_name,
+                format_size(entry.received_bytes)
+            )
+        }
+        DownloadState::Completed => entry.file_name.clone(),
+        DownloadState::Failed => format!("{} (failed)", entry.file_name),
+    }
+}
+
+/// Single-line menu label, truncated to `Tray::LABEL_MAX_CHARS`
+fn menu_label(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= Tray::LABEL_MAX_CHARS {
+        return text;
+    }
+    let mut label: String = text.chars().take(Tray::LABEL_MAX_CHARS - 1).collect();
+    label.truncate(label.trim_end().len());
+    label.push('…');
+    label
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Draw a filled circle in the bottom-right corner of an RGBA image
+fn with_badge(rgba: &[u8], width: u32, height: u32, color: [u8; 3]) -> Vec<u8> {
+    let mut out = rgba.to_vec();
+    let radius = width.min(height) as f32 * 0.22;
+    let (cx, cy) = (width as f32 - radius - 0.5, height as f32 - radius - 0.5);
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+            if dx * dx + dy * dy <= radius * radius {
+                let i = ((y * width + x) * 4) as usize;
+                out[i..i + 4].copy_from_slice(&[color[0], color[1], color[2], 0xFF]);
+            }
+        }
+    }
+    out
+}
+
+/// Grayscale, half-transparent copy of an RGBA image
+fn dimmed(rgba: &[u8]) -> Vec<u8> {
+    rgba.chunks_exact(4)
+        .flat_map(|px| {
+            let luma = (px[0] as u32 * 299 + px[1] as u32 * 587 + px[2] as u32 * 114) / 1000;
+            let luma = luma as u8;
+            [luma, luma, luma, px[3] / 2]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(rgba: &[u8], width: u32, x: u32, y: u32) -> &[u8] {
+        let i = ((y * width + x) * 4) as usize;
+        &rgba[i..i + 4]
+    }
+
+    #[test]
+    fn test_badge_covers_bottom_right_corner_only() {
+        let (width, height) = (32, 32);
+        let icon = vec![0x10; (width * height * 4) as usize];
+        let badged = with_badge(&icon, width, height, UNREAD_BADGE);
+        assert_eq!(badged.len(), icon.len());
+        assert_eq!(pixel(&badged, width, 27, 27), &[0xE5, 0x39, 0x35, 0xFF]);
+        assert_eq!(pixel(&badged, width, 4, 4), &[0x10; 4]);
+        assert_eq!(pixel(&badged, width, 31, 16), &[0x10; 4]);
+    }
+
+    #[test]
+    fn test_dimmed_is_gray_and_translucent() {
+        let dim = dimmed(&[255, 0, 0, 255, 10, 20, 30, 0]);
+        assert_eq!(dim, vec![76, 76, 76, 127, 18, 18, 18, 0]);
+    }
+
+    #[test]
+    fn test_menu_label() {
+        assert_eq!(menu_label("  Trip\n plan  "), "Trip plan");
+        let label = menu_label(&"é".repeat(Tray::LABEL_MAX_CHARS + 10));
+        assert_eq!(label.chars().count(), Tray::LABEL_MAX_CHARS);
+        assert!(label.ends_with("é…"));
+        let exact = "x".repeat(Tray::LABEL_MAX_CHARS);
+        assert_eq!(menu_label(&exact), exact);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MB");
+    }
+}
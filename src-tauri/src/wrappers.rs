@@ -3,18 +3,27 @@
 //! This module provides centralized configuration and utilities for injecting
 //! JavaScript into the main webview window.
 
+pub mod chat_mode;
 pub mod config;
+pub mod destinations;
+pub mod endpoints;
 pub mod error;
+pub mod evaluator;
 pub mod extension;
+#[cfg(test)]
+mod headless;
+pub mod params;
 pub mod templates;
+pub mod timing;
 pub mod utils;
 
 // Re-export commonly used items
 pub use config::*;
-pub use error::{WrapperError, WrapperResult};
+pub use endpoints::Endpoints;
+pub use error::{CommandResult, WrapperError, WrapperResult};
 pub use extension::{
-    apply_all_wrappers, emit_launcher_shown, emit_settings_changed, set_offline_state,
-    submit_chat_message, WrapperExt,
+    apply_all_wrappers, emit_launcher_prefilled, emit_launcher_shown, emit_settings_changed,
+    set_offline_state, submit_chat_message, WrapperExt,
 };
 pub use templates::*;
-pub use utils::{build_js, escape_js};
+pub use utils::{build_js, js_json_literal};
@@ -0,0 +1,157 @@
+//! Kimi's reasoning and web search options, chosen in the launcher
+//!
+//! Kimi keeps these as toggles next to the chat input. Before a prompt is
+//! sent, the toggles that differ from the chosen mode are clicked by
+//! `CHAT_MODE_JS`; `Unchanged` leaves a toggle as the page has it.
+
+use serde::Serialize;
+
+use crate::wrappers::config::Selectors;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reasoning {
+    #[default]
+    Unchanged,
+    Standard,
+    Thinking,
+    LongThinking,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebSearch {
+    #[default]
+    Unchanged,
+    On,
+    Off,
+}
+
+/// Last mode picked in the launcher, applied to every prompt sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ChatMode {
+    pub reasoning: Reasoning,
+    pub web_search: WebSearch,
+}
+
+/// A toggle in the page and the state it should end up in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModeToggle {
+    pub name: &'static str,
+    /// `[name, selector]` pairs tried in order
+    pub selectors: &'static [(&'static str, &'static str)],
+    pub on: bool,
+    /// Not reported as missing when the page has no such toggle
+    pub optional: bool,
+}
+
+const THINKING: &[(&str, &str)] = &[
+    ("thinking_testid", Selectors::THINKING_TOGGLE_TESTID),
+    ("thinking_aria", Selectors::THINKING_TOGGLE_ARIA),
+];
+const LONG_THINKING: &[(&str, &str)] = &[
+    (
+        "long_thinking_testid",
+        Selectors::LONG_THINKING_TOGGLE_TESTID,
+    ),
+    ("long_thinking_aria", Selectors::LONG_THINKING_TOGGLE_ARIA),
+];
+const WEB_SEARCH: &[(&str, &str)] = &[
+    ("web_search_testid", Selectors::WEB_SEARCH_TOGGLE_TESTID),
+    ("web_search_aria", Selectors::WEB_SEARCH_TOGGLE_ARIA),
+];
+
+impl ChatMode {
+    /// Toggles to set, in the order they are clicked
+    ///
+    /// Long thinking builds on thinking, so thinking is set first. Pages
+    /// without a long thinking toggle are fine unless it has to be turned on.
+    pub fn toggles(&self) -> Vec<ModeToggle> {
+        let toggle = |name, selectors, on, optional| ModeToggle {
+            name,
+            selectors,
+            on,
+            optional,
+        };
+        let mut toggles = Vec::new();
+        let reasoning = match self.reasoning {
+            Reasoning::Unchanged => None,
+            Reasoning::Standard => Some((false, false)),
+            Reasoning::Thinking => Some((true, false)),
+            Reasoning::LongThinking => Some((true, true)),
+        };
+        if let Some((thinking, long_thinking)) = reasoning {
+            toggles.push(toggle("thinking", THINKING, thinking, false));
+            toggles.push(toggle(
+                "long_thinking",
+                LONG_THINKING,
+                long_thinking,
+                !long_thinking,
+            ));
+        }
+        match self.web_search {
+            WebSearch::Unchanged => {}
+            WebSearch::On => toggles.push(toggle("web_search", WEB_SEARCH, true, false)),
+            WebSearch::Off => toggles.push(toggle("web_search", WEB_SEARCH, false, false)),
+        }
+        toggles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn states(mode: ChatMode) -> Vec<(&'static str, bool, bool)> {
+        mode.toggles()
+            .into_iter()
+            .map(|toggle| (toggle.name, toggle.on, toggle.optional))
+            .collect()
+    }
+
+    #[test]
+    fn test_unchanged_mode_sets_nothing() {
+        assert!(ChatMode::default().toggles().is_empty());
+    }
+
+    #[test]
+    fn test_reasoning_sets_thinking_before_long_thinking() {
+        let mode = |reasoning| ChatMode {
+            reasoning,
+            ..Default::default()
+        };
+        assert_eq!(
+            states(mode(Reasoning::LongThinking)),
+            vec![("thinking", true, false), ("long_thinking", true, false)]
+        );
+        assert_eq!(
+            states(mode(Reasoning::Thinking)),
+            vec![("thinking", true, false), ("long_thinking", false, true)]
+        );
+        assert_eq!(
+            states(mode(Reasoning::Standard)),
+            vec![("thinking", false, false), ("long_thinking", false, true)]
+        );
+    }
+
+    #[test]
+    fn test_web_search_alone() {
+        let mode = ChatMode {
+            web_search: WebSearch::Off,
+            ..Default::default()
+        };
+        assert_eq!(states(mode), vec![("web_search", false, false)]);
+    }
+
+    #[test]
+    fn test_serde_names() {
+        let mode: ChatMode = serde_json::from_str(r#"{"reasoning": "long_thinking"}"#).unwrap();
+        assert_eq!(mode.reasoning, Reasoning::LongThinking);
+        assert_eq!(mode.web_search, WebSearch::Unchanged);
+        assert_eq!(
+            serde_json::to_value(mode).unwrap(),
+            serde_json::json!({ "reasoning": "long_thinking", "web_search": "unchanged" })
+        );
+    }
+}
@@ -11,6 +11,9 @@ impl Selectors {
     pub const TEXTAREA_LOWERCASE_ASK: &str = "textarea[placeholder*=\"ask\"]";
     pub const TEXTAREA_TESTID: &str = "textarea[data-testid]";
     pub const TEXTAREA_ANY: &str = "textarea";
+    /// Sends in a row without `CHAT_INPUT` matching before the injection
+    /// stats warn about it
+    pub const PRIMARY_INPUT_MISS_WARNING: u32 = 3;
     pub const SEND_BUTTON: &str = ".send-button-container:not(.disabled)";
     pub const SEND_BUTTON_FALLBACK: &str = ".send-button-container";
     pub const SUBMIT_BUTTON: &str = "button[type=\"submit\"]";
@@ -22,7 +25,16 @@ impl Selectors {
     pub const STOP_BUTTON_ARIA_CAP: &str = "button[aria-label*=\"Stop\" i]";
     pub const CANCEL_BUTTON_ARIA: &str = "button[aria-label*=\"cancel\" i]";
     pub const STOP_BUTTON_DATA_TESTID: &str = "button[data-testid*=\"stop\" i]";
-    pub const DATA_SIDEBAR: &str = "[data-sidebar]";
+    /// Chat mode toggles next to the input; "thinking" also matches the
+    /// long thinking toggle's label, so that one is excluded
+    pub const THINKING_TOGGLE_TESTID: &str =
+        "[data-testid*=\"thinking\" i]:not([data-testid*=\"long\" i])";
+    pub const THINKING_TOGGLE_ARIA: &str =
+        "button[aria-label*=\"thinking\" i]:not([aria-label*=\"long\" i])";
+    pub const LONG_THINKING_TOGGLE_TESTID: &str = "[data-testid*=\"long-thinking\" i]";
+    pub const LONG_THINKING_TOGGLE_ARIA: &str = "button[aria-label*=\"long thinking\" i]";
+    pub const WEB_SEARCH_TOGGLE_TESTID: &str = "[data-testid*=\"web-search\" i]";
+    pub const WEB_SEARCH_TOGGLE_ARIA: &str = "button[aria-label*=\"web search\" i]";
 }
 
 /// Timeout values in milliseconds
@@ -38,19 +50,62 @@ impl Timeouts {
     pub const FOCUS_DELAY: u64 = 50;
     pub const SUBMIT_DELAY: u64 = 300;
     pub const FALLBACK_LOAD_TIMEOUT: u64 = 500;
-    pub const CONNECTIVITY_CHECK_DELAY: u64 = 5000;
+    /// Time between probes of the Kimi host while it is reachable
+    pub const CONNECTIVITY_PROBE_INTERVAL: u64 = 30000;
+    /// First retry delay after a failed probe; doubles while offline
+    pub const CONNECTIVITY_RETRY_DELAY: u64 = 5000;
+    pub const CONNECTIVITY_MAX_BACKOFF: u64 = 60000;
+    pub const CONNECTIVITY_PROBE_TIMEOUT: u64 = 5000;
+    /// Probes slower than this report a degraded connection
+    pub const CONNECTIVITY_SLOW_PROBE: u64 = 3000;
+    /// Consecutive failed probes before the host counts as offline
+    pub const CONNECTIVITY_FAILURES_BEFORE_OFFLINE: u32 = 3;
     pub const RESPONSE_WATCHER_INTERVAL: u64 = 500;
     pub const RESPONSE_WATCHER_INITIAL_DELAY: u64 = 2000;
     pub const RESPONSE_WATCHER_MAX_CHECKS: u32 = 600;
     pub const INJECTION_MAX_RETRIES: u32 = 15;
+    pub const PROXY_TEST: u64 = 10000;
+    pub const DOWNLOAD_PROGRESS_INTERVAL: u64 = 1000;
+    /// How long a download's file may stop growing before it counts as failed
+    pub const DOWNLOAD_STALL_TIMEOUT: u64 = 300000;
+    pub const CONVERSATION_OBSERVER_DEBOUNCE: u64 = 500;
+    /// How often outbox delivery checks whether the previous response finished
+    pub const OUTBOX_POLL_INTERVAL: u64 = 500;
+    /// Attempts at sending a prompt, including the first, before giving up
+    pub const DELIVERY_MAX_ATTEMPTS: u32 = 3;
+    /// Wait before the first retry of a failed send; doubles per retry
+    pub const DELIVERY_RETRY_DELAY: u64 = 1000;
+    pub const DELIVERY_MAX_BACKOFF: u64 = 8000;
+    /// Extra wait for the page's injection result beyond `INJECTION_TOTAL`
+    pub const DELIVERY_RESULT_GRACE: u64 = 2000;
 }
 
-/// Application URLs
+/// Application URLs, relative to the configured origin
 pub struct Urls;
 
 impl Urls {
-    pub const CHAT: &str = "https://www.kimi.com/";
-    pub const BOT: &str = "https://www.kimi.com/bot";
+    pub const DEFAULT_ORIGIN: &str = "https://www.kimi.com";
+    pub const CHAT_PATH: &str = "/";
+    pub const BOT_PATH: &str = "/bot";
+    /// Path prefix of individual conversations, e.g. `/chat/<id>`
+    pub const CONVERSATION_PATH_PREFIX: &str = "/chat/";
+    /// Static asset domains treated as first-party for every origin
+    pub const STATIC_DOMAINS: &[&str] = &["moonshot.cn"];
+    pub const OFFLINE_PAGE: &str = "tauri://localhost/index.html";
+    /// Query parameters removed from external links besides `utm_*`
+    pub const TRACKING_PARAMS: &[&str] = &[
+        "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "twclid", "igshid",
+        "mc_cid", "mc_eid", "_hsenc", "_hsmi", "mkt_tok", "ref_src",
+    ];
+}
+
+/// `kimi://` links handled by the app
+pub struct DeepLinks;
+
+impl DeepLinks {
+    pub const SCHEME: &str = "kimi";
+    /// Same limit as the launcher input
+    pub const MAX_MESSAGE_CHARS: usize = 5000;
 }
 
 /// Style injection configuration
@@ -65,5 +120,34 @@ impl Styles {
 pub struct Storage;
 
 impl Storage {
-    pub const RELOAD_GUARD_KEY: &str = "__kimi_sw_reload";
+    pub const SETTINGS_FILE: &str = "settings.json";
+    pub const SETTINGS_KEY: &str = "app_settings";
+    pub const OUTBOX_FILE: &str = "outbox.json";
+    pub const OUTBOX_KEY: &str = "entries";
+    /// Most launcher prompts kept while offline
+    pub const OUTBOX_MAX_ENTRIES: usize = 50;
+    pub const STATS_FILE: &str = "injection-stats.json";
+    pub const STATS_KEY: &str = "injection";
+}
+
+/// Webview zoom limits
+pub struct Zoom;
+
+impl Zoom {
+    pub const DEFAULT: f64 = 1.0;
+    pub const MIN: f64 = 0.5;
+    pub const MAX: f64 = 3.0;
+    pub const STEP: f64 = 0.1;
+}
+
+/// Tray icon and menu configuration
+pub struct Tray;
+
+impl Tray {
+    pub const ID: &str = "main";
+    pub const RECENT_DOWNLOADS: usize = 5;
+    pub const RECENT_CONVERSATIONS: usize = 5;
+    pub const RECENT_PROMPTS: usize = 5;
+    /// Longest menu label before it is shortened with an ellipsis
+    pub const LABEL_MAX_CHARS: usize = 48;
 }
@@ -0,0 +1,405 @@
+//! Pages a launcher prompt can be sent to
+//!
+//! The regular chat and Kimi Claw are built in; more Kimi agents or pages
+//! can be added in settings. Each destination says where it lives on the
+//! Kimi origin, how long its page needs before the message goes in, and
+//! which selectors find its input and send button when Kimi's own do not.
+//! The launcher picks one by shortcut or by a prefix typed before the
+//! message, e.g. `/claw`.
+
+use serde::{Deserialize, Serialize};
+use tauri::Url;
+
+use crate::wrappers::{
+    config::Urls,
+    endpoints::Endpoints,
+    error::{WrapperError, WrapperResult},
+    timing::TimingSettings,
+};
+
+/// How long to wait after opening a destination's page before sending
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Readiness {
+    /// The `page_load_wait` timing
+    #[default]
+    PageLoad,
+    /// The `bot_page_load_wait` timing, for agent pages that render later
+    AgentPageLoad,
+    /// A fixed wait in milliseconds
+    Delay(u64),
+}
+
+impl Readiness {
+    pub fn wait(&self, timing: &TimingSettings) -> u64 {
+        match self {
+            Readiness::PageLoad => timing.page_load_wait,
+            Readiness::AgentPageLoad => timing.bot_page_load_wait,
+            Readiness::Delay(ms) => *ms,
+        }
+    }
+}
+
+/// Selectors tried before Kimi's own when looking for the input and send
+/// button; empty lists leave only Kimi's
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SelectorProfile {
+    pub input: Vec<String>,
+    pub send: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Destination {
+    /// Stable name stored with queued and recent prompts
+    pub id: String,
+    /// Shown in the launcher
+    pub name: String,
+    /// Path of the page on the Kimi origin, e.g. `/bot`
+    pub path: String,
+    #[serde(default)]
+    pub readiness: Readiness,
+    #[serde(default)]
+    pub selectors: SelectorProfile,
+    /// Letter or digit picking the destination with Cmd/Ctrl in the launcher
+    #[serde(default)]
+    pub shortcut: Option<char>,
+    /// Typed before a message to send it here, e.g. `/claw`
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+/// Launcher shortcuts taken by the launcher itself or by text editing
+const RESERVED_SHORTCUTS: &str = "ACKVXYZ";
+
+impl Destination {
+    /// The regular chat, where prompts go unless another destination is picked
+    pub const CHAT: &'static str = "chat";
+    pub const CLAW: &'static str = "claw";
+
+    /// Built-in destinations, the chat first
+    pub fn built_in() -> Vec<Destination> {
+        vec![
+            Destination {
+                id: Self::CHAT.to_string(),
+                name: "Kimi".to_string(),
+                path: Urls::CHAT_PATH.to_string(),
+                readiness: Readiness::PageLoad,
+                selectors: SelectorProfile::default(),
+                shortcut: None,
+                prefix: None,
+            },
+            Destination {
+                id: Self::CLAW.to_string(),
+                name: "Kimi Claw".to_string(),
+                path: Urls::BOT_PATH.to_string(),
+                readiness: Readiness::AgentPageLoad,
+                selectors: SelectorProfile::default(),
+                shortcut: Some('B'),
+                prefix: Some("/claw".to_string()),
+            },
+        ]
+    }
+
+    /// Whether sending here may stay on the conversation open in the main
+    /// window; other destinations always open their own page
+    pub fn is_chat(&self) -> bool {
+        self.id == Self::CHAT
+    }
+
+    /// The page on `endpoints`' origin, or `None` if the path leaves it
+    pub fn url(&self, endpoints: &Endpoints) -> Option<Url> {
+        endpoints.join(&self.path)
+    }
+}
+
+/// Built-in destinations followed by the ones added in settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Destinations {
+    all: Vec<Destination>,
+}
+
+impl Destinations {
+    pub fn new(custom: &[Destination]) -> Self {
+        let mut all = Destination::built_in();
+        all.extend(custom.iter().cloned());
+        Self { all }
+    }
+
+    pub fn all(&self) -> &[Destination] {
+        &self.all
+    }
+
+    /// Destination with `id`, or the chat when there is none, e.g. for a
+    /// queued prompt whose destination was removed since
+    pub fn get(&self, id: &str) -> &Destination {
+        self.all.iter().find(|d| d.id == id).unwrap_or(&self.all[0])
+    }
+
+    /// Destination named by a prefix at the start of `message`, with the
+    /// message that follows it
+    ///
+    /// The prefix must be followed by whitespace and some text, so typing
+    /// just `/claw` sends that text to the chat.
+    pub fn split_prefix<'a>(&self, message: &'a str) -> Option<(&Destination, &'a str)> {
+        let trimmed = message.trim_start();
+        self.all.iter().find_map(|destination| {
+            let prefix = destination.prefix.as_deref()?;
+            let rest = trimmed.strip_prefix(prefix)?;
+            if !rest.starts_with(char::is_whitespace) || rest.trim().is_empty() {
+                return None;
+            }
+            Some((destination, rest.trim_start()))
+        })
+    }
+}
+
+impl Default for Destinations {
+    fn default() -> Self {
+        Self::new(&[])
+    }
+}
+
+/// Check destinations added in settings against each other and the built-in ones
+pub fn validate(custom: &[Destination]) -> WrapperResult<()> {
+    let invalid = |destination: &Destination, message: &str| WrapperError::InvalidSettings {
+        field: "destinations".to_string(),
+        message: format!("destination '{}': {}", destination.id, message),
+    };
+    let endpoints = Endpoints::default();
+    let mut seen = Destination::built_in();
+
+    for destination in custom {
+        let id_chars =
+            |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_';
+        if destination.id.is_empty() || !destination.id.chars().all(id_chars) {
+            return Err(invalid(destination, "id must use only a-z, 0-9, - and _"));
+        }
+        if seen.iter().any(|d| d.id == destination.id) {
+            return Err(invalid(destination, "id is already used"));
+        }
+        if destination.name.trim().is_empty() {
+            return Err(invalid(destination, "name must not be empty"));
+        }
+        if !destination.path.starts_with('/') || destination.url(&endpoints).is_none() {
+            return Err(invalid(
+                destination,
+                "path must start with / and stay on the Kimi origin",
+            ));
+        }
+        if let Readiness::Delay(ms) = destination.readiness {
+            if ms > 10_000 {
+                return Err(invalid(destination, "delay must be at most 10000 ms"));
+            }
+        }
+        let mut selectors = destination
+            .selectors
+            .input
+            .iter()
+            .chain(&destination.selectors.send);
+        if selectors.any(|s| s.trim().is_empty()) {
+            return Err(invalid(destination, "selectors must not be empty"));
+        }
+        if let Some(shortcut) = destination.shortcut {
+            let key = shortcut.to_ascii_uppercase();
+            if !key.is_ascii_alphanumeric() || RESERVED_SHORTCUTS.contains(key) {
+                return Err(invalid(
+                    destination,
+                    &format!(
+                        "shortcut must be a letter or digit other than {}",
+                        RESERVED_SHORTCUTS
+                    ),
+                ));
+            }
+            if seen
+                .iter()
+                .any(|d| d.shortcut.map(|s| s.to_ascii_uppercase()) == Some(key))
+            {
+                return Err(invalid(destination, "shortcut is already used"));
+            }
+        }
+        if let Some(prefix) = &destination.prefix {
+            if prefix.len() < 2
+                || !prefix.starts_with('/')
+                || prefix.chars().any(char::is_whitespace)
+            {
+                return Err(invalid(
+                    destination,
+                    "prefix must be / followed by a word, e.g. /docs",
+                ));
+            }
+            if seen.iter().any(|d| d.prefix.as_ref() == Some(prefix)) {
+                return Err(invalid(destination, "prefix is already used"));
+            }
+        }
+        seen.push(destination.clone());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom(id: &str) -> Destination {
+        Destination {
+            id: id.to_string(),
+            name: "Researcher".to_string(),
+            path: "/agent/researcher".to_string(),
+            readiness: Readiness::Delay(2_000),
+            selectors: SelectorProfile {
+                input: vec!["textarea.agent-input".to_string()],
+                send: Vec::new(),
+            },
+            shortcut: Some('r'),
+            prefix: Some("/research".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_built_in_destinations() {
+        let destinations = Destinations::default();
+        let endpoints = Endpoints::default();
+        let chat = destinations.get(Destination::CHAT);
+        assert!(chat.is_chat());
+        assert_eq!(
+            chat.url(&endpoints).unwrap().as_str(),
+            "https://www.kimi.com/"
+        );
+        let claw = destinations.get(Destination::CLAW);
+        assert!(!claw.is_chat());
+        assert_eq!(
+            claw.url(&endpoints).unwrap().as_str(),
+            "https://www.kimi.com/bot"
+        );
+        assert_eq!(claw.readiness, Readiness::AgentPageLoad);
+    }
+
+    #[test]
+    fn test_unknown_id_falls_back_to_chat() {
+        assert!(Destinations::default().get("removed").is_chat());
+    }
+
+    #[test]
+    fn test_readiness_waits() {
+        let timing = TimingSettings::default();
+        assert_eq!(Readiness::PageLoad.wait(&timing), timing.page_load_wait);
+        assert_eq!(
+            Readiness::AgentPageLoad.wait(&timing),
+            timing.bot_page_load_wait
+        );
+        assert_eq!(Readiness::Delay(2_500).wait(&timing), 2_500);
+    }
+
+    #[test]
+    fn test_split_prefix() {
+        let destinations = Destinations::new(&[custom("researcher")]);
+        let (destination, message) = destinations.split_prefix("  /claw  hello there").unwrap();
+        assert_eq!(destination.id, Destination::CLAW);
+        assert_eq!(message, "hello there");
+        let (destination, message) = destinations.split_prefix("/research\nsources?").unwrap();
+        assert_eq!(destination.id, "researcher");
+        assert_eq!(message, "sources?");
+
+        for message in ["/claw", "/claw   ", "/clawed it", "hello /claw there"] {
+            assert!(
+                destinations.split_prefix(message).is_none(),
+                "{:?}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_serde_shape() {
+        let destination: Destination = serde_json::from_value(serde_json::json!({
+            "id": "docs",
+            "name": "Docs",
+            "path": "/docs",
+            "readiness": { "delay": 1500 },
+        }))
+        .unwrap();
+        assert_eq!(destination.readiness, Readiness::Delay(1_500));
+        assert_eq!(destination.selectors, SelectorProfile::default());
+        assert_eq!(destination.shortcut, None);
+        assert_eq!(
+            serde_json::to_value(Readiness::AgentPageLoad).unwrap(),
+            serde_json::json!("agent_page_load")
+        );
+    }
+
+    #[test]
+    fn test_validate_accepts_custom_destination() {
+        assert!(validate(&[custom("researcher")]).is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_invalid_destinations() {
+        type Change = Box<dyn Fn(&mut Destination)>;
+        let cases: Vec<(&str, Change)> = vec![
+            (
+                "id must use",
+                Box::new(|d| d.id = "Research Bot".to_string()),
+            ),
+            (
+                "id is already used",
+                Box::new(|d| d.id = "claw".to_string()),
+            ),
+            ("name", Box::new(|d| d.name = " ".to_string())),
+            ("path", Box::new(|d| d.path = "agent".to_string())),
+            (
+                "path",
+                Box::new(|d| d.path = "//example.com/agent".to_string()),
+            ),
+            ("path", Box::new(|d| d.path = "/\\example.com".to_string())),
+            (
+                "delay",
+                Box::new(|d| d.readiness = Readiness::Delay(60_000)),
+            ),
+            (
+                "selectors",
+                Box::new(|d| d.selectors.send = vec![String::new()]),
+            ),
+            ("shortcut must", Box::new(|d| d.shortcut = Some('k'))),
+            ("shortcut must", Box::new(|d| d.shortcut = Some('/'))),
+            (
+                "shortcut is already used",
+                Box::new(|d| d.shortcut = Some('b')),
+            ),
+            (
+                "prefix must",
+                Box::new(|d| d.prefix = Some("research".to_string())),
+            ),
+            (
+                "prefix is already used",
+                Box::new(|d| d.prefix = Some("/claw".to_string())),
+            ),
+        ];
+        for (expected, change) in cases {
+            let mut destination = custom("researcher");
+            change(&mut destination);
+            match validate(&[destination]) {
+                Err(WrapperError::InvalidSettings { field, message }) => {
+                    assert_eq!(field, "destinations");
+                    assert!(
+                        message.contains(expected),
+                        "{:?} should mention {:?}",
+                        message,
+                        expected
+                    );
+                }
+                other => panic!(
+                    "expected InvalidSettings for {:?}, got {:?}",
+                    expected, other
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_duplicates_among_custom() {
+        let mut second = custom("writer");
+        second.shortcut = Some('w');
+        assert!(validate(&[custom("researcher"), second]).is_err());
+    }
+}
@@ -11,6 +11,13 @@ pub enum WrapperError {
     /// Target window not found
     WindowNotFound { window_label: String },
 
+    /// A window could not be shown, hidden, focused or navigated
+    WindowOperationFailed {
+        window_label: String,
+        operation: String,
+        message: String,
+    },
+
     /// JavaScript evaluation error
     EvalError { message: String },
 
@@ -28,6 +35,18 @@ pub enum WrapperError {
 
     /// Settings validation failed
     InvalidSettings { field: String, message: String },
+
+    /// Network connection could not be established
+    ConnectionFailed { message: String },
+
+    /// A JavaScript template and its parameters do not match
+    TemplateError { template: String, message: String },
+
+    /// Every attempt at sending a prompt failed
+    DeliveryFailed { attempts: u32, message: String },
+
+    /// The operating system refused an operation, e.g. a login item change
+    SystemError { operation: String, message: String },
 }
 
 impl fmt::Display for WrapperError {
@@ -39,6 +58,17 @@ impl fmt::Display for WrapperError {
             WrapperError::WindowNotFound { window_label } => {
                 write!(f, "Window '{}' not found", window_label)
             }
+            WrapperError::WindowOperationFailed {
+                window_label,
+                operation,
+                message,
+            } => {
+                write!(
+                    f,
+                    "Could not {} window '{}': {}",
+                    operation, window_label, message
+                )
+            }
             WrapperError::EvalError { message } => {
                 write!(f, "JavaScript evaluation error: {}", message)
             }
@@ -64,6 +94,26 @@ impl fmt::Display for WrapperError {
             WrapperError::InvalidSettings { field, message } => {
                 write!(f, "Invalid settings field '{}': {}", field, message)
             }
+            WrapperError::ConnectionFailed { message } => {
+                write!(f, "Connection failed: {}", message)
+            }
+            WrapperError::TemplateError { template, message } => {
+                write!(
+                    f,
+                    "Template '{}' could not be rendered: {}",
+                    template, message
+                )
+            }
+            WrapperError::DeliveryFailed { attempts, message } => {
+                write!(
+                    f,
+                    "Could not send the message after {} attempts: {}",
+                    attempts, message
+                )
+            }
+            WrapperError::SystemError { operation, message } => {
+                write!(f, "Could not {}: {}", operation, message)
+            }
         }
     }
 }
@@ -86,9 +136,164 @@ impl From<tauri::Error> for WrapperError {
     }
 }
 
+impl WrapperError {
+    /// Map a failed `operation` on `window` to [`WrapperError::WindowOperationFailed`]
+    pub fn window<R: tauri::Runtime>(
+        window: &tauri::WebviewWindow<R>,
+        operation: &str,
+    ) -> impl FnOnce(tauri::Error) -> Self {
+        let window_label = window.label().to_string();
+        let operation = operation.to_string();
+        move |err| WrapperError::WindowOperationFailed {
+            window_label,
+            operation,
+            message: err.to_string(),
+        }
+    }
+}
+
 /// Result type for wrapper operations
 pub type WrapperResult<T> = Result<T, WrapperError>;
 
+/// Stable name of an error, one per [`WrapperError`] variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InjectionFailed,
+    WindowNotFound,
+    WindowOperationFailed,
+    EvalError,
+    SerializationError,
+    Timeout,
+    InvalidUrl,
+    StoreError,
+    InvalidSettings,
+    ConnectionFailed,
+    TemplateError,
+    DeliveryFailed,
+    SystemError,
+}
+
+/// Broad kind of an error, for frontends that only distinguish a few cases
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// Running scripts in or sending prompts to the Kimi page
+    Injection,
+    Window,
+    /// Rejected input such as a settings value or URL
+    Validation,
+    Network,
+    Storage,
+    System,
+    /// A bug rather than something the user can fix
+    Internal,
+}
+
+impl WrapperError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            WrapperError::InjectionFailed { .. } => ErrorCode::InjectionFailed,
+            WrapperError::WindowNotFound { .. } => ErrorCode::WindowNotFound,
+            WrapperError::WindowOperationFailed { .. } => ErrorCode::WindowOperationFailed,
+            WrapperError::EvalError { .. } => ErrorCode::EvalError,
+            WrapperError::SerializationError { .. } => ErrorCode::SerializationError,
+            WrapperError::Timeout { .. } => ErrorCode::Timeout,
+            WrapperError::InvalidUrl { .. } => ErrorCode::InvalidUrl,
+            WrapperError::StoreError { .. } => ErrorCode::StoreError,
+            WrapperError::InvalidSettings { .. } => ErrorCode::InvalidSettings,
+            WrapperError::ConnectionFailed { .. } => ErrorCode::ConnectionFailed,
+            WrapperError::TemplateError { .. } => ErrorCode::TemplateError,
+            WrapperError::DeliveryFailed { .. } => ErrorCode::DeliveryFailed,
+            WrapperError::SystemError { .. } => ErrorCode::SystemError,
+        }
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            WrapperError::InjectionFailed { .. }
+            | WrapperError::EvalError { .. }
+            | WrapperError::DeliveryFailed { .. } => ErrorCategory::Injection,
+            WrapperError::WindowNotFound { .. } | WrapperError::WindowOperationFailed { .. } => {
+                ErrorCategory::Window
+            }
+            WrapperError::InvalidUrl { .. } | WrapperError::InvalidSettings { .. } => {
+                ErrorCategory::Validation
+            }
+            WrapperError::Timeout { .. } | WrapperError::ConnectionFailed { .. } => {
+                ErrorCategory::Network
+            }
+            WrapperError::StoreError { .. } => ErrorCategory::Storage,
+            WrapperError::SystemError { .. } => ErrorCategory::System,
+            WrapperError::SerializationError { .. } | WrapperError::TemplateError { .. } => {
+                ErrorCategory::Internal
+            }
+        }
+    }
+
+    /// The fields a frontend may act on; everything else is only in the message
+    fn details(&self) -> Option<serde_json::Value> {
+        use serde_json::json;
+
+        match self {
+            WrapperError::InjectionFailed { context, .. } => Some(json!({ "context": context })),
+            WrapperError::WindowNotFound { window_label } => {
+                Some(json!({ "window_label": window_label }))
+            }
+            WrapperError::WindowOperationFailed {
+                window_label,
+                operation,
+                ..
+            } => Some(json!({ "window_label": window_label, "operation": operation })),
+            WrapperError::Timeout {
+                operation,
+                duration_ms,
+            } => Some(json!({ "operation": operation, "duration_ms": duration_ms })),
+            WrapperError::InvalidUrl { url, .. } => Some(json!({ "url": url })),
+            WrapperError::StoreError { operation, .. }
+            | WrapperError::SystemError { operation, .. } => {
+                Some(json!({ "operation": operation }))
+            }
+            WrapperError::InvalidSettings { field, .. } => Some(json!({ "field": field })),
+            WrapperError::TemplateError { template, .. } => Some(json!({ "template": template })),
+            WrapperError::DeliveryFailed { attempts, .. } => Some(json!({ "attempts": attempts })),
+            WrapperError::EvalError { .. }
+            | WrapperError::SerializationError { .. }
+            | WrapperError::ConnectionFailed { .. } => None,
+        }
+    }
+}
+
+/// Error returned by Tauri commands, mirrored by `CommandError` in `src/types.ts`
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct CommandError {
+    pub code: ErrorCode,
+    pub category: ErrorCategory,
+    /// Human readable description, the `WrapperError` display text
+    pub message: String,
+    pub details: Option<serde_json::Value>,
+}
+
+impl From<WrapperError> for CommandError {
+    fn from(err: WrapperError) -> Self {
+        CommandError {
+            code: err.code(),
+            category: err.category(),
+            message: err.to_string(),
+            details: err.details(),
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Result type for Tauri commands
+pub type CommandResult<T> = Result<T, CommandError>;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -123,6 +328,96 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_command_error_serializes_code_category_and_details() {
+        let err = WrapperError::InvalidSettings {
+            field: "timing.retry_delay".to_string(),
+            message: "too long".to_string(),
+        };
+        let value = serde_json::to_value(CommandError::from(err)).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "code": "invalid_settings",
+                "category": "validation",
+                "message": "Invalid settings field 'timing.retry_delay': too long",
+                "details": { "field": "timing.retry_delay" },
+            })
+        );
+
+        let err = WrapperError::ConnectionFailed {
+            message: "refused".to_string(),
+        };
+        let value = serde_json::to_value(CommandError::from(err)).unwrap();
+        assert_eq!(value["code"], "connection_failed");
+        assert_eq!(value["details"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_error_codes_match_typescript() {
+        let text = |s: &str| s.to_string();
+        let errors = [
+            WrapperError::InjectionFailed {
+                context: text("message"),
+                source: text(""),
+            },
+            WrapperError::WindowNotFound {
+                window_label: text("main"),
+            },
+            WrapperError::WindowOperationFailed {
+                window_label: text("main"),
+                operation: text("show"),
+                message: text(""),
+            },
+            WrapperError::EvalError { message: text("") },
+            WrapperError::SerializationError { message: text("") },
+            WrapperError::Timeout {
+                operation: text("send"),
+                duration_ms: 1,
+            },
+            WrapperError::InvalidUrl {
+                url: text(""),
+                reason: text(""),
+            },
+            WrapperError::StoreError {
+                operation: text("save"),
+                message: text(""),
+            },
+            WrapperError::InvalidSettings {
+                field: text("proxy.url"),
+                message: text(""),
+            },
+            WrapperError::ConnectionFailed { message: text("") },
+            WrapperError::TemplateError {
+                template: text("message"),
+                message: text(""),
+            },
+            WrapperError::DeliveryFailed {
+                attempts: 3,
+                message: text(""),
+            },
+            WrapperError::SystemError {
+                operation: text("update open at login"),
+                message: text(""),
+            },
+        ];
+        let types = include_str!("../../../src/types.ts");
+        for err in errors {
+            let command = CommandError::from(err);
+            for name in [
+                serde_json::to_value(command.code).unwrap(),
+                serde_json::to_value(command.category).unwrap(),
+            ] {
+                let quoted = format!("'{}'", name.as_str().unwrap());
+                assert!(
+                    types.contains(&quoted),
+                    "{} is missing from src/types.ts",
+                    quoted
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_error_debug_format() {
         let err = WrapperError::Timeout {
@@ -0,0 +1,59 @@
+//! Running JavaScript in a webview
+//!
+//! The wrappers only need to hand a script to a page, so they go through
+//! [`Evaluator`] instead of `WebviewWindow` directly. Tests use
+//! [`RecordingEvaluator`] to see what would have been injected without a
+//! window.
+
+use tauri::WebviewWindow;
+
+/// Something that can run a script in a page
+pub trait Evaluator {
+    /// Run `js`; the error is the webview's own message
+    fn evaluate(&self, js: &str) -> Result<(), String>;
+}
+
+impl Evaluator for WebviewWindow {
+    fn evaluate(&self, js: &str) -> Result<(), String> {
+        self.eval(js).map_err(|e| e.to_string())
+    }
+}
+
+/// Records scripts instead of running them, optionally failing one call
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct RecordingEvaluator {
+    scripts: std::cell::RefCell<Vec<String>>,
+    calls: std::cell::Cell<usize>,
+    /// 0-based call to fail, with the error it returns
+    failure: Option<(usize, String)>,
+}
+
+#[cfg(test)]
+impl RecordingEvaluator {
+    /// Fail call `index` (0-based) with `message`; the failed script is not recorded
+    pub fn failing_at(index: usize, message: &str) -> Self {
+        Self {
+            failure: Some((index, message.to_string())),
+            ..Default::default()
+        }
+    }
+
+    pub fn scripts(&self) -> Vec<String> {
+        self.scripts.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+impl Evaluator for RecordingEvaluator {
+    fn evaluate(&self, js: &str) -> Result<(), String> {
+        let call = self.calls.replace(self.calls.get() + 1);
+        match &self.failure {
+            Some((index, message)) if *index == call => Err(message.clone()),
+            _ => {
+                self.scripts.borrow_mut().push(js.to_string());
+                Ok(())
+            }
+        }
+    }
+}
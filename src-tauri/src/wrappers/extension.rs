@@ -1,180 +1,171 @@
 //! WebviewWindow extension trait for wrapper operations
 
-use tauri::{AppHandle, Emitter, WebviewWindow};
+use tauri::{AppHandle, Emitter};
 
 use crate::wrappers::{
-    config,
+    chat_mode::ChatMode,
+    destinations::SelectorProfile,
+    endpoints::Endpoints,
     error::{WrapperError, WrapperResult},
-    templates::{
-        CONNECTIVITY_JS, INJECT_MESSAGE_JS, LINK_INTERCEPTOR_JS, RESPONSE_WATCHER_JS,
-        TITLEBAR_OVERLAP_JS,
-    },
-    utils::build_js,
+    evaluator::Evaluator,
+    params::{self, TemplateParams},
+    templates::{CONNECTIVITY_JS, QUIT_SHORTCUT_JS, ZOOM_SHORTCUTS_JS},
+    timing::TimingSettings,
 };
 
-/// Extension trait for WebviewWindow to provide wrapper injection methods
+/// Wrapper injections, available on anything that can evaluate scripts
 pub trait WrapperExt {
     /// Inject titlebar overlap styles (macOS only)
     fn inject_titlebar_styles(&self) -> WrapperResult<()>;
 
-    /// Inject a message into the chat input
-    fn inject_message(&self, message: &str) -> WrapperResult<()>;
+    /// Inject a message into the chat input, also trying `selectors`
+    fn inject_message(
+        &self,
+        message: &str,
+        selectors: &SelectorProfile,
+        timing: &TimingSettings,
+    ) -> WrapperResult<()>;
 
     /// Inject response watcher for notification handling
-    fn inject_response_watcher(&self) -> WrapperResult<()>;
+    fn inject_response_watcher(&self, timing: &TimingSettings) -> WrapperResult<()>;
 
-    /// Inject connectivity monitoring
+    /// Inject the script switching Kimi's toggles to `mode`
+    fn inject_chat_mode(&self, mode: &ChatMode, timing: &TimingSettings) -> WrapperResult<()>;
+
+    /// Inject forwarding of browser online/offline events
     fn inject_connectivity(&self) -> WrapperResult<()>;
 
     /// Inject external link interceptor
-    fn inject_link_interceptor(&self) -> WrapperResult<()>;
+    fn inject_link_interceptor(
+        &self,
+        endpoints: &Endpoints,
+        rule_hosts: &[String],
+    ) -> WrapperResult<()>;
+
+    /// Inject keyboard shortcuts for zooming the page
+    fn inject_zoom_shortcuts(&self) -> WrapperResult<()>;
+
+    /// Inject the keyboard shortcut that quits the app
+    fn inject_quit_shortcut(&self) -> WrapperResult<()>;
+
+    /// Inject the observer reporting conversation title and URL changes
+    fn inject_conversation_observer(&self) -> WrapperResult<()>;
+
+    /// Show, update or hide the send status toast
+    fn inject_delivery_toast(
+        &self,
+        status: &'static str,
+        text: &str,
+        failed_id: Option<u64>,
+    ) -> WrapperResult<()>;
 }
 
-impl WrapperExt for WebviewWindow {
+impl<E: Evaluator> WrapperExt for E {
     fn inject_titlebar_styles(&self) -> WrapperResult<()> {
-        let js = build_js(
-            TITLEBAR_OVERLAP_JS,
-            &[
-                ("style_id", config::Styles::STYLE_ID),
-                ("header_padding", config::Styles::HEADER_PADDING),
-            ],
-        );
-        self.eval(&js).map_err(|e| WrapperError::InjectionFailed {
-            context: "titlebar styles".to_string(),
-            source: e.to_string(),
-        })
+        inject_params(self, &params::TitlebarStyles::default())
     }
 
-    fn inject_message(&self, message: &str) -> WrapperResult<()> {
-        use crate::wrappers::utils::escape_js;
-        use config::*;
-
-        let js = build_js(
-            INJECT_MESSAGE_JS,
-            &[
-                ("message", &escape_js(message)),
-                ("max_retries", &Timeouts::INJECTION_MAX_RETRIES.to_string()),
-                ("retry_delay", &Timeouts::RETRY_DELAY.to_string()),
-                ("total_timeout", &Timeouts::INJECTION_TOTAL.to_string()),
-                (
-                    "fallback_timeout",
-                    &Timeouts::FALLBACK_LOAD_TIMEOUT.to_string(),
-                ),
-                ("react_init_delay", &Timeouts::REACT_INIT_DELAY.to_string()),
-                ("focus_delay", &Timeouts::FOCUS_DELAY.to_string()),
-                ("submit_delay", &Timeouts::SUBMIT_DELAY.to_string()),
-                ("selector_chat_input", Selectors::CHAT_INPUT),
-                ("selector_contenteditable", Selectors::CONTENTEDITABLE),
-                ("selector_textarea_ask", Selectors::TEXTAREA_ASK),
-                ("selector_textarea_message", Selectors::TEXTAREA_MESSAGE),
-                (
-                    "selector_textarea_lowercase_ask",
-                    Selectors::TEXTAREA_LOWERCASE_ASK,
-                ),
-                ("selector_textarea_testid", Selectors::TEXTAREA_TESTID),
-                ("selector_textarea_any", Selectors::TEXTAREA_ANY),
-                ("selector_send_button", Selectors::SEND_BUTTON),
-                (
-                    "selector_send_button_fallback",
-                    Selectors::SEND_BUTTON_FALLBACK,
-                ),
-                ("selector_submit_button", Selectors::SUBMIT_BUTTON),
-                ("selector_send_aria", Selectors::SEND_ARIA_LABEL),
-                ("selector_send_aria_cap", Selectors::SEND_ARIA_LABEL_CAP),
-                ("selector_send_data_testid", Selectors::SEND_DATA_TESTID),
-                ("selector_form_button_last", Selectors::FORM_BUTTON_LAST),
-            ],
-        );
-        self.eval(&js).map_err(|e| WrapperError::InjectionFailed {
-            context: "chat message".to_string(),
-            source: e.to_string(),
-        })
+    fn inject_message(
+        &self,
+        message: &str,
+        selectors: &SelectorProfile,
+        timing: &TimingSettings,
+    ) -> WrapperResult<()> {
+        inject_params(
+            self,
+            &params::InjectMessage::new(message, timing).with_selectors(selectors),
+        )
     }
 
-    fn inject_response_watcher(&self) -> WrapperResult<()> {
-        use config::*;
-
-        let js = build_js(
-            RESPONSE_WATCHER_JS,
-            &[
-                (
-                    "check_interval",
-                    &Timeouts::RESPONSE_WATCHER_INTERVAL.to_string(),
-                ),
-                (
-                    "initial_delay",
-                    &Timeouts::RESPONSE_WATCHER_INITIAL_DELAY.to_string(),
-                ),
-                (
-                    "max_checks",
-                    &Timeouts::RESPONSE_WATCHER_MAX_CHECKS.to_string(),
-                ),
-                ("selector_stop_aria", Selectors::STOP_BUTTON_ARIA),
-                ("selector_stop_aria_cap", Selectors::STOP_BUTTON_ARIA_CAP),
-                ("selector_cancel_aria", Selectors::CANCEL_BUTTON_ARIA),
-                (
-                    "selector_stop_data_testid",
-                    Selectors::STOP_BUTTON_DATA_TESTID,
-                ),
-            ],
-        );
-        self.eval(&js).map_err(|e| WrapperError::InjectionFailed {
-            context: "response watcher".to_string(),
-            source: e.to_string(),
-        })
+    fn inject_response_watcher(&self, timing: &TimingSettings) -> WrapperResult<()> {
+        inject_params(self, &params::ResponseWatcher::new(timing))
+    }
+
+    fn inject_chat_mode(&self, mode: &ChatMode, timing: &TimingSettings) -> WrapperResult<()> {
+        inject_params(self, &params::SetChatMode::new(mode, timing))
     }
 
     fn inject_connectivity(&self) -> WrapperResult<()> {
-        use config::*;
-
-        let js = build_js(
-            CONNECTIVITY_JS,
-            &[
-                ("chat_url", Urls::CHAT),
-                ("reload_key", Storage::RELOAD_GUARD_KEY),
-                ("selector_data_sidebar", Selectors::DATA_SIDEBAR),
-                (
-                    "connectivity_check_delay",
-                    &Timeouts::CONNECTIVITY_CHECK_DELAY.to_string(),
-                ),
-            ],
-        );
-        self.eval(&js).map_err(|e| WrapperError::InjectionFailed {
-            context: "connectivity monitor".to_string(),
-            source: e.to_string(),
-        })
+        inject(self, "connectivity events", CONNECTIVITY_JS)
+    }
+
+    fn inject_link_interceptor(
+        &self,
+        endpoints: &Endpoints,
+        rule_hosts: &[String],
+    ) -> WrapperResult<()> {
+        inject_params(self, &params::LinkInterceptor::new(endpoints, rule_hosts))
     }
 
-    fn inject_link_interceptor(&self) -> WrapperResult<()> {
-        self.eval(LINK_INTERCEPTOR_JS)
-            .map_err(|e| WrapperError::InjectionFailed {
-                context: "link interceptor".to_string(),
-                source: e.to_string(),
-            })
+    fn inject_zoom_shortcuts(&self) -> WrapperResult<()> {
+        inject(self, "zoom shortcuts", ZOOM_SHORTCUTS_JS)
     }
+
+    fn inject_quit_shortcut(&self) -> WrapperResult<()> {
+        inject(self, "quit shortcut", QUIT_SHORTCUT_JS)
+    }
+
+    fn inject_conversation_observer(&self) -> WrapperResult<()> {
+        inject_params(self, &params::ConversationObserver::default())
+    }
+
+    fn inject_delivery_toast(
+        &self,
+        status: &'static str,
+        text: &str,
+        failed_id: Option<u64>,
+    ) -> WrapperResult<()> {
+        inject_params(
+            self,
+            &params::DeliveryToast {
+                status,
+                text: text.to_string(),
+                failed_id,
+            },
+        )
+    }
+}
+
+/// Run `js`, naming `context` if the webview rejects it
+fn inject<E: Evaluator + ?Sized>(target: &E, context: &str, js: &str) -> WrapperResult<()> {
+    target
+        .evaluate(js)
+        .map_err(|source| WrapperError::InjectionFailed {
+            context: context.to_string(),
+            source,
+        })
+}
+
+fn inject_params<E: Evaluator + ?Sized, P: TemplateParams>(
+    target: &E,
+    params: &P,
+) -> WrapperResult<()> {
+    inject(target, P::NAME, &params.render()?)
 }
 
 /// Apply all standard wrappers to the main window
-pub fn apply_all_wrappers(window: &WebviewWindow) {
+pub fn apply_all_wrappers<E: Evaluator>(window: &E, settings: &crate::AppSettings) {
+    let endpoints = settings.endpoints();
     // These all fail silently as requested
     let _ = window.inject_connectivity();
-    let _ = window.inject_link_interceptor();
+    let _ = window.inject_link_interceptor(&endpoints, &settings.link_routing.rule_hosts());
+    let _ = window.inject_conversation_observer();
 }
 
 /// Submit a message to the chat window with all necessary injections
-pub fn submit_chat_message(window: &WebviewWindow, message: &str) -> WrapperResult<()> {
-    window.inject_message(message)?;
-    window.inject_response_watcher()
+pub fn submit_chat_message<E: Evaluator>(
+    window: &E,
+    message: &str,
+    selectors: &SelectorProfile,
+    timing: &TimingSettings,
+) -> WrapperResult<()> {
+    window.inject_message(message, selectors, timing)?;
+    window.inject_response_watcher(timing)
 }
 
 /// Set offline state in the main window
-pub fn set_offline_state(window: &WebviewWindow) -> WrapperResult<()> {
-    window
-        .eval("document.getElementById('main-container').className = 'container offline';")
-        .map_err(|e| WrapperError::InjectionFailed {
-            context: "offline state".to_string(),
-            source: e.to_string(),
-        })
+pub fn set_offline_state<E: Evaluator>(window: &E, endpoints: &Endpoints) -> WrapperResult<()> {
+    inject_params(window, &params::OfflineState::new(endpoints))
 }
 
 /// Emit launcher shown event to the launcher window
@@ -182,7 +173,133 @@ pub fn emit_launcher_shown(app: &AppHandle) {
     let _ = app.emit("launcher-shown", ());
 }
 
+/// Emit launcher shown event with text to fill into the input
+///
+/// `new_chat` overrides the new-chat default for this message when set.
+pub fn emit_launcher_prefilled(app: &AppHandle, message: &str, new_chat: Option<bool>) {
+    let _ = app.emit(
+        "launcher-shown",
+        serde_json::json!({ "message": message, "new_chat": new_chat }),
+    );
+}
+
 /// Emit settings changed event
 pub fn emit_settings_changed(app: &AppHandle, settings: &crate::AppSettings) {
     let _ = app.emit("settings-changed", settings);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wrappers::evaluator::RecordingEvaluator;
+
+    fn endpoints() -> Endpoints {
+        crate::AppSettings::default().endpoints()
+    }
+
+    #[test]
+    fn test_submit_chat_message_injects_message_then_watcher() {
+        let window = RecordingEvaluator::default();
+        let timing = TimingSettings {
+            submit_delay: 1_000,
+            response_watcher_interval: 250,
+            ..Default::default()
+        };
+        let selectors = SelectorProfile {
+            input: vec!["textarea.agent-input".to_string()],
+            send: Vec::new(),
+        };
+        submit_chat_message(&window, "Hello", &selectors, &timing).unwrap();
+        assert_eq!(
+            window.scripts(),
+            vec![
+                params::InjectMessage::new("Hello", &timing)
+                    .with_selectors(&selectors)
+                    .render()
+                    .unwrap(),
+                params::ResponseWatcher::new(&timing).render().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_submit_chat_message_stops_when_message_fails() {
+        let window = RecordingEvaluator::failing_at(0, "window closed");
+        let error = submit_chat_message(
+            &window,
+            "Hello",
+            &SelectorProfile::default(),
+            &TimingSettings::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            WrapperError::InjectionFailed { ref context, ref source }
+                if context == "chat message" && source == "window closed"
+        ));
+        assert!(window.scripts().is_empty());
+    }
+
+    #[test]
+    fn test_submit_chat_message_reports_watcher_failure() {
+        let window = RecordingEvaluator::failing_at(1, "eval failed");
+        let error = submit_chat_message(
+            &window,
+            "Hello",
+            &SelectorProfile::default(),
+            &TimingSettings::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            WrapperError::InjectionFailed { ref context, .. } if context == "response watcher"
+        ));
+        assert_eq!(window.scripts().len(), 1);
+    }
+
+    #[test]
+    fn test_apply_all_wrappers_injects_every_wrapper() {
+        let settings = crate::AppSettings::default();
+        let window = RecordingEvaluator::default();
+        apply_all_wrappers(&window, &settings);
+        assert_eq!(
+            window.scripts(),
+            vec![
+                CONNECTIVITY_JS.to_string(),
+                params::LinkInterceptor::new(&endpoints(), &settings.link_routing.rule_hosts())
+                    .render()
+                    .unwrap(),
+                params::ConversationObserver::default().render().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_all_wrappers_continues_after_failure() {
+        let window = RecordingEvaluator::failing_at(0, "not ready");
+        apply_all_wrappers(&window, &crate::AppSettings::default());
+        assert_eq!(window.scripts().len(), 2);
+    }
+
+    #[test]
+    fn test_set_offline_state_injects_chat_url() {
+        let window = RecordingEvaluator::default();
+        set_offline_state(&window, &endpoints()).unwrap();
+        assert_eq!(
+            window.scripts(),
+            vec![params::OfflineState::new(&endpoints()).render().unwrap()]
+        );
+
+        let window = RecordingEvaluator::failing_at(0, "page gone");
+        assert_eq!(
+            set_offline_state(&window, &endpoints())
+                .unwrap_err()
+                .to_string(),
+            WrapperError::InjectionFailed {
+                context: "offline state".to_string(),
+                source: "page gone".to_string(),
+            }
+            .to_string()
+        );
+    }
+}
@@ -0,0 +1,520 @@
+//! Running the injected templates in tests without a webview
+//!
+//! [`Page`] loads a fixture from `tests/headless` into QuickJS, on top of the
+//! DOM shim next to the fixtures. Timers run on a virtual clock that only
+//! moves in [`Page::advance`], so the tests can step through retries and
+//! polling without waiting, and everything a script sends to
+//! `window.__TAURI__` is recorded.
+
+use rquickjs::{CatchResultExt, Context, Runtime};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::wrappers::{
+    chat_mode::{ChatMode, Reasoning, WebSearch},
+    config::{Selectors, Timeouts, Urls},
+    destinations::SelectorProfile,
+    endpoints::Endpoints,
+    params::{self, TemplateParams},
+    templates::CONNECTIVITY_JS,
+    timing::TimingSettings,
+};
+
+const DOM_SHIM_JS: &str = include_str!("../../tests/headless/dom_shim.js");
+
+/// Kimi's chat page with the contenteditable editor and a few links
+const EDITOR_PAGE: &str = include_str!("../../tests/headless/kimi_editor.html");
+/// Older layout with a textarea inside a form
+const TEXTAREA_PAGE: &str = include_str!("../../tests/headless/kimi_textarea.html");
+/// A conversation while Kimi is still writing, with the stop button shown
+const STREAMING_PAGE: &str = include_str!("../../tests/headless/kimi_streaming.html");
+
+/// Where the fixtures pretend to be served from
+const PAGE_URL: &str = "https://www.kimi.com/chat/fixture";
+
+/// An event sent through `window.__TAURI__.event.emit`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Emitted {
+    pub event: String,
+    pub payload: serde_json::Value,
+}
+
+/// A command sent through `window.__TAURI__.core.invoke`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Invoked {
+    pub command: String,
+    pub args: serde_json::Value,
+}
+
+pub struct Page {
+    runtime: Runtime,
+    context: Context,
+}
+
+impl Page {
+    /// Load `html` as the current page
+    pub fn load(html: &str) -> Self {
+        let runtime = Runtime::new().expect("QuickJS runtime");
+        let context = Context::full(&runtime).expect("QuickJS context");
+        let page = Self { runtime, context };
+        page.run(DOM_SHIM_JS).expect("DOM shim failed to load");
+        let load = format!(
+            "__loadFixture({}, {})",
+            serde_json::to_string(html).unwrap(),
+            serde_json::to_string(PAGE_URL).unwrap()
+        );
+        page.run(&load).expect("fixture failed to load");
+        page
+    }
+
+    /// Run a script, then the promise jobs it queued
+    pub fn run(&self, js: &str) -> Result<(), String> {
+        self.context
+            .with(|ctx| ctx.eval::<(), _>(js).catch(&ctx).map_err(|e| e.to_string()))?;
+        self.run_jobs()
+    }
+
+    fn run_jobs(&self) -> Result<(), String> {
+        loop {
+            match self.runtime.execute_pending_job() {
+                Ok(true) => continue,
+                Ok(false) => return Ok(()),
+                Err(_) => return Err("a promise job threw".to_string()),
+            }
+        }
+    }
+
+    /// Move the clock forward by `ms`, running every timer that comes due
+    pub fn advance(&self, ms: u64) {
+        let limit = self.eval::<u64>("__now()") + ms;
+        while self.eval::<bool>(&format!("__runNextTimer({})", limit)) {
+            self.run_jobs().unwrap_or_else(|e| panic!("{}", e));
+        }
+    }
+
+    /// Value of a JavaScript expression, passed through JSON
+    pub fn eval<T: DeserializeOwned>(&self, expr: &str) -> T {
+        let json = self.context.with(|ctx| {
+            ctx.eval::<String, _>(format!("JSON.stringify({})", expr))
+                .catch(&ctx)
+                .map_err(|e| e.to_string())
+        });
+        let json = json.unwrap_or_else(|e| panic!("{}: {}", expr, e));
+        serde_json::from_str(&json).unwrap_or_else(|e| panic!("{}: {} in {}", expr, e, json))
+    }
+
+    pub fn emitted(&self) -> Vec<Emitted> {
+        self.eval("__emitted")
+    }
+
+    /// Payloads of the emitted `event`s
+    pub fn emitted_payloads(&self, event: &str) -> Vec<serde_json::Value> {
+        self.emitted()
+            .into_iter()
+            .filter(|emitted| emitted.event == event)
+            .map(|emitted| emitted.payload)
+            .collect()
+    }
+
+    pub fn invoked(&self) -> Vec<Invoked> {
+        self.eval("__invoked")
+    }
+
+    /// Events dispatched so far, as `(type, target)` with targets written
+    /// like `div.send-button-container`
+    pub fn dispatched(&self) -> Vec<(String, String)> {
+        #[derive(Deserialize)]
+        struct Dispatched {
+            r#type: String,
+            target: String,
+        }
+        self.eval::<Vec<Dispatched>>("__dispatched")
+            .into_iter()
+            .map(|d| (d.r#type, d.target))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render<P: TemplateParams>(params: &P) -> String {
+        params.render().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Inject `message` and give the script all the time it may take
+    fn send(page: &Page, message: &str) -> serde_json::Value {
+        let params = params::InjectMessage::new(message, &TimingSettings::default());
+        page.run(&render(&params)).unwrap();
+        page.advance(Timeouts::INJECTION_TOTAL + 1000);
+        let mut results = page.emitted_payloads("inject-result");
+        assert_eq!(
+            results.len(),
+            1,
+            "expected one inject-result: {:?}",
+            results
+        );
+        results.remove(0)
+    }
+
+    /// Make the fixture's toolkit buttons flip `aria-pressed` when clicked,
+    /// as Kimi's toggles do
+    fn enable_toggles(page: &Page) {
+        page.run(
+            "document.querySelectorAll('.toolkit-item').forEach(button => button.addEventListener('click', () => \
+                button.setAttribute('aria-pressed', String(button.getAttribute('aria-pressed') !== 'true'))))",
+        )
+        .unwrap();
+    }
+
+    /// Apply `mode` and return the single `chat-mode-result`
+    fn set_mode(page: &Page, mode: ChatMode) -> serde_json::Value {
+        page.run(&render(&params::SetChatMode::new(
+            &mode,
+            &TimingSettings::default(),
+        )))
+        .unwrap();
+        page.advance(Timeouts::INJECTION_TOTAL);
+        let mut results = page.emitted_payloads("chat-mode-result");
+        assert_eq!(
+            results.len(),
+            1,
+            "expected one chat-mode-result: {:?}",
+            results
+        );
+        results.remove(0)
+    }
+
+    fn pressed(page: &Page, testid: &str) -> String {
+        page.eval(&format!(
+            "document.querySelector('[data-testid=\"{}\"]').getAttribute('aria-pressed')",
+            testid
+        ))
+    }
+
+    fn click(page: &Page, id: &str) -> bool {
+        page.eval(&format!(
+            "document.getElementById('{}').dispatchEvent(new MouseEvent('click', {{ bubbles: true, cancelable: true }}))",
+            id
+        ))
+    }
+
+    #[test]
+    fn test_fixture_selectors_match() {
+        let page = Page::load(EDITOR_PAGE);
+        let query = |selector: &str| {
+            page.eval::<bool>(&format!(
+                "!!document.querySelector({})",
+                serde_json::to_string(selector).unwrap()
+            ))
+        };
+        assert!(query(Selectors::CHAT_INPUT));
+        assert!(query(Selectors::CONTENTEDITABLE));
+        assert!(query(Selectors::SEND_BUTTON));
+        assert!(!query(Selectors::TEXTAREA_ANY));
+        assert!(!query(Selectors::STOP_BUTTON_ARIA));
+
+        let page = Page::load(STREAMING_PAGE);
+        assert!(page.eval::<bool>(&format!(
+            "!!document.querySelector({})",
+            serde_json::to_string(Selectors::STOP_BUTTON_ARIA_CAP).unwrap()
+        )));
+    }
+
+    #[test]
+    fn test_inject_message_into_editor() {
+        let page = Page::load(EDITOR_PAGE);
+        let message = "Hello </script> `Kimi` ${name} 'quoted' \"twice\"\u{2028}next";
+        let result = send(&page, message);
+
+        assert_eq!(result["success"], true);
+        assert_eq!(result["error"], serde_json::Value::Null);
+        assert_eq!(result["input_selector"], "chat_input");
+        assert_eq!(result["send_selector"], "send_button");
+        assert_eq!(result["retries"], 0);
+        let editor: String = page.eval("document.querySelector('.chat-input-editor').textContent");
+        assert_eq!(editor, message);
+        let elapsed = Timeouts::REACT_INIT_DELAY + Timeouts::FOCUS_DELAY + Timeouts::SUBMIT_DELAY;
+        assert_eq!(result["elapsed_ms"], elapsed);
+
+        let dispatched = page.dispatched();
+        assert!(dispatched.contains(&("input".to_string(), "div.chat-input-editor".to_string())));
+        assert_eq!(
+            dispatched.last(),
+            Some(&("click".to_string(), "div.send-button-container".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_inject_message_falls_back_when_send_is_disabled() {
+        let page = Page::load(EDITOR_PAGE);
+        page.run("document.querySelector('.send-button-container').classList.add('disabled')")
+            .unwrap();
+        let result = send(&page, "Hello");
+        assert_eq!(result["success"], true);
+        assert_eq!(result["send_selector"], "send_button_fallback");
+    }
+
+    #[test]
+    fn test_inject_message_into_textarea() {
+        let page = Page::load(TEXTAREA_PAGE);
+        let result = send(&page, "Line one\nline two");
+
+        assert_eq!(result["success"], true);
+        assert_eq!(result["input_selector"], "textarea_ask");
+        assert_eq!(result["send_selector"], "submit_button");
+        let value: String = page.eval("document.querySelector('textarea').value");
+        assert_eq!(value, "Line one\nline two");
+        let events: Vec<String> = page
+            .dispatched()
+            .into_iter()
+            .filter(|(_, target)| target == "textarea")
+            .map(|(event, _)| event)
+            .collect();
+        assert_eq!(events, vec!["input", "change"]);
+    }
+
+    #[test]
+    fn test_inject_message_tries_destination_selectors_first() {
+        let page = Page::load(TEXTAREA_PAGE);
+        let selectors = SelectorProfile {
+            input: vec![
+                "textarea[".to_string(),
+                "form.chat-form textarea".to_string(),
+            ],
+            send: vec!["button[aria-label=\"Attach file\"]".to_string()],
+        };
+        let params = params::InjectMessage::new("Hello", &TimingSettings::default())
+            .with_selectors(&selectors);
+        page.run(&render(&params)).unwrap();
+        page.advance(Timeouts::INJECTION_TOTAL + 1000);
+        let results = page.emitted_payloads("inject-result");
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["success"], true);
+        assert_eq!(results[0]["input_selector"], "custom_input_2");
+        assert_eq!(results[0]["send_selector"], "custom_send_1");
+        assert_eq!(
+            page.dispatched().last(),
+            Some(&("click".to_string(), "button".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_inject_message_presses_enter_without_send_button() {
+        let page = Page::load(TEXTAREA_PAGE);
+        page.run("document.querySelectorAll('button').forEach(b => b.remove())")
+            .unwrap();
+        let result = send(&page, "Hello");
+        assert_eq!(result["success"], true);
+        assert_eq!(result["send_selector"], "enter_key");
+        assert_eq!(
+            page.dispatched().last(),
+            Some(&("keydown".to_string(), "textarea".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_inject_message_gives_up_without_input() {
+        let page = Page::load(TEXTAREA_PAGE);
+        page.run("document.querySelector('form').remove()").unwrap();
+        let result = send(&page, "Hello");
+
+        assert_eq!(result["success"], false);
+        assert_eq!(result["input_selector"], serde_json::Value::Null);
+        assert_eq!(result["retries"], Timeouts::INJECTION_MAX_RETRIES);
+        assert!(result["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Could not find chat input"));
+    }
+
+    #[test]
+    fn test_inject_message_waits_for_page_load() {
+        let page = Page::load(EDITOR_PAGE);
+        page.run("document.readyState = 'loading'").unwrap();
+        page.run(&render(&params::InjectMessage::new(
+            "Hello",
+            &TimingSettings::default(),
+        )))
+        .unwrap();
+        page.advance(Timeouts::FALLBACK_LOAD_TIMEOUT / 2);
+        page.run("document.readyState = 'complete'; window.dispatchEvent(new Event('load'))")
+            .unwrap();
+        page.advance(Timeouts::INJECTION_TOTAL);
+
+        let results = page.emitted_payloads("inject-result");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["success"], true);
+        let elapsed = Timeouts::FALLBACK_LOAD_TIMEOUT / 2
+            + Timeouts::REACT_INIT_DELAY
+            + Timeouts::FOCUS_DELAY
+            + Timeouts::SUBMIT_DELAY;
+        assert_eq!(results[0]["elapsed_ms"], elapsed);
+    }
+
+    #[test]
+    fn test_chat_mode_clicks_only_toggles_that_differ() {
+        let page = Page::load(EDITOR_PAGE);
+        enable_toggles(&page);
+        let result = set_mode(
+            &page,
+            ChatMode {
+                reasoning: Reasoning::Thinking,
+                web_search: WebSearch::On,
+            },
+        );
+
+        // The page has no long thinking toggle, which is fine when it stays off
+        assert_eq!(result["changed"], serde_json::json!(["thinking"]));
+        assert_eq!(result["missing"], serde_json::json!([]));
+        assert_eq!(pressed(&page, "toolkit-thinking"), "true");
+        assert_eq!(pressed(&page, "toolkit-web-search"), "true");
+    }
+
+    #[test]
+    fn test_chat_mode_finds_toggle_shown_by_another() {
+        let page = Page::load(EDITOR_PAGE);
+        enable_toggles(&page);
+        // Turning thinking on reveals the long thinking toggle a moment later
+        page.run(
+            "document.querySelector('[data-testid=\"toolkit-thinking\"]').addEventListener('click', () => setTimeout(() => { \
+                const long = document.createElement('button'); \
+                long.className = 'toolkit-item'; \
+                long.setAttribute('data-testid', 'toolkit-long-thinking'); \
+                long.setAttribute('aria-pressed', 'false'); \
+                long.addEventListener('click', () => long.setAttribute('aria-pressed', 'true')); \
+                document.querySelector('.chat-input-toolkit').appendChild(long); \
+            }, 1000))",
+        )
+        .unwrap();
+        let result = set_mode(
+            &page,
+            ChatMode {
+                reasoning: Reasoning::LongThinking,
+                web_search: WebSearch::Off,
+            },
+        );
+
+        assert_eq!(
+            result["changed"],
+            serde_json::json!(["thinking", "web_search", "long_thinking"])
+        );
+        assert_eq!(result["missing"], serde_json::json!([]));
+        assert_eq!(pressed(&page, "toolkit-long-thinking"), "true");
+        assert_eq!(pressed(&page, "toolkit-web-search"), "false");
+    }
+
+    #[test]
+    fn test_chat_mode_reports_missing_toggles() {
+        let page = Page::load(TEXTAREA_PAGE);
+        let result = set_mode(
+            &page,
+            ChatMode {
+                reasoning: Reasoning::LongThinking,
+                web_search: WebSearch::On,
+            },
+        );
+        assert_eq!(result["changed"], serde_json::json!([]));
+        assert_eq!(
+            result["missing"],
+            serde_json::json!(["thinking", "long_thinking", "web_search"])
+        );
+    }
+
+    #[test]
+    fn test_response_watcher_reports_completion() {
+        let page = Page::load(STREAMING_PAGE);
+        page.run(&render(&params::ResponseWatcher::new(
+            &TimingSettings::default(),
+        )))
+        .unwrap();
+        page.advance(
+            Timeouts::RESPONSE_WATCHER_INITIAL_DELAY + 3 * Timeouts::RESPONSE_WATCHER_INTERVAL,
+        );
+        assert!(page.emitted().is_empty());
+
+        page.run("document.querySelector('.stop-button').remove()")
+            .unwrap();
+        page.advance(Timeouts::RESPONSE_WATCHER_INTERVAL);
+        assert_eq!(
+            page.emitted_payloads("response-complete"),
+            vec![serde_json::json!({ "title": "Kimi - Answer in progress", "url": PAGE_URL })]
+        );
+        assert!(!page.eval::<bool>("!!window.__kimiResponseWatcher"));
+    }
+
+    #[test]
+    fn test_response_watcher_gives_up_after_max_checks() {
+        let page = Page::load(EDITOR_PAGE);
+        let watcher = params::ResponseWatcher::new(&TimingSettings {
+            response_watcher_max_checks: 3,
+            ..Default::default()
+        });
+        page.run(&render(&watcher)).unwrap();
+        page.advance(watcher.initial_delay + 4 * watcher.check_interval);
+
+        let events: Vec<String> = page.emitted().into_iter().map(|e| e.event).collect();
+        assert_eq!(events, vec!["response-watch-ended"]);
+    }
+
+    #[test]
+    fn test_response_watcher_runs_once() {
+        let page = Page::load(STREAMING_PAGE);
+        let js = render(&params::ResponseWatcher::new(&TimingSettings::default()));
+        page.run(&js).unwrap();
+        page.run(&js).unwrap();
+        page.advance(
+            Timeouts::RESPONSE_WATCHER_INITIAL_DELAY + Timeouts::RESPONSE_WATCHER_INTERVAL,
+        );
+        page.run("document.querySelector('.stop-button').remove()")
+            .unwrap();
+        page.advance(Timeouts::RESPONSE_WATCHER_INTERVAL);
+        assert_eq!(page.emitted_payloads("response-complete").len(), 1);
+    }
+
+    #[test]
+    fn test_connectivity_events_trigger_check() {
+        let page = Page::load(EDITOR_PAGE);
+        page.run(CONNECTIVITY_JS).unwrap();
+        page.run(CONNECTIVITY_JS).unwrap();
+        page.run("window.dispatchEvent(new Event('offline'))")
+            .unwrap();
+        page.run("window.dispatchEvent(new Event('online'))")
+            .unwrap();
+
+        let commands: Vec<String> = page.invoked().into_iter().map(|i| i.command).collect();
+        assert_eq!(commands, vec!["check_connectivity", "check_connectivity"]);
+    }
+
+    #[test]
+    fn test_link_interceptor_routes_links() {
+        let page = Page::load(EDITOR_PAGE);
+        let endpoints = Endpoints::parse(Urls::DEFAULT_ORIGIN).unwrap();
+        let interceptor =
+            params::LinkInterceptor::new(&endpoints, &["docs.example.com".to_string()]);
+        page.run(&render(&interceptor)).unwrap();
+
+        // Links staying in Kimi are left to the page
+        assert!(click(&page, "link-internal"));
+        assert!(page.invoked().is_empty());
+
+        let opened = |id: &str| {
+            let before = page.invoked().len();
+            assert!(!click(&page, id), "{} was not intercepted", id);
+            let invoked = page.invoked();
+            assert_eq!(invoked.len(), before + 1);
+            let last = invoked.last().unwrap();
+            assert_eq!(last.command, "open_external_link");
+            last.args["url"].as_str().unwrap().to_string()
+        };
+        assert_eq!(
+            opened("link-external-text"),
+            "https://example.com/article?id=1"
+        );
+        assert_eq!(opened("link-new-window"), "https://www.kimi.com/share/abc");
+        assert_eq!(opened("link-rule"), "https://docs.example.com/guide");
+        assert_eq!(opened("link-mail"), "mailto:support@example.com");
+    }
+}
@@ -0,0 +1,453 @@
+//! Typed parameters for the templates in `wrappers::templates`
+//!
+//! Each template with parameters has a struct here. Rendering serializes the
+//! struct into one JSON object, which the template reads back with
+//! `JSON.parse({{params}})`; prompts, selectors and hosts are never spliced
+//! into the script source. The tests render every template and feed
+//! arbitrary text through the encoding.
+
+use serde::Serialize;
+
+use crate::wrappers::{
+    chat_mode::{ChatMode, ModeToggle},
+    config::{Selectors, Styles, Timeouts},
+    destinations::SelectorProfile,
+    endpoints::Endpoints,
+    error::WrapperResult,
+    templates::*,
+    timing::TimingSettings,
+    utils::{build_js, js_json_literal},
+};
+
+/// Parameters of one template, serialized as its `params` object
+pub trait TemplateParams: Serialize {
+    /// Name used in errors
+    const NAME: &'static str;
+    const TEMPLATE: &'static str;
+
+    fn render(&self) -> WrapperResult<String> {
+        let params = js_json_literal(self)?;
+        build_js(Self::NAME, Self::TEMPLATE, &[("params", &params)])
+    }
+}
+
+/// Only injected on macOS
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TitlebarStyles {
+    pub style_id: &'static str,
+    pub header_padding: &'static str,
+}
+
+impl Default for TitlebarStyles {
+    fn default() -> Self {
+        Self {
+            style_id: Styles::STYLE_ID,
+            header_padding: Styles::HEADER_PADDING,
+        }
+    }
+}
+
+impl TemplateParams for TitlebarStyles {
+    const NAME: &'static str = "titlebar styles";
+    const TEMPLATE: &'static str = TITLEBAR_OVERLAP_JS;
+}
+
+/// Selectors tried in order to find the chat input
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InputSelectors {
+    pub chat_input: &'static str,
+    pub contenteditable: &'static str,
+    pub textarea_ask: &'static str,
+    pub textarea_message: &'static str,
+    pub textarea_lowercase_ask: &'static str,
+    pub textarea_testid: &'static str,
+    pub textarea_any: &'static str,
+}
+
+impl Default for InputSelectors {
+    fn default() -> Self {
+        Self {
+            chat_input: Selectors::CHAT_INPUT,
+            contenteditable: Selectors::CONTENTEDITABLE,
+            textarea_ask: Selectors::TEXTAREA_ASK,
+            textarea_message: Selectors::TEXTAREA_MESSAGE,
+            textarea_lowercase_ask: Selectors::TEXTAREA_LOWERCASE_ASK,
+            textarea_testid: Selectors::TEXTAREA_TESTID,
+            textarea_any: Selectors::TEXTAREA_ANY,
+        }
+    }
+}
+
+/// Selectors tried in order to find the send button
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SendSelectors {
+    pub send_button: &'static str,
+    pub send_button_fallback: &'static str,
+    pub submit_button: &'static str,
+    pub send_aria: &'static str,
+    pub send_aria_cap: &'static str,
+    pub send_data_testid: &'static str,
+    pub form_button_last: &'static str,
+}
+
+impl Default for SendSelectors {
+    fn default() -> Self {
+        Self {
+            send_button: Selectors::SEND_BUTTON,
+            send_button_fallback: Selectors::SEND_BUTTON_FALLBACK,
+            submit_button: Selectors::SUBMIT_BUTTON,
+            send_aria: Selectors::SEND_ARIA_LABEL,
+            send_aria_cap: Selectors::SEND_ARIA_LABEL_CAP,
+            send_data_testid: Selectors::SEND_DATA_TESTID,
+            form_button_last: Selectors::FORM_BUTTON_LAST,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InjectMessage {
+    pub message: String,
+    pub max_retries: u32,
+    pub retry_delay: u64,
+    pub total_timeout: u64,
+    pub fallback_timeout: u64,
+    pub react_init_delay: u64,
+    pub focus_delay: u64,
+    pub submit_delay: u64,
+    pub input: InputSelectors,
+    pub send: SendSelectors,
+    /// The destination's selectors, tried before `input` and `send`
+    pub custom: SelectorProfile,
+}
+
+impl InjectMessage {
+    /// Parameters for sending `message` to the chat with the given timings
+    pub fn new(message: &str, timing: &TimingSettings) -> Self {
+        Self {
+            message: message.to_string(),
+            max_retries: timing.injection_max_retries,
+            retry_delay: timing.retry_delay,
+            total_timeout: timing.injection_total,
+            fallback_timeout: timing.fallback_load_timeout,
+            react_init_delay: timing.react_init_delay,
+            focus_delay: timing.focus_delay,
+            submit_delay: timing.submit_delay,
+            input: InputSelectors::default(),
+            send: SendSelectors::default(),
+            custom: SelectorProfile::default(),
+        }
+    }
+
+    /// Also look for the input and send button with a destination's selectors
+    pub fn with_selectors(mut self, selectors: &SelectorProfile) -> Self {
+        self.custom = selectors.clone();
+        self
+    }
+}
+
+impl TemplateParams for InjectMessage {
+    const NAME: &'static str = "chat message";
+    const TEMPLATE: &'static str = INJECT_MESSAGE_JS;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SetChatMode {
+    pub toggles: Vec<ModeToggle>,
+    pub max_retries: u32,
+    pub retry_delay: u64,
+    pub fallback_timeout: u64,
+    pub react_init_delay: u64,
+    /// Wait after each click for the page to update
+    pub click_delay: u64,
+}
+
+impl SetChatMode {
+    pub fn new(mode: &ChatMode, timing: &TimingSettings) -> Self {
+        Self {
+            toggles: mode.toggles(),
+            max_retries: timing.injection_max_retries,
+            retry_delay: timing.retry_delay,
+            fallback_timeout: timing.fallback_load_timeout,
+            react_init_delay: timing.react_init_delay,
+            click_delay: timing.focus_delay,
+        }
+    }
+}
+
+impl TemplateParams for SetChatMode {
+    const NAME: &'static str = "chat mode";
+    const TEMPLATE: &'static str = CHAT_MODE_JS;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResponseWatcher {
+    pub check_interval: u64,
+    pub initial_delay: u64,
+    pub max_checks: u32,
+    pub stop_aria: &'static str,
+    pub stop_aria_cap: &'static str,
+    pub cancel_aria: &'static str,
+    pub stop_data_testid: &'static str,
+}
+
+impl ResponseWatcher {
+    pub fn new(timing: &TimingSettings) -> Self {
+        Self {
+            check_interval: timing.response_watcher_interval,
+            initial_delay: timing.response_watcher_initial_delay,
+            max_checks: timing.response_watcher_max_checks,
+            stop_aria: Selectors::STOP_BUTTON_ARIA,
+            stop_aria_cap: Selectors::STOP_BUTTON_ARIA_CAP,
+            cancel_aria: Selectors::CANCEL_BUTTON_ARIA,
+            stop_data_testid: Selectors::STOP_BUTTON_DATA_TESTID,
+        }
+    }
+}
+
+impl TemplateParams for ResponseWatcher {
+    const NAME: &'static str = "response watcher";
+    const TEMPLATE: &'static str = RESPONSE_WATCHER_JS;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LinkInterceptor {
+    pub first_party_domains: Vec<String>,
+    pub rule_hosts: Vec<String>,
+}
+
+impl LinkInterceptor {
+    pub fn new(endpoints: &Endpoints, rule_hosts: &[String]) -> Self {
+        Self {
+            first_party_domains: endpoints.first_party_domains(),
+            rule_hosts: rule_hosts.to_vec(),
+        }
+    }
+}
+
+impl TemplateParams for LinkInterceptor {
+    const NAME: &'static str = "link interceptor";
+    const TEMPLATE: &'static str = LINK_INTERCEPTOR_JS;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConversationObserver {
+    pub debounce: u64,
+}
+
+impl Default for ConversationObserver {
+    fn default() -> Self {
+        Self {
+            debounce: Timeouts::CONVERSATION_OBSERVER_DEBOUNCE,
+        }
+    }
+}
+
+impl TemplateParams for ConversationObserver {
+    const NAME: &'static str = "conversation observer";
+    const TEMPLATE: &'static str = CONVERSATION_OBSERVER_JS;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeliveryToast {
+    /// `sending`, `retrying`, `failed` or `hidden`
+    pub status: &'static str,
+    pub text: String,
+    /// Id of the failed prompt the resend and dismiss actions apply to
+    pub failed_id: Option<u64>,
+}
+
+impl TemplateParams for DeliveryToast {
+    const NAME: &'static str = "delivery toast";
+    const TEMPLATE: &'static str = DELIVERY_TOAST_JS;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OfflineState {
+    pub chat_url: String,
+    pub chat_host: String,
+}
+
+impl OfflineState {
+    pub fn new(endpoints: &Endpoints) -> Self {
+        Self {
+            chat_url: endpoints.chat().to_string(),
+            chat_host: endpoints.host().to_string(),
+        }
+    }
+}
+
+impl TemplateParams for OfflineState {
+    const NAME: &'static str = "offline state";
+    const TEMPLATE: &'static str = OFFLINE_STATE_JS;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wrappers::utils::placeholders;
+    use proptest::prelude::*;
+
+    /// Templates injected as they are
+    const STATIC_TEMPLATES: &[(&str, &str)] = &[
+        ("connectivity events", CONNECTIVITY_JS),
+        ("zoom shortcuts", ZOOM_SHORTCUTS_JS),
+        ("quit shortcut", QUIT_SHORTCUT_JS),
+    ];
+
+    fn assert_renders<P: TemplateParams>(params: &P) -> String {
+        let js = params.render().unwrap_or_else(|e| panic!("{}", e));
+        assert!(!js.contains("{{"), "{} left a placeholder", P::NAME);
+        js
+    }
+
+    /// Decode the `params` object of a rendered template the way the page does
+    ///
+    /// A JSON string literal is also a JavaScript string literal with the same
+    /// value, so reading it as JSON matches what `JSON.parse(...)` sees.
+    fn decode(js: &str) -> serde_json::Value {
+        let start = js.find("JSON.parse(").expect("no JSON.parse call") + "JSON.parse(".len();
+        let mut literals = serde_json::Deserializer::from_str(&js[start..]).into_iter::<String>();
+        let json = literals
+            .next()
+            .expect("no literal")
+            .expect("not a string literal");
+        assert!(js[start + literals.byte_offset()..].starts_with(");"));
+        serde_json::from_str(&json).expect("literal does not hold JSON")
+    }
+
+    /// Render `params` and check the template around the literal is unchanged
+    /// and the literal decodes to the serialized parameters
+    fn assert_round_trip<P: TemplateParams>(params: &P) -> serde_json::Value {
+        let js = params.render().unwrap();
+        let literal = js_json_literal(params).unwrap();
+        assert_eq!(js, P::TEMPLATE.replacen("{{params}}", &literal, 1));
+        for c in literal.chars() {
+            assert!(
+                !matches!(c, '\0'..='\u{1f}' | '<' | '\u{2028}' | '\u{2029}'),
+                "{:?} left unescaped in {}",
+                c,
+                literal
+            );
+        }
+        let decoded = decode(&js);
+        assert_eq!(decoded, serde_json::to_value(params).unwrap());
+        decoded
+    }
+
+    /// Text mixing arbitrary characters with ones that broke escaping before
+    fn hostile_text() -> impl Strategy<Value = String> {
+        let piece = prop_oneof![
+            any::<char>().prop_map(String::from),
+            prop::sample::select(vec![
+                "\u{2028}",
+                "\u{2029}",
+                "\0",
+                "</script>",
+                "<!--",
+                "'",
+                "\"",
+                "`",
+                "${x}",
+                "\\",
+                "\\u0041",
+                "\r\n",
+                "{{params}}",
+                "}}",
+                "');alert(1);//",
+            ])
+            .prop_map(String::from),
+        ];
+        prop::collection::vec(piece, 0..24).prop_map(|pieces| pieces.concat())
+    }
+
+    #[test]
+    fn test_every_template_renders() {
+        let endpoints = Endpoints::parse(crate::wrappers::config::Urls::DEFAULT_ORIGIN).unwrap();
+        assert_renders(&TitlebarStyles::default());
+        assert_renders(&InjectMessage::new("Hello", &TimingSettings::default()));
+        assert_renders(&ResponseWatcher::new(&TimingSettings::default()));
+        assert_renders(&SetChatMode::new(
+            &ChatMode::default(),
+            &TimingSettings::default(),
+        ));
+        assert_renders(&LinkInterceptor::new(
+            &endpoints,
+            &["docs.example.com".to_string()],
+        ));
+        assert_renders(&ConversationObserver::default());
+        assert_renders(&DeliveryToast {
+            status: "sending",
+            text: "Sending…".to_string(),
+            failed_id: None,
+        });
+        assert_renders(&OfflineState::new(&endpoints));
+    }
+
+    #[test]
+    fn test_static_templates_have_no_placeholders() {
+        for (name, template) in STATIC_TEMPLATES {
+            let names = placeholders(template).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            assert!(names.is_empty(), "{} has placeholders {:?}", name, names);
+        }
+    }
+
+    #[test]
+    fn test_inject_message_passes_message_as_json() {
+        let js = assert_renders(&InjectMessage::new(
+            "a `b` ${c}",
+            &TimingSettings::default(),
+        ));
+        assert!(js.contains(r#"\"message\":\"a `b` ${c}\""#));
+        assert!(js.contains("const message = params.message;"));
+        assert!(js.contains("emitResult"));
+        let params = decode(&js);
+        assert_eq!(params["input"]["chat_input"], Selectors::CHAT_INPUT);
+        assert_eq!(params["max_retries"], Timeouts::INJECTION_MAX_RETRIES);
+    }
+
+    #[test]
+    fn test_chat_mode_passes_toggles_with_selector_pairs() {
+        let mode = ChatMode {
+            web_search: crate::wrappers::chat_mode::WebSearch::On,
+            ..Default::default()
+        };
+        let params = assert_round_trip(&SetChatMode::new(&mode, &TimingSettings::default()));
+        assert_eq!(params["toggles"][0]["name"], "web_search");
+        assert_eq!(params["toggles"][0]["on"], true);
+        assert_eq!(
+            params["toggles"][0]["selectors"][0],
+            serde_json::json!(["web_search_testid", Selectors::WEB_SEARCH_TOGGLE_TESTID])
+        );
+    }
+
+    #[test]
+    fn test_message_placeholders_stay_text() {
+        let params = assert_round_trip(&InjectMessage::new(
+            "{{params}}",
+            &TimingSettings::default(),
+        ));
+        assert_eq!(params["message"], "{{params}}");
+    }
+
+    proptest! {
+        #[test]
+        fn prop_message_round_trips(message in hostile_text()) {
+            let params = assert_round_trip(&InjectMessage::new(&message, &TimingSettings::default()));
+            prop_assert_eq!(params["message"].as_str(), Some(message.as_str()));
+        }
+
+        #[test]
+        fn prop_toast_text_round_trips(text in hostile_text()) {
+            let params = assert_round_trip(&DeliveryToast { status: "failed", text: text.clone(), failed_id: Some(3) });
+            prop_assert_eq!(params["text"].as_str(), Some(text.as_str()));
+        }
+
+        #[test]
+        fn prop_rule_hosts_round_trip(hosts in prop::collection::vec(hostile_text(), 0..4)) {
+            let endpoints = Endpoints::parse(crate::wrappers::config::Urls::DEFAULT_ORIGIN).unwrap();
+            let params = assert_round_trip(&LinkInterceptor::new(&endpoints, &hosts));
+            prop_assert_eq!(params["rule_hosts"].clone(), serde_json::to_value(&hosts).unwrap());
+        }
+    }
+}
@@ -1,9 +1,13 @@
 //! JavaScript template strings for wrapper injections
+//!
+//! Templates that take values read them from one `params` object, decoded
+//! with `JSON.parse({{params}})` and filled in by `wrappers::params`.
 
 /// JavaScript template for hiding titlebar overlap on macOS
 pub const TITLEBAR_OVERLAP_JS: &str = r#"
 (function() {
-    const STYLE_ID = '{{style_id}}';
+    const params = JSON.parse({{params}});
+    const STYLE_ID = params.style_id;
     
     function injectStyles() {
         if (document.getElementById(STYLE_ID)) return;
@@ -17,7 +21,7 @@ pub const TITLEBAR_OVERLAP_JS: &str = r#"
             }
             .app-header,
             header {
-                padding-top: {{header_padding}} !important;
+                padding-top: ${params.header_padding} !important;
             }
         `;
         document.head.appendChild(style);
@@ -39,16 +43,28 @@ pub const TITLEBAR_OVERLAP_JS: &str = r#"
 /// JavaScript template for injecting messages into chat input
 pub const INJECT_MESSAGE_JS: &str = r#"
 (function() {
-    const message = `{{message}}`;
-    const maxRetries = {{max_retries}};
-    const retryDelay = {{retry_delay}};
-    const totalTimeout = {{total_timeout}};
+    const params = JSON.parse({{params}});
+    const message = params.message;
+    const maxRetries = params.max_retries;
+    const retryDelay = params.retry_delay;
+    const totalTimeout = params.total_timeout;
+    const startedAt = Date.now();
     let retryCount = 0;
     let timedOut = false;
+    // Names of the selectors that matched, reported for the injection stats
+    let inputSelector = null;
+    let sendSelector = null;
     
     function emitResult(success, error) {
         if (window.__TAURI__) {
-            window.__TAURI__.event.emit('inject-result', { success, error: error || null });
+            window.__TAURI__.event.emit('inject-result', {
+                success,
+                error: error || null,
+                input_selector: inputSelector,
+                send_selector: sendSelector,
+                retries: retryCount,
+                elapsed_ms: Date.now() - startedAt
+            });
         }
     }
     
@@ -56,16 +72,41 @@ pub const INJECT_MESSAGE_JS: &str = r#"
         timedOut = true;
         const msg = 'Message injection timed out after ' + totalTimeout + 'ms';
         emitResult(false, msg);
-    }}, totalTimeout);
+    }, totalTimeout);
+    
+    // First element matching one of the `[name, selector]` pairs, in order;
+    // a destination's selector that is not valid CSS is skipped
+    function findFirst(selectors) {
+        for (const [name, selector] of selectors) {
+            let element = null;
+            try {
+                element = document.querySelector(selector);
+            } catch (err) {
+                continue;
+            }
+            if (element) return { name, element };
+        }
+        return null;
+    }
+    
+    // The destination's own selectors as pairs named `<kind>_1`, `<kind>_2`, ...
+    function customPairs(selectors, kind) {
+        return selectors.map((selector, i) => [kind + '_' + (i + 1), selector]);
+    }
     
     function findTextarea() {
-        return document.querySelector('{{selector_chat_input}}')
-            || document.querySelector('{{selector_contenteditable}}')
-            || document.querySelector('{{selector_textarea_ask}}')
-            || document.querySelector('{{selector_textarea_message}}')
-            || document.querySelector('{{selector_textarea_lowercase_ask}}')
-            || document.querySelector('{{selector_textarea_testid}}')
-            || document.querySelector('{{selector_textarea_any}}');
+        const found = findFirst([
+            ...customPairs(params.custom.input, 'custom_input'),
+            ['chat_input', params.input.chat_input],
+            ['contenteditable', params.input.contenteditable],
+            ['textarea_ask', params.input.textarea_ask],
+            ['textarea_message', params.input.textarea_message],
+            ['textarea_lowercase_ask', params.input.textarea_lowercase_ask],
+            ['textarea_testid', params.input.textarea_testid],
+            ['textarea_any', params.input.textarea_any]
+        ]);
+        inputSelector = found ? found.name : null;
+        return found ? found.element : null;
     }
     
     async function injectMessage() {
@@ -74,11 +115,11 @@ pub const INJECT_MESSAGE_JS: &str = r#"
         if (document.readyState !== 'complete') {
             await new Promise(resolve => {
                 window.addEventListener('load', resolve, { once: true });
-                setTimeout(resolve, {{fallback_timeout}});
+                setTimeout(resolve, params.fallback_timeout);
             });
         }
         
-        await new Promise(r => setTimeout(r, {{react_init_delay}}));
+        await new Promise(r => setTimeout(r, params.react_init_delay));
         
         const textarea = findTextarea();
         
@@ -97,11 +138,11 @@ pub const INJECT_MESSAGE_JS: &str = r#"
             if (textarea.classList && textarea.classList.contains('chat-input-editor')) {
                 textarea.contentEditable = 'true';
                 textarea.focus();
-                await new Promise(r => setTimeout(r, {{focus_delay}}));
+                await new Promise(r => setTimeout(r, params.focus_delay));
                 textarea.innerHTML = '';
                 document.execCommand('insertText', false, message);
                 textarea.dispatchEvent(new Event('input', { bubbles: true }));
-                setTimeout(() => submitForm(textarea), {{submit_delay}});
+                setTimeout(() => submitForm(textarea), params.submit_delay);
                 return;
             }
             
@@ -124,7 +165,7 @@ pub const INJECT_MESSAGE_JS: &str = r#"
                     data: message
                 }));
                 
-                setTimeout(() => submitForm(textarea), {{submit_delay}});
+                setTimeout(() => submitForm(textarea), params.submit_delay);
                 return;
             }
             
@@ -136,7 +177,7 @@ pub const INJECT_MESSAGE_JS: &str = r#"
             textarea.dispatchEvent(new Event('input', { bubbles: true }));
             textarea.dispatchEvent(new Event('change', { bubbles: true }));
             textarea.focus();
-            setTimeout(() => submitForm(textarea), {{submit_delay}});
+            setTimeout(() => submitForm(textarea), params.submit_delay);
         } catch (err) {
             clearTimeout(timeoutId);
             emitResult(false, 'Failed to set message: ' + err.message);
@@ -150,18 +191,23 @@ pub const INJECT_MESSAGE_JS: &str = r#"
         submitted = true;
         clearTimeout(timeoutId);
         
-        const sendBtn = document.querySelector('{{selector_send_button}}')
-            || document.querySelector('{{selector_send_button_fallback}}')
-            || document.querySelector('{{selector_submit_button}}')
-            || document.querySelector('{{selector_send_aria}}')
-            || document.querySelector('{{selector_send_aria_cap}}')
-            || document.querySelector('{{selector_send_data_testid}}')
-            || document.querySelector('{{selector_form_button_last}}');
+        const found = findFirst([
+            ...customPairs(params.custom.send, 'custom_send'),
+            ['send_button', params.send.send_button],
+            ['send_button_fallback', params.send.send_button_fallback],
+            ['submit_button', params.send.submit_button],
+            ['send_aria', params.send.send_aria],
+            ['send_aria_cap', params.send.send_aria_cap],
+            ['send_data_testid', params.send.send_data_testid],
+            ['form_button_last', params.send.form_button_last]
+        ]);
         
-        if (sendBtn) {
-            sendBtn.click();
+        if (found) {
+            sendSelector = found.name;
+            found.element.click();
             emitResult(true);
         } else {
+            sendSelector = 'enter_key';
             if (textarea) {
                 textarea.dispatchEvent(new KeyboardEvent('keydown', {
                     key: 'Enter',
@@ -182,23 +228,99 @@ pub const INJECT_MESSAGE_JS: &str = r#"
 })();
 "#;
 
+/// JavaScript template setting Kimi's chat mode toggles before a prompt
+///
+/// Clicks each toggle in `toggles` whose state differs from `on` and reports
+/// the clicked and missing toggles as `chat-mode-result`. Missing toggles are
+/// looked for again until `max_retries`, since turning one on can reveal
+/// the next.
+pub const CHAT_MODE_JS: &str = r#"
+(function() {
+    const params = JSON.parse({{params}});
+    const startedAt = Date.now();
+    const changed = [];
+    let attempts = 0;
+    
+    function emitResult(missing) {
+        if (window.__TAURI__) {
+            window.__TAURI__.event.emit('chat-mode-result', {
+                changed,
+                missing,
+                elapsed_ms: Date.now() - startedAt
+            });
+        }
+    }
+    
+    // First element matching one of the `[name, selector]` pairs, in order
+    function findFirst(selectors) {
+        for (const [name, selector] of selectors) {
+            const element = document.querySelector(selector);
+            if (element) return { name, element };
+        }
+        return null;
+    }
+    
+    function isOn(element) {
+        if (element.getAttribute('aria-pressed') === 'true') return true;
+        if (element.getAttribute('aria-checked') === 'true') return true;
+        const state = element.getAttribute('data-state');
+        if (state === 'on' || state === 'checked' || state === 'active') return true;
+        return ['active', 'selected', 'checked', 'is-active'].some(name => element.classList.contains(name));
+    }
+    
+    async function apply() {
+        attempts++;
+        const missing = [];
+        for (const toggle of params.toggles) {
+            const found = findFirst(toggle.selectors);
+            if (!found) {
+                if (!toggle.optional) missing.push(toggle.name);
+                continue;
+            }
+            if (isOn(found.element) !== toggle.on) {
+                found.element.click();
+                if (!changed.includes(toggle.name)) changed.push(toggle.name);
+                await new Promise(r => setTimeout(r, params.click_delay));
+            }
+        }
+        if (missing.length > 0 && attempts < params.max_retries) {
+            setTimeout(apply, params.retry_delay);
+            return;
+        }
+        emitResult(missing);
+    }
+    
+    (async function() {
+        if (document.readyState !== 'complete') {
+            await new Promise(resolve => {
+                window.addEventListener('load', resolve, { once: true });
+                setTimeout(resolve, params.fallback_timeout);
+            });
+        }
+        await new Promise(r => setTimeout(r, params.react_init_delay));
+        await apply();
+    })().catch(() => emitResult(params.toggles.map(toggle => toggle.name)));
+})();
+"#;
+
 /// JavaScript template for watching AI response completion
 pub const RESPONSE_WATCHER_JS: &str = r#"
 (function() {
     if (window.__kimiResponseWatcher) return;
     window.__kimiResponseWatcher = true;
     
-    const CHECK_INTERVAL = {{check_interval}};
-    const INITIAL_DELAY = {{initial_delay}};
+    const params = JSON.parse({{params}});
+    const CHECK_INTERVAL = params.check_interval;
+    const INITIAL_DELAY = params.initial_delay;
     let wasStreaming = false;
     let checkCount = 0;
-    const MAX_CHECKS = {{max_checks}};
+    const MAX_CHECKS = params.max_checks;
     
     function isStreaming() {
-        const stopBtn = document.querySelector('{{selector_stop_aria}}')
-            || document.querySelector('{{selector_stop_aria_cap}}')
-            || document.querySelector('{{selector_cancel_aria}}')
-            || document.querySelector('{{selector_stop_data_testid}}');
+        const stopBtn = document.querySelector(params.stop_aria)
+            || document.querySelector(params.stop_aria_cap)
+            || document.querySelector(params.cancel_aria)
+            || document.querySelector(params.stop_data_testid);
         return !!stopBtn;
     }
     
@@ -209,6 +331,9 @@ pub const RESPONSE_WATCHER_JS: &str = r#"
             if (checkCount > MAX_CHECKS) {
                 clearInterval(intervalId);
                 window.__kimiResponseWatcher = false;
+                if (window.__TAURI__) {
+                    window.__TAURI__.event.emit('response-watch-ended', {});
+                }
                 return;
             }
             
@@ -222,7 +347,10 @@ pub const RESPONSE_WATCHER_JS: &str = r#"
                 clearInterval(intervalId);
                 window.__kimiResponseWatcher = false;
                 if (window.__TAURI__) {
-                    window.__TAURI__.event.emit('response-complete', {});
+                    window.__TAURI__.event.emit('response-complete', {
+                        title: document.title,
+                        url: window.location.href
+                    });
                 }
             }
         }, CHECK_INTERVAL);
@@ -230,93 +358,236 @@ pub const RESPONSE_WATCHER_JS: &str = r#"
 })();
 "#;
 
-/// JavaScript template for PWA connectivity monitoring
+/// JavaScript forwarding the browser's online/offline events
+///
+/// Connectivity is decided by probes in Rust; these events only make the
+/// next probe happen immediately instead of at its scheduled time.
 pub const CONNECTIVITY_JS: &str = r#"
 (function() {
-    const CHAT_URL = '{{chat_url}}';
-    const RELOAD_KEY = '{{reload_key}}';
+    if (window.__kimiConnectivity) return;
+    window.__kimiConnectivity = true;
     
-    async function checkServiceWorker() {
-        if (!('serviceWorker' in navigator)) {
-            return { supported: false, registered: false };
-        }
-        try {
-            const registrations = await navigator.serviceWorker.getRegistrations();
-            const hasSW = registrations.length > 0;
-            return { supported: true, registered: hasSW };
-        } catch (e) {
-            return { supported: true, registered: false };
+    function checkNow() {
+        if (window.__TAURI__) {
+            window.__TAURI__.core.invoke('check_connectivity').catch(() => {});
         }
     }
     
-    window.addEventListener('offline', () => {
-        console.log('[Kimi] Browser went offline');
-    });
-    
-    window.addEventListener('online', () => {
-        if (window.location.href.includes('www.kimi.com')) {
-            window.location.reload();
-        } else {
-            window.location.href = CHAT_URL;
-        }
-    });
-    
-    setTimeout(async () => {
-        const isErrorPage = !navigator.onLine
-            || document.title.toLowerCase().includes('error')
-            || document.title.toLowerCase().includes('not found')
-            || document.title === ''
-            || (document.body && document.body.innerText.length < 50
-                && !document.querySelector('{{selector_data_sidebar}}'));
-        
-        if (!isErrorPage || window.location.href.includes('tauri')) {
-            await checkServiceWorker();
-            return;
-        }
-        
-        const sw = await checkServiceWorker();
-        if (sw.registered) {
-            if (sessionStorage.getItem(RELOAD_KEY)) {
-                sessionStorage.removeItem(RELOAD_KEY);
-                if (window.__TAURI__) {
-                    window.__TAURI__.core.invoke('navigate_to_offline').catch(() => {});
-                }
-            } else {
-                sessionStorage.setItem(RELOAD_KEY, '1');
-                window.location.reload();
-            }
-        } else {
-            if (window.__TAURI__) {
-                window.__TAURI__.core.invoke('navigate_to_offline').catch(() => {});
-            }
-        }
-    }, {{connectivity_check_delay}});
+    window.addEventListener('offline', checkNow);
+    window.addEventListener('online', checkNow);
 })();
 "#;
 
 /// JavaScript template for intercepting external links
+///
+/// Links leaving the first-party domains, opening a new window or matching a
+/// user rule are handed to `open_external_link`, which routes them in Rust.
 pub const LINK_INTERCEPTOR_JS: &str = r#"
 (function() {
     if (window.__kimiLinkInterceptor) return;
     window.__kimiLinkInterceptor = true;
     
+    const params = JSON.parse({{params}});
+    const FIRST_PARTY_DOMAINS = params.first_party_domains;
+    const RULE_HOSTS = params.rule_hosts;
+    
+    function hostMatches(host, domain) {
+        return host === domain || host.endsWith('.' + domain);
+    }
+    
     document.addEventListener('click', function(e) {
         const link = e.target.closest('a[href]');
         if (!link) return;
         
-        const href = link.getAttribute('href');
-        if (!href) return;
+        let url;
+        try {
+            url = new URL(link.href, window.location.href);
+        } catch (err) {
+            return;
+        }
         
-        const isExternal = href.startsWith('http') && !href.includes('kimi.com') && !href.includes('moonshot.cn');
-        const isMailto = href.startsWith('mailto:');
+        const isMailto = url.protocol === 'mailto:';
+        const isHttp = url.protocol === 'http:' || url.protocol === 'https:';
+        if (!isMailto && !isHttp) return;
         
-        if (isExternal || isMailto) {
-            e.preventDefault();
-            e.stopPropagation();
-            if (window.__TAURI__) {
-                window.__TAURI__.core.invoke('open_external_link', { url: href }).catch(() => {});
-            }
+        if (isHttp) {
+            const host = url.hostname.toLowerCase();
+            const isFirstParty = FIRST_PARTY_DOMAINS.some(d => hostMatches(host, d));
+            const hasRule = RULE_HOSTS.some(d => hostMatches(host, d));
+            if (isFirstParty && !hasRule && link.target !== '_blank') return;
+        }
+        
+        e.preventDefault();
+        e.stopPropagation();
+        if (window.__TAURI__) {
+            window.__TAURI__.core.invoke('open_external_link', { url: url.href }).catch(() => {});
+        }
+    }, true);
+})();
+"#;
+
+/// JavaScript template for switching the local fallback page to its offline state
+pub const OFFLINE_STATE_JS: &str = r#"
+(function() {
+    const params = JSON.parse({{params}});
+    window.__kimiChatUrl = params.chat_url;
+    const host = document.getElementById('offline-host');
+    if (host) host.textContent = params.chat_host;
+    document.getElementById('main-container').className = 'container offline';
+})();
+"#;
+
+/// JavaScript template for Ctrl/Cmd +, - and 0 zoom shortcuts
+pub const ZOOM_SHORTCUTS_JS: &str = r#"
+(function() {
+    if (window.__kimiZoomShortcuts) return;
+    window.__kimiZoomShortcuts = true;
+    
+    window.addEventListener('keydown', function(e) {
+        if (!(e.ctrlKey || e.metaKey) || e.altKey) return;
+        
+        let action = null;
+        if (e.key === '+' || e.key === '=' || e.code === 'NumpadAdd') {
+            action = 'in';
+        } else if (e.key === '-' || e.key === '_' || e.code === 'NumpadSubtract') {
+            action = 'out';
+        } else if (e.key === '0' || e.code === 'Numpad0') {
+            action = 'reset';
+        }
+        if (!action) return;
+        
+        e.preventDefault();
+        e.stopPropagation();
+        if (window.__TAURI__) {
+            window.__TAURI__.core.invoke('adjust_zoom', { action }).catch(() => {});
         }
     }, true);
 })();
 "#;
+
+/// JavaScript for the Ctrl/Cmd+Q shortcut that exits the app
+///
+/// Closing the window only hides it by default, so the page needs its own
+/// way to quit. Injected into the main window and the launcher and settings
+/// pages.
+pub const QUIT_SHORTCUT_JS: &str = r#"
+(function() {
+    if (window.__kimiQuitShortcut) return;
+    window.__kimiQuitShortcut = true;
+    
+    window.addEventListener('keydown', function(e) {
+        if (!(e.ctrlKey || e.metaKey) || e.altKey || e.shiftKey) return;
+        if (e.key !== 'q' && e.key !== 'Q') return;
+        
+        e.preventDefault();
+        e.stopPropagation();
+        if (window.__TAURI__) {
+            window.__TAURI__.core.invoke('quit_app').catch(() => {});
+        }
+    }, true);
+})();
+"#;
+
+/// JavaScript template for the send status toast in the main window
+///
+/// Shows `text` for the `status` of a prompt being sent: `sending`,
+/// `retrying`, `failed` (with resend and dismiss actions for the failed
+/// prompt `failed_id`) or `hidden`.
+pub const DELIVERY_TOAST_JS: &str = r#"
+(function() {
+    const ID = 'kimi-delivery-toast';
+    const params = JSON.parse({{params}});
+    const status = params.status;
+    const text = params.text;
+    const failedId = params.failed_id;
+    
+    let toast = document.getElementById(ID);
+    if (status === 'hidden') {
+        if (toast) toast.remove();
+        return;
+    }
+    if (!toast) {
+        toast = document.createElement('div');
+        toast.id = ID;
+        toast.setAttribute('role', 'status');
+        toast.style.cssText = [
+            'position: fixed', 'left: 50%', 'bottom: 24px', 'transform: translateX(-50%)',
+            'z-index: 2147483647', 'display: flex', 'align-items: center', 'gap: 12px',
+            'max-width: 480px', 'padding: 10px 16px', 'border-radius: 10px',
+            'font: 13px -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif',
+            'color: #fff', 'box-shadow: 0 4px 16px rgba(0, 0, 0, 0.25)'
+        ].join(';');
+        document.body.appendChild(toast);
+    }
+    toast.style.background = status === 'failed' ? '#c62828' : '#323236';
+    
+    const label = document.createElement('span');
+    label.textContent = text;
+    toast.replaceChildren(label);
+    
+    function action(name, command) {
+        const button = document.createElement('button');
+        button.textContent = name;
+        button.style.cssText = 'border: none; background: none; color: inherit; font: inherit; font-weight: 600; cursor: pointer; padding: 0;';
+        button.addEventListener('click', () => {
+            toast.remove();
+            if (window.__TAURI__) {
+                window.__TAURI__.core.invoke(command, { id: failedId }).catch(() => {});
+            }
+        });
+        toast.appendChild(button);
+    }
+    
+    if (status === 'failed' && failedId !== null) {
+        action('Resend', 'resend_failed_prompt');
+        action('Dismiss', 'dismiss_failed_prompt');
+    }
+})();
+"#;
+
+/// JavaScript template reporting the conversation shown in the page
+///
+/// Kimi switches conversations without reloading, so title changes and
+/// history navigation are observed and reported as `conversation-changed`.
+pub const CONVERSATION_OBSERVER_JS: &str = r#"
+(function() {
+    if (window.__kimiConversationObserver) return;
+    window.__kimiConversationObserver = true;
+    
+    const params = JSON.parse({{params}});
+    const DEBOUNCE = params.debounce;
+    let lastReported = '';
+    let timer = null;
+    
+    function report() {
+        clearTimeout(timer);
+        timer = setTimeout(() => {
+            const key = window.location.href + '\n' + document.title;
+            if (key === lastReported) return;
+            lastReported = key;
+            if (window.__TAURI__) {
+                window.__TAURI__.event.emit('conversation-changed', {
+                    title: document.title,
+                    url: window.location.href
+                });
+            }
+        }, DEBOUNCE);
+    }
+    
+    new MutationObserver(report).observe(document.head, {
+        subtree: true,
+        childList: true,
+        characterData: true
+    });
+    window.addEventListener('popstate', report);
+    for (const method of ['pushState', 'replaceState']) {
+        const original = history[method];
+        history[method] = function() {
+            const result = original.apply(this, arguments);
+            report();
+            return result;
+        };
+    }
+    report();
+})();
+"#;
@@ -0,0 +1,235 @@
+//! Timings for sending prompts and watching responses
+//!
+//! The defaults are the `Timeouts` constants. Slow machines or connections
+//! may need longer waits, so the settings window's Advanced section can
+//! change them; they are read again for every prompt sent.
+
+use std::ops::RangeInclusive;
+
+use crate::wrappers::{
+    config::Timeouts,
+    error::{WrapperError, WrapperResult},
+};
+
+/// Timings in milliseconds, except for the counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TimingSettings {
+    /// Time the injected script has to find the input and send
+    pub injection_total: u64,
+    /// Attempts at finding the chat input
+    pub injection_max_retries: u32,
+    /// Pause between attempts at finding the chat input
+    pub retry_delay: u64,
+    /// Wait for the page's editor to initialize before each attempt
+    pub react_init_delay: u64,
+    pub focus_delay: u64,
+    /// Wait between filling in the message and pressing send
+    pub submit_delay: u64,
+    /// Longest wait for a still loading page's `load` event
+    pub fallback_load_timeout: u64,
+    /// Wait after opening a chat page before sending
+    pub page_load_wait: u64,
+    /// Wait after opening an agent page such as Kimi Claw before sending
+    pub bot_page_load_wait: u64,
+    /// Wait after showing the window when sending to the current page
+    pub window_visible_delay: u64,
+    pub response_watcher_interval: u64,
+    pub response_watcher_initial_delay: u64,
+    /// Checks before the response watcher gives up
+    pub response_watcher_max_checks: u32,
+}
+
+impl Default for TimingSettings {
+    fn default() -> Self {
+        Self {
+            injection_total: Timeouts::INJECTION_TOTAL,
+            injection_max_retries: Timeouts::INJECTION_MAX_RETRIES,
+            retry_delay: Timeouts::RETRY_DELAY,
+            react_init_delay: Timeouts::REACT_INIT_DELAY,
+            focus_delay: Timeouts::FOCUS_DELAY,
+            submit_delay: Timeouts::SUBMIT_DELAY,
+            fallback_load_timeout: Timeouts::FALLBACK_LOAD_TIMEOUT,
+            page_load_wait: Timeouts::PAGE_LOAD_WAIT,
+            bot_page_load_wait: Timeouts::BOT_PAGE_LOAD_WAIT,
+            window_visible_delay: Timeouts::WINDOW_VISIBLE_DELAY,
+            response_watcher_interval: Timeouts::RESPONSE_WATCHER_INTERVAL,
+            response_watcher_initial_delay: Timeouts::RESPONSE_WATCHER_INITIAL_DELAY,
+            response_watcher_max_checks: Timeouts::RESPONSE_WATCHER_MAX_CHECKS,
+        }
+    }
+}
+
+impl TimingSettings {
+    /// Each field with its value and allowed range
+    fn ranges(&self) -> [(&'static str, u64, RangeInclusive<u64>); 13] {
+        [
+            ("injection_total", self.injection_total, 1_000..=60_000),
+            (
+                "injection_max_retries",
+                self.injection_max_retries.into(),
+                1..=100,
+            ),
+            ("retry_delay", self.retry_delay, 50..=5_000),
+            ("react_init_delay", self.react_init_delay, 0..=5_000),
+            ("focus_delay", self.focus_delay, 0..=2_000),
+            ("submit_delay", self.submit_delay, 0..=5_000),
+            (
+                "fallback_load_timeout",
+                self.fallback_load_timeout,
+                0..=10_000,
+            ),
+            ("page_load_wait", self.page_load_wait, 0..=10_000),
+            ("bot_page_load_wait", self.bot_page_load_wait, 0..=10_000),
+            ("window_visible_delay", self.window_visible_delay, 0..=2_000),
+            (
+                "response_watcher_interval",
+                self.response_watcher_interval,
+                100..=10_000,
+            ),
+            (
+                "response_watcher_initial_delay",
+                self.response_watcher_initial_delay,
+                0..=30_000,
+            ),
+            (
+                "response_watcher_max_checks",
+                self.response_watcher_max_checks.into(),
+                1..=10_000,
+            ),
+        ]
+    }
+
+    /// Check every value is in range and the injection steps fit its timeout
+    pub fn validate(&self) -> WrapperResult<()> {
+        let invalid = |field: &str, message: String| WrapperError::InvalidSettings {
+            field: format!("timing.{}", field),
+            message,
+        };
+        for (field, value, range) in self.ranges() {
+            if !range.contains(&value) {
+                return Err(invalid(
+                    field,
+                    format!(
+                        "{} must be between {} and {}",
+                        value,
+                        range.start(),
+                        range.end()
+                    ),
+                ));
+            }
+        }
+
+        let retries = u64::from(self.injection_max_retries) * self.retry_delay;
+        if retries > self.injection_total {
+            return Err(invalid(
+                "retry_delay",
+                format!(
+                    "{} retries of {} ms take longer than the {} ms injection timeout",
+                    self.injection_max_retries, self.retry_delay, self.injection_total
+                ),
+            ));
+        }
+        let attempt = self.fallback_load_timeout
+            + self.react_init_delay
+            + self.focus_delay
+            + self.submit_delay;
+        if attempt >= self.injection_total {
+            return Err(invalid(
+                "injection_total",
+                format!(
+                    "{} ms is not enough for one attempt, which can take {} ms",
+                    self.injection_total, attempt
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Longest time the response watcher keeps watching
+    pub fn response_watch_limit(&self) -> u64 {
+        self.response_watcher_initial_delay
+            + self.response_watcher_interval * u64::from(self.response_watcher_max_checks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field_of(error: WrapperError) -> String {
+        match error {
+            WrapperError::InvalidSettings { field, .. } => field,
+            other => panic!("unexpected error {}", other),
+        }
+    }
+
+    #[test]
+    fn test_defaults_are_valid() {
+        TimingSettings::default().validate().unwrap();
+    }
+
+    #[test]
+    fn test_rejects_values_out_of_range() {
+        let timing = TimingSettings {
+            focus_delay: 5_000,
+            ..Default::default()
+        };
+        assert_eq!(
+            field_of(timing.validate().unwrap_err()),
+            "timing.focus_delay"
+        );
+
+        let timing = TimingSettings {
+            response_watcher_max_checks: 0,
+            ..Default::default()
+        };
+        let error = timing.validate().unwrap_err();
+        assert!(
+            error.to_string().contains("between 1 and 10000"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_retries_must_fit_injection_timeout() {
+        let timing = TimingSettings {
+            injection_max_retries: 20,
+            retry_delay: 500,
+            ..Default::default()
+        };
+        assert_eq!(
+            field_of(timing.validate().unwrap_err()),
+            "timing.retry_delay"
+        );
+
+        let timing = TimingSettings {
+            injection_total: 20_000,
+            ..timing
+        };
+        timing.validate().unwrap();
+    }
+
+    #[test]
+    fn test_single_attempt_must_fit_injection_timeout() {
+        let timing = TimingSettings {
+            injection_total: 2_000,
+            injection_max_retries: 2,
+            fallback_load_timeout: 1_500,
+            submit_delay: 500,
+            ..Default::default()
+        };
+        assert_eq!(
+            field_of(timing.validate().unwrap_err()),
+            "timing.injection_total"
+        );
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let timing: TimingSettings = serde_json::from_str(r#"{"retry_delay": 400}"#).unwrap();
+        assert_eq!(timing.retry_delay, 400);
+        assert_eq!(timing.injection_total, Timeouts::INJECTION_TOTAL);
+    }
+}
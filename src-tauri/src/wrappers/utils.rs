@@ -1,28 +1,91 @@
 //! Utility functions for wrapper operations
 
-/// Escape a string for safe use in JavaScript template literals
+use crate::wrappers::error::{WrapperError, WrapperResult};
+
+/// Encode `value` as a JavaScript string literal holding its JSON
 ///
-/// Escapes: backslashes, backticks, dollar signs, newlines, carriage returns
+/// The page decodes it with `JSON.parse`, so the value never becomes part of
+/// the script's syntax. On top of the JSON string escapes, `<` is written as
+/// `\u003c` so the literal cannot end a `<script>` element or open an HTML
+/// comment, and U+2028 and U+2029 are escaped for engines that still treat
+/// them as line terminators.
 ///
 /// # Examples
 ///
 /// ```
-/// use kimi_lib::wrappers::utils::escape_js;
+/// use kimi_lib::wrappers::utils::js_json_literal;
 ///
-/// let escaped = escape_js("Hello `world`");
-/// assert_eq!(escaped, "Hello \\`world\\`");
+/// let literal = js_json_literal(&["</script>"]).unwrap();
+/// assert_eq!(literal, r#""[\"\u003c/script>\"]""#);
 /// ```
-pub fn escape_js(s: &str) -> String {
-    s.replace('\\', "\\\\")
-        .replace('`', "\\`")
-        .replace('$', "\\$")
-        .replace('\n', "\\n")
-        .replace('\r', "\\r")
+pub fn js_json_literal<T: serde::Serialize + ?Sized>(value: &T) -> WrapperResult<String> {
+    let json = serde_json::to_string(value)?;
+    let literal = serde_json::to_string(&json)?;
+    let mut escaped = String::with_capacity(literal.len());
+    for c in literal.chars() {
+        match c {
+            '<' => escaped.push_str("\\u003c"),
+            '\u{2028}' => escaped.push_str("\\u2028"),
+            '\u{2029}' => escaped.push_str("\\u2029"),
+            _ => escaped.push(c),
+        }
+    }
+    Ok(escaped)
+}
+
+/// A piece of a parsed template
+enum Segment<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+}
+
+/// Split `template` into text and `{{name}}` placeholders
+///
+/// Names are ASCII letters, digits and underscores. Any other `{{` or `}}`
+/// is rejected, since it is almost always a typo in the template.
+fn parse_template(template: &str) -> Result<Vec<Segment<'_>>, String> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let (text, tail) = rest.split_at(start);
+        if text.contains("}}") {
+            return Err(format!("stray '}}}}' in \"{}\"", text.trim()));
+        }
+        let end = tail.find("}}").ok_or_else(|| "unclosed '{{'".to_string())?;
+        let name = &tail[2..end];
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("invalid placeholder '{{{{{}}}}}'", name));
+        }
+        segments.push(Segment::Text(text));
+        segments.push(Segment::Placeholder(name));
+        rest = &tail[end + 2..];
+    }
+    if rest.contains("}}") {
+        return Err(format!("stray '}}}}' in \"{}\"", rest.trim()));
+    }
+    segments.push(Segment::Text(rest));
+    Ok(segments)
+}
+
+/// Names of the placeholders in `template`, each listed once
+#[cfg(test)]
+pub fn placeholders(template: &str) -> Result<Vec<&str>, String> {
+    let mut names = Vec::new();
+    for segment in parse_template(template)? {
+        if let Segment::Placeholder(name) = segment {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    Ok(names)
 }
 
 /// Build a JavaScript template by replacing placeholders
 ///
-/// Placeholders are in the format `{{key}}`
+/// Placeholders are in the format `{{key}}`. Every placeholder needs a value
+/// and every value a placeholder; `name` identifies the template in errors.
+/// Values are inserted as given and not scanned for placeholders.
 ///
 /// # Examples
 ///
@@ -30,13 +93,43 @@ pub fn escape_js(s: &str) -> String {
 /// use kimi_lib::wrappers::utils::build_js;
 ///
 /// let template = "Hello {{name}}!";
-/// let result = build_js(template, &[("name", "World")]);
+/// let result = build_js("greeting", template, &[("name", "World")]).unwrap();
 /// assert_eq!(result, "Hello World!");
+/// assert!(build_js("greeting", template, &[]).is_err());
 /// ```
-pub fn build_js(template: &str, vars: &[(&str, &str)]) -> String {
-    vars.iter().fold(template.to_string(), |acc, (key, val)| {
-        acc.replace(&format!("{{{{{}}}}}", key), val)
-    })
+pub fn build_js(name: &str, template: &str, vars: &[(&str, &str)]) -> WrapperResult<String> {
+    let error = |message: String| WrapperError::TemplateError {
+        template: name.to_string(),
+        message,
+    };
+    let segments = parse_template(template).map_err(error)?;
+
+    for (i, (key, _)) in vars.iter().enumerate() {
+        if vars[..i].iter().any(|(other, _)| other == key) {
+            return Err(error(format!("'{}' is given twice", key)));
+        }
+        let used = segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Placeholder(p) if p == key));
+        if !used {
+            return Err(error(format!("unknown parameter '{}'", key)));
+        }
+    }
+
+    let mut js = String::with_capacity(template.len());
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => js.push_str(text),
+            Segment::Placeholder(placeholder) => {
+                let (_, value) = vars
+                    .iter()
+                    .find(|(key, _)| *key == placeholder)
+                    .ok_or_else(|| error(format!("no value for '{{{{{}}}}}'", placeholder)))?;
+                js.push_str(value);
+            }
+        }
+    }
+    Ok(js)
 }
 
 #[cfg(test)]
@@ -44,47 +137,50 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_escape_js_escapes_backticks() {
-        let escaped = escape_js("code `inline` here");
-        assert!(escaped.contains(r"\`inline\`"));
-        assert!(!escaped.contains("code `inline` here"));
-    }
-
-    #[test]
-    fn test_escape_js_escapes_backslashes() {
-        let escaped = escape_js(r"path\to\file");
-        assert!(escaped.contains(r"path\\to\\file"));
+    fn test_build_js_replaces_placeholders() {
+        let template = "Hello {{name}}, you are {{age}} years old";
+        let result = build_js("test", template, &[("name", "Alice"), ("age", "30")]).unwrap();
+        assert_eq!(result, "Hello Alice, you are 30 years old");
     }
 
     #[test]
-    fn test_escape_js_escapes_dollar_signs() {
-        let escaped = escape_js("cost is $100");
-        assert!(escaped.contains(r"cost is \$100"));
+    fn test_build_js_rejects_missing_values() {
+        let err = build_js("test", "Hello {{name}}", &[]).unwrap_err();
+        assert!(err.to_string().contains("{{name}}"), "{}", err);
     }
 
     #[test]
-    fn test_escape_js_escapes_newlines() {
-        let escaped = escape_js("line1\nline2\rline3");
-        assert!(escaped.contains(r"line1\nline2\rline3"));
+    fn test_build_js_rejects_unknown_and_repeated_keys() {
+        assert!(build_js("test", "Hello {{name}}", &[("name", "a"), ("age", "1")]).is_err());
+        assert!(build_js("test", "Hello {{name}}", &[("name", "a"), ("name", "b")]).is_err());
     }
 
     #[test]
-    fn test_escape_js_handles_empty_string() {
-        let escaped = escape_js("");
-        assert_eq!(escaped, "");
+    fn test_build_js_rejects_stray_braces() {
+        for template in [
+            "f(() => {}}, 1)",
+            "{{name}} }}",
+            "{{ name }}",
+            "{{name",
+            "{{}}",
+        ] {
+            assert!(
+                build_js("test", template, &[("name", "a")]).is_err(),
+                "{}",
+                template
+            );
+        }
     }
 
     #[test]
-    fn test_build_js_replaces_placeholders() {
-        let template = "Hello {{name}}, you are {{age}} years old";
-        let result = build_js(template, &[("name", "Alice"), ("age", "30")]);
-        assert_eq!(result, "Hello Alice, you are 30 years old");
+    fn test_build_js_does_not_expand_values() {
+        let result = build_js("test", "{{a}} {{b}}", &[("a", "{{b}}"), ("b", "x")]).unwrap();
+        assert_eq!(result, "{{b}} x");
     }
 
     #[test]
-    fn test_build_js_handles_missing_placeholders() {
-        let template = "Hello {{name}}";
-        let result = build_js(template, &[]);
-        assert_eq!(result, "Hello {{name}}");
+    fn test_placeholders_lists_each_name_once() {
+        assert_eq!(placeholders("{{a}} {{b}} {{a}}").unwrap(), vec!["a", "b"]);
+        assert!(placeholders("no placeholders").unwrap().is_empty());
     }
 }
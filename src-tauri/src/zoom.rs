@@ -0,0 +1,117 @@
+//! Per-window zoom level, persisted in settings
+
+use tauri::{Manager, WebviewWindow};
+
+use crate::settings::{self, AppSettings};
+use crate::wrappers::{config::Zoom, WrapperResult};
+
+/// Zoom change requested by a keyboard shortcut
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ZoomAction {
+    In,
+    Out,
+    Reset,
+}
+
+/// Clamp a zoom factor to the supported range
+pub fn clamp(level: f64) -> f64 {
+    if level.is_finite() {
+        level.clamp(Zoom::MIN, Zoom::MAX)
+    } else {
+        Zoom::DEFAULT
+    }
+}
+
+/// Compute the zoom factor that follows `current` for the given action
+///
+/// Results are rounded to two decimals so repeated steps don't accumulate
+/// floating point drift in the stored value.
+pub fn next(current: f64, action: ZoomAction) -> f64 {
+    let level = match action {
+        ZoomAction::In => current + Zoom::STEP,
+        ZoomAction::Out => current - Zoom::STEP,
+        ZoomAction::Reset => Zoom::DEFAULT,
+    };
+    clamp((level * 100.0).round() / 100.0)
+}
+
+/// Stored zoom factor for a window label
+pub fn level_for(settings: &AppSettings, label: &str) -> f64 {
+    settings
+        .zoom_levels
+        .get(label)
+        .copied()
+        .map(clamp)
+        .unwrap_or(Zoom::DEFAULT)
+}
+
+/// Re-apply the persisted zoom factor, e.g. after a navigation or restart
+pub fn apply_saved(window: &WebviewWindow) -> WrapperResult<()> {
+    let settings = settings::load(window.app_handle())?;
+    window.set_zoom(level_for(&settings, window.label()))?;
+    Ok(())
+}
+
+/// Apply a zoom action to the window and persist the resulting factor
+pub fn adjust(window: &WebviewWindow, action: ZoomAction) -> WrapperResult<f64> {
+    let label = window.label().to_string();
+    let mut level = Zoom::DEFAULT;
+    settings::update(window.app_handle(), |s| {
+        level = next(level_for(s, &label), action);
+        if level == Zoom::DEFAULT {
+            s.zoom_levels.remove(&label);
+        } else {
+            s.zoom_levels.insert(label.clone(), level);
+        }
+    })?;
+    window.set_zoom(level)?;
+    Ok(level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_steps_in_and_out() {
+        assert_eq!(next(1.0, ZoomAction::In), 1.1);
+        assert_eq!(next(1.0, ZoomAction::Out), 0.9);
+        assert_eq!(next(1.7, ZoomAction::Reset), Zoom::DEFAULT);
+    }
+
+    #[test]
+    fn test_next_does_not_drift() {
+        let mut level = Zoom::DEFAULT;
+        for _ in 0..3 {
+            level = next(level, ZoomAction::In);
+        }
+        assert_eq!(level, 1.3);
+        for _ in 0..3 {
+            level = next(level, ZoomAction::Out);
+        }
+        assert_eq!(level, Zoom::DEFAULT);
+    }
+
+    #[test]
+    fn test_next_clamps_to_limits() {
+        assert_eq!(next(Zoom::MAX, ZoomAction::In), Zoom::MAX);
+        assert_eq!(next(Zoom::MIN, ZoomAction::Out), Zoom::MIN);
+    }
+
+    #[test]
+    fn test_clamp_rejects_non_finite() {
+        assert_eq!(clamp(f64::NAN), Zoom::DEFAULT);
+        assert_eq!(clamp(f64::INFINITY), Zoom::DEFAULT);
+    }
+
+    #[test]
+    fn test_level_for_uses_stored_value_or_default() {
+        let mut settings = AppSettings::default();
+        assert_eq!(level_for(&settings, "main"), Zoom::DEFAULT);
+        settings.zoom_levels.insert("main".to_string(), 1.5);
+        assert_eq!(level_for(&settings, "main"), 1.5);
+        settings.zoom_levels.insert("main".to_string(), 10.0);
+        assert_eq!(level_for(&settings, "main"), Zoom::MAX);
+    }
+}
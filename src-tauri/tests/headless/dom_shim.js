@@ -0,0 +1,720 @@
+// Minimal browser environment for running the injected templates in tests
+//
+// Covers what the templates touch: elements parsed from a fixture page,
+// querySelector with the selectors in `wrappers::config::Selectors`, events
+// with capture and bubbling, URL, and timers on a virtual clock that the
+// Rust side advances. Calls to `window.__TAURI__` are recorded instead of
+// reaching an app.
+(function (global) {
+    'use strict';
+
+    // ---- Timers -------------------------------------------------------
+
+    let now = 0;
+    let nextTimerId = 1;
+    let nextSeq = 1;
+    const timers = new Map();
+
+    function schedule(fn, delay, args, repeat) {
+        const id = nextTimerId++;
+        const ms = Math.max(0, Number(delay) || 0);
+        timers.set(id, { fn, args, at: now + ms, interval: repeat ? Math.max(1, ms) : null, seq: nextSeq++ });
+        return id;
+    }
+
+    global.setTimeout = (fn, delay, ...args) => schedule(fn, delay, args, false);
+    global.setInterval = (fn, delay, ...args) => schedule(fn, delay, args, true);
+    global.clearTimeout = id => { timers.delete(id); };
+    global.clearInterval = id => { timers.delete(id); };
+    Date.now = () => now;
+
+    // Run the earliest timer due at or before `limit`; false once none is left
+    global.__runNextTimer = function (limit) {
+        let next = null;
+        for (const [id, timer] of timers) {
+            if (timer.at > limit) continue;
+            if (!next || timer.at < next.timer.at || (timer.at === next.timer.at && timer.seq < next.timer.seq)) {
+                next = { id, timer };
+            }
+        }
+        if (!next) {
+            now = Math.max(now, limit);
+            return false;
+        }
+        const { id, timer } = next;
+        now = timer.at;
+        if (timer.interval === null) {
+            timers.delete(id);
+        } else {
+            timer.at += timer.interval;
+            timer.seq = nextSeq++;
+        }
+        timer.fn(...timer.args);
+        return true;
+    };
+    global.__now = () => now;
+
+    // ---- URL ------------------------------------------------------------
+
+    class URL {
+        constructor(url, base) {
+            url = String(url).trim();
+            const scheme = /^([a-zA-Z][a-zA-Z0-9+.-]*:)(.*)$/.exec(url);
+            if (!scheme) {
+                if (base === undefined) throw new TypeError('Invalid URL: ' + url);
+                const b = base instanceof URL ? base : new URL(base);
+                let resolved;
+                if (url.startsWith('//')) resolved = b.protocol + url;
+                else if (url.startsWith('/')) resolved = b.origin + url;
+                else if (url.startsWith('?')) resolved = b.origin + b.pathname + url;
+                else if (url.startsWith('#')) resolved = b.origin + b.pathname + b.search + url;
+                else resolved = b.origin + b.pathname.replace(/[^/]*$/, '') + url;
+                return new URL(resolved);
+            }
+            this.protocol = scheme[1].toLowerCase();
+            let rest = scheme[2];
+            const hash = rest.indexOf('#');
+            this.hash = hash >= 0 ? rest.slice(hash) : '';
+            if (hash >= 0) rest = rest.slice(0, hash);
+            const query = rest.indexOf('?');
+            this.search = query >= 0 ? rest.slice(query) : '';
+            if (query >= 0) rest = rest.slice(0, query);
+            if (rest.startsWith('//')) {
+                const authority = rest.slice(2).split('/')[0];
+                const host = authority.slice(authority.lastIndexOf('@') + 1).toLowerCase();
+                if (!host) throw new TypeError('Invalid URL: ' + url);
+                this.host = host;
+                this.hostname = host.replace(/:\d+$/, '');
+                this.port = (/:(\d+)$/.exec(host) || [null, ''])[1];
+                this.pathname = rest.slice(2 + authority.length) || '/';
+            } else {
+                this.host = this.hostname = this.port = '';
+                this.pathname = rest;
+            }
+        }
+
+        get origin() {
+            return this.host ? this.protocol + '//' + this.host : 'null';
+        }
+
+        get href() {
+            return (this.host ? this.protocol + '//' + this.host : this.protocol) + this.pathname + this.search + this.hash;
+        }
+
+        toString() {
+            return this.href;
+        }
+    }
+    global.URL = URL;
+
+    // ---- Events ---------------------------------------------------------
+
+    class Event {
+        constructor(type, init = {}) {
+            this.type = type;
+            this.bubbles = !!init.bubbles;
+            this.cancelable = !!init.cancelable;
+            this.defaultPrevented = false;
+            this.target = null;
+            this.currentTarget = null;
+            this.stopped = false;
+        }
+
+        preventDefault() {
+            if (this.cancelable) this.defaultPrevented = true;
+        }
+
+        stopPropagation() {
+            this.stopped = true;
+        }
+    }
+
+    class InputEvent extends Event {
+        constructor(type, init = {}) {
+            super(type, init);
+            this.inputType = init.inputType || '';
+            this.data = init.data === undefined ? null : init.data;
+        }
+    }
+
+    class KeyboardEvent extends Event {
+        constructor(type, init = {}) {
+            super(type, init);
+            this.key = init.key || '';
+            this.code = init.code || '';
+            this.keyCode = init.keyCode || 0;
+            this.which = init.which || 0;
+        }
+    }
+
+    class MouseEvent extends Event {}
+
+    // Every dispatched event, for assertions on what the page saw
+    global.__dispatched = [];
+
+    class EventTarget {
+        constructor() {
+            this.listeners = [];
+        }
+
+        addEventListener(type, fn, options) {
+            const capture = typeof options === 'boolean' ? options : !!(options && options.capture);
+            const once = typeof options === 'object' && options !== null && !!options.once;
+            if (this.listeners.some(l => l.type === type && l.fn === fn && l.capture === capture)) return;
+            this.listeners.push({ type, fn, capture, once });
+        }
+
+        removeEventListener(type, fn, options) {
+            const capture = typeof options === 'boolean' ? options : !!(options && options.capture);
+            this.listeners = this.listeners.filter(l => !(l.type === type && l.fn === fn && l.capture === capture));
+        }
+
+        callListeners(event, phase) {
+            event.currentTarget = this;
+            for (const listener of this.listeners.slice()) {
+                if (listener.type !== event.type) continue;
+                if (phase === 'capture' && !listener.capture) continue;
+                if (phase === 'bubble' && listener.capture) continue;
+                if (listener.once) this.removeEventListener(event.type, listener.fn, listener.capture);
+                listener.fn.call(this, event);
+            }
+        }
+
+        dispatchEvent(event) {
+            event.target = this;
+            global.__dispatched.push({ type: event.type, target: describe(this) });
+            const path = [];
+            for (let node = this.parentNode; node; node = node.parentNode) path.push(node);
+            if (this !== global) path.push(global);
+            for (let i = path.length - 1; i >= 0 && !event.stopped; i--) path[i].callListeners(event, 'capture');
+            if (!event.stopped) this.callListeners(event, 'target');
+            if (event.bubbles) {
+                for (let i = 0; i < path.length && !event.stopped; i++) path[i].callListeners(event, 'bubble');
+            }
+            event.currentTarget = null;
+            return !event.defaultPrevented;
+        }
+    }
+
+    // ---- Nodes ----------------------------------------------------------
+
+    class Node extends EventTarget {
+        constructor() {
+            super();
+            this.parentNode = null;
+            this.childNodes = [];
+        }
+
+        get parentElement() {
+            return this.parentNode instanceof Element ? this.parentNode : null;
+        }
+
+        appendChild(child) {
+            if (child.parentNode) child.parentNode.removeChild(child);
+            child.parentNode = this;
+            this.childNodes.push(child);
+            return child;
+        }
+
+        removeChild(child) {
+            this.childNodes = this.childNodes.filter(c => c !== child);
+            child.parentNode = null;
+            return child;
+        }
+
+        remove() {
+            if (this.parentNode) this.parentNode.removeChild(this);
+        }
+
+        replaceChildren(...nodes) {
+            for (const child of this.childNodes) child.parentNode = null;
+            this.childNodes = [];
+            for (const node of nodes) this.appendChild(node);
+        }
+
+        get textContent() {
+            return this.childNodes.map(c => c.textContent).join('');
+        }
+
+        set textContent(value) {
+            this.replaceChildren();
+            if (value !== null && value !== undefined && value !== '') {
+                this.appendChild(new Text(String(value)));
+            }
+        }
+    }
+
+    class Text extends Node {
+        constructor(data) {
+            super();
+            this.data = data;
+        }
+
+        get textContent() {
+            return this.data;
+        }
+
+        set textContent(value) {
+            this.data = String(value);
+        }
+    }
+
+    class Element extends Node {
+        constructor(tagName, attributes = {}) {
+            super();
+            this.tagName = tagName.toUpperCase();
+            this.attributes = new Map(Object.entries(attributes));
+            this.style = {};
+        }
+
+        getAttribute(name) {
+            name = name.toLowerCase();
+            return this.attributes.has(name) ? this.attributes.get(name) : null;
+        }
+
+        setAttribute(name, value) {
+            this.attributes.set(name.toLowerCase(), String(value));
+        }
+
+        hasAttribute(name) {
+            return this.attributes.has(name.toLowerCase());
+        }
+
+        removeAttribute(name) {
+            this.attributes.delete(name.toLowerCase());
+        }
+
+        get id() {
+            return this.getAttribute('id') || '';
+        }
+
+        set id(value) {
+            this.setAttribute('id', value);
+        }
+
+        get className() {
+            return this.getAttribute('class') || '';
+        }
+
+        set className(value) {
+            this.setAttribute('class', value);
+        }
+
+        get classList() {
+            const element = this;
+            const names = () => element.className.split(/\s+/).filter(Boolean);
+            return {
+                contains: name => names().includes(name),
+                add: (...added) => { element.className = [...new Set([...names(), ...added])].join(' '); },
+                remove: (...removed) => { element.className = names().filter(n => !removed.includes(n)).join(' '); },
+            };
+        }
+
+        get children() {
+            return this.childNodes.filter(c => c instanceof Element);
+        }
+
+        get contentEditable() {
+            const value = this.getAttribute('contenteditable');
+            if (value === null) return 'inherit';
+            return value === '' ? 'true' : value;
+        }
+
+        set contentEditable(value) {
+            this.setAttribute('contenteditable', value);
+        }
+
+        get innerHTML() {
+            return this.childNodes.map(serialize).join('');
+        }
+
+        set innerHTML(html) {
+            this.replaceChildren();
+            parseInto(this, String(html));
+        }
+
+        focus() {
+            global.document.activeElement = this;
+        }
+
+        blur() {
+            if (global.document.activeElement === this) global.document.activeElement = null;
+        }
+
+        click() {
+            this.dispatchEvent(new MouseEvent('click', { bubbles: true, cancelable: true }));
+        }
+
+        matches(selector) {
+            return parseSelectorList(selector).some(complex => matchesComplex(this, complex));
+        }
+
+        closest(selector) {
+            for (let node = this; node instanceof Element; node = node.parentNode) {
+                if (node.matches(selector)) return node;
+            }
+            return null;
+        }
+
+        querySelectorAll(selector) {
+            const list = parseSelectorList(selector);
+            return descendants(this).filter(el => list.some(complex => matchesComplex(el, complex)));
+        }
+
+        querySelector(selector) {
+            return this.querySelectorAll(selector)[0] || null;
+        }
+
+        getElementsByTagName(tag) {
+            return descendants(this).filter(el => el.tagName === tag.toUpperCase());
+        }
+    }
+
+    class HTMLTextAreaElement extends Element {
+        get value() {
+            return this.currentValue === undefined ? this.textContent : this.currentValue;
+        }
+
+        set value(value) {
+            this.currentValue = String(value);
+        }
+    }
+
+    class HTMLAnchorElement extends Element {
+        get href() {
+            const href = this.getAttribute('href');
+            if (href === null) return '';
+            try {
+                return new URL(href, global.location.href).href;
+            } catch (err) {
+                return href;
+            }
+        }
+
+        get target() {
+            return this.getAttribute('target') || '';
+        }
+    }
+
+    class HTMLButtonElement extends Element {}
+
+    const ELEMENT_CLASSES = { TEXTAREA: HTMLTextAreaElement, A: HTMLAnchorElement, BUTTON: HTMLButtonElement };
+
+    function createElement(tagName, attributes) {
+        const Class = ELEMENT_CLASSES[tagName.toUpperCase()] || Element;
+        return new Class(tagName, attributes);
+    }
+
+    function descendants(root) {
+        const found = [];
+        (function walk(node) {
+            for (const child of node.children) {
+                found.push(child);
+                walk(child);
+            }
+        })(root);
+        return found;
+    }
+
+    function describe(target) {
+        if (target === global) return 'window';
+        if (target === global.document) return 'document';
+        if (!(target instanceof Element)) return '#text';
+        let text = target.tagName.toLowerCase();
+        if (target.id) text += '#' + target.id;
+        for (const name of target.className.split(/\s+/).filter(Boolean)) text += '.' + name;
+        return text;
+    }
+
+    // ---- HTML -------------------------------------------------------------
+
+    const VOID_ELEMENTS = ['area', 'base', 'br', 'col', 'embed', 'hr', 'img', 'input', 'link', 'meta', 'source', 'wbr'];
+    const ENTITIES = { amp: '&', lt: '<', gt: '>', quot: '"', apos: "'", nbsp: ' ' };
+
+    function decodeEntities(text) {
+        return text.replace(/&(#x[0-9a-f]+|#\d+|\w+);/gi, (entity, name) => {
+            if (name[0] === '#') {
+                const code = name[1] === 'x' || name[1] === 'X' ? parseInt(name.slice(2), 16) : parseInt(name.slice(1), 10);
+                return String.fromCodePoint(code);
+            }
+            return ENTITIES[name] !== undefined ? ENTITIES[name] : entity;
+        });
+    }
+
+    function parseInto(root, html) {
+        const stack = [root];
+        const tag = /<!--[\s\S]*?-->|<!doctype[^>]*>|<\/([a-zA-Z][\w-]*)\s*>|<([a-zA-Z][\w-]*)((?:\s+[^\s"'>\/=]+(?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s"'>]+))?)*)\s*(\/?)>/gi;
+        const attribute = /([^\s"'>\/=]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+)))?/g;
+        let last = 0;
+        let match;
+        const text = value => {
+            if (value) stack[stack.length - 1].appendChild(new Text(decodeEntities(value)));
+        };
+        while ((match = tag.exec(html)) !== null) {
+            text(html.slice(last, match.index));
+            last = tag.lastIndex;
+            const [, closing, opening, attrs, selfClosing] = match;
+            if (closing) {
+                const name = closing.toUpperCase();
+                const index = stack.map(el => el.tagName).lastIndexOf(name);
+                if (index > 0) stack.length = index;
+            } else if (opening) {
+                const attributes = {};
+                let a;
+                attribute.lastIndex = 0;
+                while ((a = attribute.exec(attrs)) !== null) {
+                    const value = a[2] !== undefined ? a[2] : a[3] !== undefined ? a[3] : a[4] !== undefined ? a[4] : '';
+                    attributes[a[1].toLowerCase()] = decodeEntities(value);
+                }
+                const element = createElement(opening, attributes);
+                stack[stack.length - 1].appendChild(element);
+                if (!selfClosing && !VOID_ELEMENTS.includes(opening.toLowerCase())) stack.push(element);
+            }
+        }
+        text(html.slice(last));
+    }
+
+    function serialize(node) {
+        if (!(node instanceof Element)) {
+            return node.textContent.replace(/&/g, '&amp;').replace(/</g, '&lt;').replace(/>/g, '&gt;');
+        }
+        const tag = node.tagName.toLowerCase();
+        let attrs = '';
+        for (const [name, value] of node.attributes) attrs += ' ' + name + '="' + value.replace(/"/g, '&quot;') + '"';
+        if (VOID_ELEMENTS.includes(tag)) return '<' + tag + attrs + '>';
+        return '<' + tag + attrs + '>' + node.childNodes.map(serialize).join('') + '</' + tag + '>';
+    }
+
+    // ---- Selectors ------------------------------------------------------------
+
+    // A selector list is an array of complex selectors; a complex selector is
+    // an array of `{ combinator, compound }` from left to right.
+    function parseSelectorList(selector) {
+        const list = [];
+        let complex = [];
+        let combinator = null;
+        let i = 0;
+        const s = String(selector).trim();
+        while (i < s.length) {
+            const c = s[i];
+            if (c === ',') {
+                list.push(complex);
+                complex = [];
+                combinator = null;
+                i++;
+            } else if (c === '>') {
+                combinator = '>';
+                i++;
+            } else if (/\s/.test(c)) {
+                if (complex.length && combinator === null) combinator = ' ';
+                i++;
+            } else {
+                const [compound, end] = parseCompound(s, i);
+                complex.push({ combinator: complex.length ? combinator || ' ' : null, compound });
+                combinator = null;
+                i = end;
+            }
+        }
+        list.push(complex);
+        if (list.some(c => c.length === 0)) throw new SyntaxError('Invalid selector: ' + selector);
+        return list;
+    }
+
+    function parseCompound(s, i) {
+        const parts = [];
+        const name = /^(\*|[a-zA-Z][\w-]*)/.exec(s.slice(i));
+        if (name) {
+            if (name[1] !== '*') parts.push({ kind: 'tag', value: name[1].toUpperCase() });
+            i += name[1].length;
+        }
+        while (i < s.length) {
+            const rest = s.slice(i);
+            let m;
+            if ((m = /^\.([\w-]+)/.exec(rest))) {
+                parts.push({ kind: 'class', value: m[1] });
+            } else if ((m = /^#([\w-]+)/.exec(rest))) {
+                parts.push({ kind: 'id', value: m[1] });
+            } else if ((m = /^\[\s*([\w-]+)\s*(?:([~*^$|]?=)\s*(?:"([^"]*)"|'([^']*)'|([^\s\]]+))\s*(i)?\s*)?\]/.exec(rest))) {
+                const value = m[3] !== undefined ? m[3] : m[4] !== undefined ? m[4] : m[5];
+                parts.push({ kind: 'attr', name: m[1].toLowerCase(), op: m[2] || null, value, insensitive: !!m[6] });
+            } else if ((m = /^:not\(/.exec(rest))) {
+                const close = s.indexOf(')', i);
+                if (close < 0) throw new SyntaxError('Invalid selector: ' + s);
+                parts.push({ kind: 'not', list: parseSelectorList(s.slice(i + m[0].length, close)) });
+                i = close + 1;
+                continue;
+            } else if ((m = /^:(first-child|last-child|first-of-type|last-of-type)/.exec(rest))) {
+                parts.push({ kind: 'pseudo', value: m[1] });
+            } else {
+                break;
+            }
+            i += m[0].length;
+        }
+        if (!parts.length && !name) throw new SyntaxError('Invalid selector: ' + s);
+        return [parts, i];
+    }
+
+    function matchesAttribute(element, part) {
+        let actual = element.getAttribute(part.name);
+        if (actual === null) return false;
+        if (!part.op) return true;
+        let expected = part.value;
+        if (part.insensitive) {
+            actual = actual.toLowerCase();
+            expected = expected.toLowerCase();
+        }
+        switch (part.op) {
+            case '=': return actual === expected;
+            case '~=': return actual.split(/\s+/).includes(expected);
+            case '*=': return expected !== '' && actual.includes(expected);
+            case '^=': return expected !== '' && actual.startsWith(expected);
+            case '$=': return expected !== '' && actual.endsWith(expected);
+            case '|=': return actual === expected || actual.startsWith(expected + '-');
+        }
+        return false;
+    }
+
+    function matchesCompound(element, parts) {
+        return parts.every(part => {
+            switch (part.kind) {
+                case 'tag': return element.tagName === part.value;
+                case 'class': return element.classList.contains(part.value);
+                case 'id': return element.id === part.value;
+                case 'attr': return matchesAttribute(element, part);
+                case 'not': return !part.list.some(complex => matchesComplex(element, complex));
+                case 'pseudo': {
+                    const siblings = element.parentNode ? element.parentNode.children : [element];
+                    const sameType = siblings.filter(el => el.tagName === element.tagName);
+                    if (part.value === 'first-child') return siblings[0] === element;
+                    if (part.value === 'last-child') return siblings[siblings.length - 1] === element;
+                    if (part.value === 'first-of-type') return sameType[0] === element;
+                    return sameType[sameType.length - 1] === element;
+                }
+            }
+            return false;
+        });
+    }
+
+    function matchesComplex(element, complex, index = complex.length - 1) {
+        const { combinator, compound } = complex[index];
+        if (!matchesCompound(element, compound)) return false;
+        if (index === 0) return true;
+        if (combinator === '>') {
+            return element.parentElement !== null && matchesComplex(element.parentElement, complex, index - 1);
+        }
+        for (let ancestor = element.parentElement; ancestor; ancestor = ancestor.parentElement) {
+            if (matchesComplex(ancestor, complex, index - 1)) return true;
+        }
+        return false;
+    }
+
+    // ---- Document and window -------------------------------------------------
+
+    class Document extends Node {
+        constructor() {
+            super();
+            this.readyState = 'complete';
+            this.activeElement = null;
+            this.documentElement = createElement('html');
+            this.appendChild(this.documentElement);
+        }
+
+        get head() {
+            return this.documentElement.querySelector('head');
+        }
+
+        get body() {
+            return this.documentElement.querySelector('body');
+        }
+
+        get title() {
+            const title = this.documentElement.querySelector('title');
+            return title ? title.textContent : '';
+        }
+
+        set title(value) {
+            let title = this.documentElement.querySelector('title');
+            if (!title) title = this.head.appendChild(createElement('title'));
+            title.textContent = value;
+        }
+
+        get children() {
+            return [this.documentElement];
+        }
+
+        createElement(tagName) {
+            return createElement(tagName);
+        }
+
+        createTextNode(data) {
+            return new Text(String(data));
+        }
+
+        getElementById(id) {
+            return this.querySelector('#' + id);
+        }
+
+        querySelector(selector) {
+            return this.documentElement.matches(selector) ? this.documentElement : this.documentElement.querySelector(selector);
+        }
+
+        querySelectorAll(selector) {
+            const all = this.documentElement.querySelectorAll(selector);
+            return this.documentElement.matches(selector) ? [this.documentElement, ...all] : all;
+        }
+
+        // Editors built on contenteditable receive typed text this way
+        execCommand(command, showUi, value) {
+            const target = this.activeElement;
+            if (command !== 'insertText' || !target || target.contentEditable !== 'true') return false;
+            target.appendChild(new Text(String(value)));
+            return true;
+        }
+
+        createRange() {
+            return { selectNodeContents() {}, collapse() {} };
+        }
+    }
+
+    // Replace the document with `html`, served from `url`
+    global.__loadFixture = function (html, url) {
+        const document = new Document();
+        global.document = document;
+        global.location = new URL(url);
+        const match = /<html[^>]*>([\s\S]*)<\/html>/i.exec(html);
+        parseInto(document.documentElement, match ? match[1] : html);
+        for (const name of ['head', 'body']) {
+            if (!document.documentElement.querySelector(name)) document.documentElement.appendChild(createElement(name));
+        }
+    };
+
+    global.window = global;
+    global.listeners = [];
+    for (const name of ['addEventListener', 'removeEventListener', 'callListeners', 'dispatchEvent']) {
+        global[name] = EventTarget.prototype[name];
+    }
+    global.getSelection = () => ({ removeAllRanges() {}, addRange() {} });
+    Object.assign(global, {
+        Event, InputEvent, KeyboardEvent, MouseEvent, EventTarget, Node, Text, Element,
+        HTMLTextAreaElement, HTMLAnchorElement, HTMLButtonElement,
+    });
+
+    // ---- Tauri ------------------------------------------------------------------
+
+    global.__emitted = [];
+    global.__invoked = [];
+    global.__TAURI__ = {
+        event: {
+            emit(event, payload) {
+                global.__emitted.push({ event, payload: payload === undefined ? null : payload });
+                return Promise.resolve();
+            },
+        },
+        core: {
+            invoke(command, args) {
+                global.__invoked.push({ command, args: args === undefined ? null : args });
+                return Promise.resolve(null);
+            },
+        },
+    };
+})(globalThis);
@@ -1,5 +1,22 @@
 const { invoke } = window.__TAURI__.core;
 const { listen } = window.__TAURI__.event;
+function isCommandError(error) {
+    return typeof error === 'object' && error !== null && 'code' in error && 'message' in error;
+}
+/**
+ * Short explanation of a failed submit_message call, shown as the placeholder
+ */
+function describeSubmitError(error) {
+    switch (error.code) {
+        case 'window_not_found':
+        case 'window_operation_failed':
+            return 'Kimi window is unavailable — try again';
+        case 'store_error':
+            return 'Could not queue the message — try again';
+        default:
+            return 'Failed to send — try again';
+    }
+}
 /**
  * Launcher App Class - Encapsulates all launcher functionality
  * Provides a quick input interface for sending messages to Kimi
@@ -9,12 +26,16 @@ class LauncherApp {
     input;
     submitBtn;
     newChatToggle;
-    botModeToggle;
+    destinationSelect;
+    reasoningSelect;
+    webSearchSelect;
     // State
     focusTimeout = null;
     isSubmitting = false;
     newChatMode = true; // Default: start new conversations
-    botMode = false; // Default: normal chat mode
+    destination = 'chat'; // Picked destination id; the chat by default
+    destinations = [];
+    modLabel = '⌘';
     // Constants
     MAX_MESSAGE_LENGTH = 5000;
     constructor() {
@@ -22,7 +43,9 @@ class LauncherApp {
         this.input = document.getElementById('launcher-input');
         this.submitBtn = document.getElementById('submit-btn');
         this.newChatToggle = document.getElementById('new-chat-toggle');
-        this.botModeToggle = document.getElementById('bot-mode-toggle');
+        this.destinationSelect = document.getElementById('destination-select');
+        this.reasoningSelect = document.getElementById('reasoning-select');
+        this.webSearchSelect = document.getElementById('web-search-select');
         // Validate elements
         if (!this.input || !this.submitBtn) {
             console.error('Critical UI elements not found. Launcher cannot initialize.');
@@ -41,6 +64,7 @@ class LauncherApp {
         const isMac = navigator.platform?.toUpperCase().includes('MAC')
             || navigator.userAgent?.includes('Mac');
         const modLabel = isMac ? '⌘' : 'Ctrl';
+        this.modLabel = modLabel;
         // Update all .mod-key elements
         document.querySelectorAll('.mod-key').forEach(el => {
             el.textContent = modLabel;
@@ -49,10 +73,6 @@ class LauncherApp {
         if (this.newChatToggle) {
             this.newChatToggle.title = `Start a new conversation (${modLabel}+K)`;
         }
-        // Update the bot-mode toggle tooltip
-        if (this.botModeToggle) {
-            this.botModeToggle.title = `Open in Kimi Claw (${modLabel}+B)`;
-        }
     }
     /**
      * Initialize DOM event listeners
@@ -68,10 +88,14 @@ class LauncherApp {
         if (this.newChatToggle) {
             this.newChatToggle.addEventListener('click', () => this.toggleNewChat());
         }
-        // Bot mode toggle
-        if (this.botModeToggle) {
-            this.botModeToggle.addEventListener('click', () => this.toggleBotMode());
-        }
+        // Destination select, and a destination prefix typed before the message
+        this.destinationSelect?.addEventListener('change', () => {
+            this.pickDestination(this.destinationSelect?.value ?? 'chat');
+        });
+        this.input?.addEventListener('input', () => this.updateDestination());
+        // Chat mode selects, remembered by the backend when a message is sent
+        this.reasoningSelect?.addEventListener('change', () => this.updateChatModeSelects());
+        this.webSearchSelect?.addEventListener('change', () => this.updateChatModeSelects());
         // Window focus
         window.addEventListener('focus', () => this.handleWindowFocus());
         // Cleanup
@@ -81,32 +105,43 @@ class LauncherApp {
      * Initialize Tauri event listeners
      */
     initTauriListeners() {
-        // Load new-chat default from settings
+        // Load new-chat default and destinations from settings
         this.loadNewChatDefault();
-        // Listen for launcher-shown event from Rust to clear and focus input
-        listen('launcher-shown', () => {
+        this.loadDestinations();
+        // Listen for launcher-shown event from Rust to reset (or prefill) and focus input
+        listen('launcher-shown', (event) => {
+            const prefill = event.payload;
             if (this.input) {
-                this.input.value = '';
+                this.input.value = prefill?.message ?? '';
                 this.input.focus();
             }
-            // Re-load setting in case it was changed
-            this.loadNewChatDefault();
+            this.updateDestination();
+            if (typeof prefill?.new_chat === 'boolean') {
+                this.newChatMode = prefill.new_chat;
+                this.newChatToggle?.classList.toggle('active', this.newChatMode);
+                this.updatePlaceholder();
+            }
+            else {
+                // Re-load setting in case it was changed
+                this.loadNewChatDefault();
+            }
         }).catch((error) => {
             console.error('Failed to listen for launcher-shown event:', error);
         });
         // Listen for settings-changed event
         listen('settings-changed', (event) => {
             const settings = event.payload;
+            if (settings?.chat_mode) {
+                this.applyChatMode(settings.chat_mode);
+            }
+            // Destinations may have been added or removed
+            this.loadDestinations();
             if (settings && typeof settings.new_chat_default === 'boolean') {
                 this.newChatMode = settings.new_chat_default;
                 if (this.newChatToggle) {
                     this.newChatToggle.classList.toggle('active', this.newChatMode);
                 }
-                if (this.input) {
-                    this.input.placeholder = this.newChatMode
-                        ? 'Ask Kimi anything...'
-                        : 'Continue current chat...';
-                }
+                this.updatePlaceholder();
             }
         }).catch((error) => {
             console.error('Failed to listen for settings-changed event:', error);
@@ -116,7 +151,7 @@ class LauncherApp {
             const { success, error } = event.payload ?? {};
             if (!success && error) {
                 console.error('[Kimi] Message injection failed:', error);
-                this.showError(error);
+                this.showError('Failed to send — try again');
             }
         }).catch((error) => {
             console.error('Failed to listen for inject-result event:', error);
@@ -124,8 +159,10 @@ class LauncherApp {
     }
     /**
      * Show error state in the launcher
+     * @param text - Shown in place of the placeholder
+     * @param restore - Put back into the input once the error state ends
      */
-    async showError(_errorMessage) {
+    async showError(text, restore = '') {
         // Briefly re-show the launcher with an error state
         try {
             await invoke('show_launcher');
@@ -141,7 +178,7 @@ class LauncherApp {
         // Show error in the input placeholder
         if (this.input) {
             this.input.value = '';
-            this.input.placeholder = 'Failed to send — try again';
+            this.input.placeholder = text;
             this.input.focus();
         }
         // Add error class for visual feedback
@@ -150,14 +187,20 @@ class LauncherApp {
         setTimeout(() => {
             container.classList.remove('launcher-error');
             this.updatePlaceholder();
+            if (restore && this.input && !this.input.value) {
+                this.input.value = restore;
+            }
         }, 2500);
     }
     /**
-     * Load new chat default setting from backend
+     * Load new chat default and the last chat mode from backend
      */
     async loadNewChatDefault() {
         try {
             const settings = await invoke('get_settings');
+            if (settings.chat_mode) {
+                this.applyChatMode(settings.chat_mode);
+            }
             this.newChatMode = settings.new_chat_default ?? true;
             if (this.newChatToggle) {
                 this.newChatToggle.classList.toggle('active', this.newChatMode);
@@ -169,6 +212,72 @@ class LauncherApp {
             console.warn('Failed to load settings, using defaults:', error);
         }
     }
+    /**
+     * Load the destinations the launcher can send to
+     */
+    async loadDestinations() {
+        try {
+            this.destinations = await invoke('get_destinations');
+        }
+        catch (error) {
+            console.warn('Failed to load destinations:', error);
+            return;
+        }
+        if (!this.destinationSelect)
+            return;
+        this.destinationSelect.replaceChildren(...this.destinations.map(destination => {
+            const option = document.createElement('option');
+            option.value = destination.id;
+            option.textContent = destination.shortcut
+                ? `${destination.name} (${this.modLabel}+${destination.shortcut.toUpperCase()})`
+                : destination.name;
+            return option;
+        }));
+        if (!this.destinations.some(d => d.id === this.destination)) {
+            this.destination = 'chat';
+        }
+        this.updateDestination();
+    }
+    /**
+     * Destination named by a prefix typed before the message, e.g. `/claw `
+     */
+    prefixedDestination() {
+        const text = this.input?.value.trimStart() ?? '';
+        return this.destinations.find(destination => {
+            const prefix = destination.prefix;
+            return !!prefix && text.startsWith(prefix) && /^\s/.test(text.slice(prefix.length));
+        }) ?? null;
+    }
+    /**
+     * Destination the message goes to: a typed prefix wins over the picked one
+     */
+    currentDestination() {
+        return this.prefixedDestination()
+            ?? this.destinations.find(d => d.id === this.destination)
+            ?? null;
+    }
+    /**
+     * Pick a destination; any other than the chat opens a new conversation
+     */
+    pickDestination(id) {
+        this.destination = id;
+        if (id !== 'chat' && !this.newChatMode) {
+            this.newChatMode = true;
+            this.newChatToggle?.classList.add('active');
+        }
+        this.updateDestination();
+    }
+    /**
+     * Show the current destination in the select and placeholder
+     */
+    updateDestination() {
+        const id = this.currentDestination()?.id ?? this.destination;
+        if (this.destinationSelect) {
+            this.destinationSelect.value = id;
+            this.destinationSelect.classList.toggle('active', id !== 'chat');
+        }
+        this.updatePlaceholder();
+    }
     /**
      * Focus the input element
      */
@@ -195,11 +304,15 @@ class LauncherApp {
                 this.toggleNewChat();
                 return;
             }
-            // Cmd/Ctrl+B to toggle bot mode
-            if (e.key === 'b' && (e.metaKey || e.ctrlKey)) {
-                e.preventDefault();
-                this.toggleBotMode();
-                return;
+            // Cmd/Ctrl+<shortcut> to pick a destination, again to go back to the chat
+            if (e.metaKey || e.ctrlKey) {
+                const key = e.key.toUpperCase();
+                const destination = this.destinations.find(d => d.shortcut?.toUpperCase() === key);
+                if (destination) {
+                    e.preventDefault();
+                    this.pickDestination(this.destination === destination.id ? 'chat' : destination.id);
+                    return;
+                }
             }
             // Enter to submit
             if (e.key === 'Enter' && !e.shiftKey && this.input) {
@@ -250,20 +363,32 @@ class LauncherApp {
         this.updatePlaceholder();
     }
     /**
-     * Toggle bot mode
+     * Show a chat mode in the selects
      */
-    toggleBotMode() {
-        this.botMode = !this.botMode;
-        if (this.botModeToggle) {
-            this.botModeToggle.classList.toggle('active', this.botMode);
+    applyChatMode(mode) {
+        if (this.reasoningSelect) {
+            this.reasoningSelect.value = mode.reasoning;
         }
-        // When bot mode is enabled, also enable new chat mode
-        if (this.botMode && !this.newChatMode) {
-            this.newChatMode = true;
-            this.newChatToggle?.classList.add('active');
+        if (this.webSearchSelect) {
+            this.webSearchSelect.value = mode.web_search;
         }
-        // Update placeholder to reflect mode
-        this.updatePlaceholder();
+        this.updateChatModeSelects();
+    }
+    /**
+     * Chat mode currently picked in the selects
+     */
+    readChatMode() {
+        return {
+            reasoning: (this.reasoningSelect?.value) ?? 'unchanged',
+            web_search: (this.webSearchSelect?.value) ?? 'unchanged',
+        };
+    }
+    /**
+     * Highlight the selects that change Kimi's mode
+     */
+    updateChatModeSelects() {
+        this.reasoningSelect?.classList.toggle('active', this.reasoningSelect.value !== 'unchanged');
+        this.webSearchSelect?.classList.toggle('active', this.webSearchSelect.value !== 'unchanged');
     }
     /**
      * Update input placeholder based on current mode
@@ -271,8 +396,9 @@ class LauncherApp {
     updatePlaceholder() {
         if (!this.input)
             return;
-        if (this.botMode) {
-            this.input.placeholder = 'Ask Kimi Claw...';
+        const destination = this.currentDestination();
+        if (destination && destination.id !== 'chat') {
+            this.input.placeholder = `Ask ${destination.name}...`;
         }
         else if (this.newChatMode) {
             this.input.placeholder = 'Ask Kimi anything...';
@@ -321,7 +447,8 @@ class LauncherApp {
             const args = {
                 message,
                 newChat: this.newChatMode,
-                botMode: this.botMode
+                destination: this.destination,
+                chatMode: this.readChatMode()
             };
             const submitPromise = invoke('submit_message', args);
             // Add timeout to prevent hanging
@@ -331,6 +458,11 @@ class LauncherApp {
             await Promise.race([submitPromise, timeoutPromise]);
         }
         catch (error) {
+            if (isCommandError(error)) {
+                console.error(`Failed to submit message (${error.code}):`, error.message);
+                this.showError(describeSubmitError(error), originalMessage);
+                return;
+            }
             console.error('Failed to submit message:', error);
             // Restore message on error
             if (this.input && originalMessage) {
@@ -1,9 +1,38 @@
 // Kimi Launcher TypeScript
-import type { AppSettings, InjectResult, SettingsChangedEvent } from './types';
+import type {
+  AppSettings,
+  ChatMode,
+  CommandError,
+  Destination,
+  InjectResult,
+  LauncherShownEvent,
+  Reasoning,
+  SettingsChangedEvent,
+  WebSearch,
+} from './types';
 
 const { invoke } = window.__TAURI__.core;
 const { listen } = window.__TAURI__.event;
 
+function isCommandError(error: unknown): error is CommandError {
+  return typeof error === 'object' && error !== null && 'code' in error && 'message' in error;
+}
+
+/**
+ * Short explanation of a failed submit_message call, shown as the placeholder
+ */
+function describeSubmitError(error: CommandError): string {
+  switch (error.code) {
+    case 'window_not_found':
+    case 'window_operation_failed':
+      return 'Kimi window is unavailable — try again';
+    case 'store_error':
+      return 'Could not queue the message — try again';
+    default:
+      return 'Failed to send — try again';
+  }
+}
+
 /**
  * Launcher App Class - Encapsulates all launcher functionality
  * Provides a quick input interface for sending messages to Kimi
@@ -13,13 +42,17 @@ class LauncherApp {
   private readonly input: HTMLInputElement | null;
   private readonly submitBtn: HTMLButtonElement | null;
   private readonly newChatToggle: HTMLElement | null;
-  private readonly botModeToggle: HTMLElement | null;
+  private readonly destinationSelect: HTMLSelectElement | null;
+  private readonly reasoningSelect: HTMLSelectElement | null;
+  private readonly webSearchSelect: HTMLSelectElement | null;
   
   // State
   private focusTimeout: number | null = null;
   private isSubmitting = false;
   private newChatMode = true; // Default: start new conversations
-  private botMode = false; // Default: normal chat mode
+  private destination = 'chat'; // Picked destination id; the chat by default
+  private destinations: Destination[] = [];
+  private modLabel = '⌘';
   
   // Constants
   private readonly MAX_MESSAGE_LENGTH = 5000;
@@ -29,7 +62,9 @@ class LauncherApp {
     this.input = document.getElementById('launcher-input') as HTMLInputElement | null;
     this.submitBtn = document.getElementById('submit-btn') as HTMLButtonElement | null;
     this.newChatToggle = document.getElementById('new-chat-toggle');
-    this.botModeToggle = document.getElementById('bot-mode-toggle');
+    this.destinationSelect = document.getElementById('destination-select') as HTMLSelectElement | null;
+    this.reasoningSelect = document.getElementById('reasoning-select') as HTMLSelectElement | null;
+    this.webSearchSelect = document.getElementById('web-search-select') as HTMLSelectElement | null;
     
     // Validate elements
     if (!this.input || !this.submitBtn) {
@@ -52,6 +87,7 @@ class LauncherApp {
     const isMac = navigator.platform?.toUpperCase().includes('MAC')
       || navigator.userAgent?.includes('Mac');
     const modLabel = isMac ? '⌘' : 'Ctrl';
+    this.modLabel = modLabel;
     
     // Update all .mod-key elements
     document.querySelectorAll('.mod-key').forEach(el => {
@@ -62,11 +98,6 @@ class LauncherApp {
     if (this.newChatToggle) {
       this.newChatToggle.title = `Start a new conversation (${modLabel}+K)`;
     }
-    
-    // Update the bot-mode toggle tooltip
-    if (this.botModeToggle) {
-      this.botModeToggle.title = `Open in Kimi Claw (${modLabel}+B)`;
-    }
   }
   
   /**
@@ -87,10 +118,15 @@ class LauncherApp {
       this.newChatToggle.addEventListener('click', () => this.toggleNewChat());
     }
     
-    // Bot mode toggle
-    if (this.botModeToggle) {
-      this.botModeToggle.addEventListener('click', () => this.toggleBotMode());
-    }
+    // Destination select, and a destination prefix typed before the message
+    this.destinationSelect?.addEventListener('change', () => {
+      this.pickDestination(this.destinationSelect?.value ?? 'chat');
+    });
+    this.input?.addEventListener('input', () => this.updateDestination());
+    
+    // Chat mode selects, remembered by the backend when a message is sent
+    this.reasoningSelect?.addEventListener('change', () => this.updateChatModeSelects());
+    this.webSearchSelect?.addEventListener('change', () => this.updateChatModeSelects());
     
     // Window focus
     window.addEventListener('focus', () => this.handleWindowFocus());
@@ -103,17 +139,26 @@ class LauncherApp {
    * Initialize Tauri event listeners
    */
   private initTauriListeners(): void {
-    // Load new-chat default from settings
+    // Load new-chat default and destinations from settings
     this.loadNewChatDefault();
+    this.loadDestinations();
     
-    // Listen for launcher-shown event from Rust to clear and focus input
-    listen('launcher-shown', () => {
+    // Listen for launcher-shown event from Rust to reset (or prefill) and focus input
+    listen<LauncherShownEvent>('launcher-shown', (event) => {
+      const prefill = event.payload;
       if (this.input) {
-        this.input.value = '';
+        this.input.value = prefill?.message ?? '';
         this.input.focus();
       }
-      // Re-load setting in case it was changed
-      this.loadNewChatDefault();
+      this.updateDestination();
+      if (typeof prefill?.new_chat === 'boolean') {
+        this.newChatMode = prefill.new_chat;
+        this.newChatToggle?.classList.toggle('active', this.newChatMode);
+        this.updatePlaceholder();
+      } else {
+        // Re-load setting in case it was changed
+        this.loadNewChatDefault();
+      }
     }).catch((error: Error) => {
       console.error('Failed to listen for launcher-shown event:', error);
     });
@@ -121,16 +166,17 @@ class LauncherApp {
     // Listen for settings-changed event
     listen<SettingsChangedEvent>('settings-changed', (event) => {
       const settings = event.payload;
+      if (settings?.chat_mode) {
+        this.applyChatMode(settings.chat_mode);
+      }
+      // Destinations may have been added or removed
+      this.loadDestinations();
       if (settings && typeof settings.new_chat_default === 'boolean') {
         this.newChatMode = settings.new_chat_default;
         if (this.newChatToggle) {
           this.newChatToggle.classList.toggle('active', this.newChatMode);
         }
-        if (this.input) {
-          this.input.placeholder = this.newChatMode
-            ? 'Ask Kimi anything...'
-            : 'Continue current chat...';
-        }
+        this.updatePlaceholder();
       }
     }).catch((error: Error) => {
       console.error('Failed to listen for settings-changed event:', error);
@@ -141,7 +187,7 @@ class LauncherApp {
       const { success, error } = event.payload ?? {};
       if (!success && error) {
         console.error('[Kimi] Message injection failed:', error);
-        this.showError(error);
+        this.showError('Failed to send — try again');
       }
     }).catch((error: Error) => {
       console.error('Failed to listen for inject-result event:', error);
@@ -150,8 +196,10 @@ class LauncherApp {
   
   /**
    * Show error state in the launcher
+   * @param text - Shown in place of the placeholder
+   * @param restore - Put back into the input once the error state ends
    */
-  private async showError(_errorMessage: string): Promise<void> {
+  private async showError(text: string, restore = ''): Promise<void> {
     // Briefly re-show the launcher with an error state
     try {
       await invoke('show_launcher');
@@ -167,7 +215,7 @@ class LauncherApp {
     // Show error in the input placeholder
     if (this.input) {
       this.input.value = '';
-      this.input.placeholder = 'Failed to send — try again';
+      this.input.placeholder = text;
       this.input.focus();
     }
     
@@ -178,15 +226,21 @@ class LauncherApp {
     setTimeout(() => {
       container.classList.remove('launcher-error');
       this.updatePlaceholder();
+      if (restore && this.input && !this.input.value) {
+        this.input.value = restore;
+      }
     }, 2500);
   }
 
   /**
-   * Load new chat default setting from backend
+   * Load new chat default and the last chat mode from backend
    */
   private async loadNewChatDefault(): Promise<void> {
     try {
       const settings = await invoke<AppSettings>('get_settings');
+      if (settings.chat_mode) {
+        this.applyChatMode(settings.chat_mode);
+      }
       this.newChatMode = settings.new_chat_default ?? true;
       if (this.newChatToggle) {
         this.newChatToggle.classList.toggle('active', this.newChatMode);
@@ -198,6 +252,76 @@ class LauncherApp {
     }
   }
   
+  /**
+   * Load the destinations the launcher can send to
+   */
+  private async loadDestinations(): Promise<void> {
+    try {
+      this.destinations = await invoke<Destination[]>('get_destinations');
+    } catch (error) {
+      console.warn('Failed to load destinations:', error);
+      return;
+    }
+    if (!this.destinationSelect) return;
+    
+    this.destinationSelect.replaceChildren(...this.destinations.map(destination => {
+      const option = document.createElement('option');
+      option.value = destination.id;
+      option.textContent = destination.shortcut
+        ? `${destination.name} (${this.modLabel}+${destination.shortcut.toUpperCase()})`
+        : destination.name;
+      return option;
+    }));
+    if (!this.destinations.some(d => d.id === this.destination)) {
+      this.destination = 'chat';
+    }
+    this.updateDestination();
+  }
+  
+  /**
+   * Destination named by a prefix typed before the message, e.g. `/claw `
+   */
+  private prefixedDestination(): Destination | null {
+    const text = this.input?.value.trimStart() ?? '';
+    return this.destinations.find(destination => {
+      const prefix = destination.prefix;
+      return !!prefix && text.startsWith(prefix) && /^\s/.test(text.slice(prefix.length));
+    }) ?? null;
+  }
+  
+  /**
+   * Destination the message goes to: a typed prefix wins over the picked one
+   */
+  private currentDestination(): Destination | null {
+    return this.prefixedDestination()
+      ?? this.destinations.find(d => d.id === this.destination)
+      ?? null;
+  }
+  
+  /**
+   * Pick a destination; any other than the chat opens a new conversation
+   */
+  private pickDestination(id: string): void {
+    this.destination = id;
+    if (id !== 'chat' && !this.newChatMode) {
+      this.newChatMode = true;
+      this.newChatToggle?.classList.add('active');
+    }
+    this.updateDestination();
+  }
+  
+  /**
+   * Show the current destination in the select and placeholder
+   */
+  private updateDestination(): void {
+    const id = this.currentDestination()?.id ?? this.destination;
+    if (this.destinationSelect) {
+      this.destinationSelect.value = id;
+      this.destinationSelect.classList.toggle('active', id !== 'chat');
+    }
+    this.updatePlaceholder();
+  }
+  
   /**
    * Focus the input element
    */
@@ -227,11 +351,15 @@ class LauncherApp {
         return;
       }
       
-      // Cmd/Ctrl+B to toggle bot mode
-      if (e.key === 'b' && (e.metaKey || e.ctrlKey)) {
-        e.preventDefault();
-        this.toggleBotMode();
-        return;
+      // Cmd/Ctrl+<shortcut> to pick a destination, again to go back to the chat
+      if (e.metaKey || e.ctrlKey) {
+        const key = e.key.toUpperCase();
+        const destination = this.destinations.find(d => d.shortcut?.toUpperCase() === key);
+        if (destination) {
+          e.preventDefault();
+          this.pickDestination(this.destination === destination.id ? 'chat' : destination.id);
+          return;
+        }
       }
       
       // Enter to submit
@@ -287,20 +415,34 @@ class LauncherApp {
   }
   
   /**
-   * Toggle bot mode
+   * Show a chat mode in the selects
    */
-  private toggleBotMode(): void {
-    this.botMode = !this.botMode;
-    if (this.botModeToggle) {
-      this.botModeToggle.classList.toggle('active', this.botMode);
+  private applyChatMode(mode: ChatMode): void {
+    if (this.reasoningSelect) {
+      this.reasoningSelect.value = mode.reasoning;
     }
-    // When bot mode is enabled, also enable new chat mode
-    if (this.botMode && !this.newChatMode) {
-      this.newChatMode = true;
-      this.newChatToggle?.classList.add('active');
+    if (this.webSearchSelect) {
+      this.webSearchSelect.value = mode.web_search;
     }
-    // Update placeholder to reflect mode
-    this.updatePlaceholder();
+    this.updateChatModeSelects();
+  }
+  
+  /**
+   * Chat mode currently picked in the selects
+   */
+  private readChatMode(): ChatMode {
+    return {
+      reasoning: (this.reasoningSelect?.value as Reasoning | undefined) ?? 'unchanged',
+      web_search: (this.webSearchSelect?.value as WebSearch | undefined) ?? 'unchanged',
+    };
+  }
+  
+  /**
+   * Highlight the selects that change Kimi's mode
+   */
+  private updateChatModeSelects(): void {
+    this.reasoningSelect?.classList.toggle('active', this.reasoningSelect.value !== 'unchanged');
+    this.webSearchSelect?.classList.toggle('active', this.webSearchSelect.value !== 'unchanged');
   }
   
   /**
@@ -309,8 +451,9 @@ class LauncherApp {
   private updatePlaceholder(): void {
     if (!this.input) return;
     
-    if (this.botMode) {
-      this.input.placeholder = 'Ask Kimi Claw...';
+    const destination = this.currentDestination();
+    if (destination && destination.id !== 'chat') {
+      this.input.placeholder = `Ask ${destination.name}...`;
     } else if (this.newChatMode) {
       this.input.placeholder = 'Ask Kimi anything...';
     } else {
@@ -368,7 +511,8 @@ class LauncherApp {
       const args = {
         message,
         newChat: this.newChatMode,
-        botMode: this.botMode
+        destination: this.destination,
+        chatMode: this.readChatMode()
       };
       const submitPromise = invoke('submit_message', args as Record<string, unknown>);
       
@@ -380,6 +524,11 @@ class LauncherApp {
       await Promise.race([submitPromise, timeoutPromise]);
       
     } catch (error) {
+      if (isCommandError(error)) {
+        console.error(`Failed to submit message (${error.code}):`, error.message);
+        this.showError(describeSubmitError(error), originalMessage);
+        return;
+      }
       console.error('Failed to submit message:', error);
       
       // Restore message on error
@@ -1,16 +1,90 @@
+const LINK_ACTIONS = ['in_app', 'browser', 'app_window', 'block', 'ask'];
+const DAYS = ['mon', 'tue', 'wed', 'thu', 'fri', 'sat', 'sun'];
+const TIME_PATTERN = /^([01]?\d|2[0-3]):[0-5]\d$/;
 const { invoke } = window.__TAURI__.core;
+const { listen } = window.__TAURI__.event;
+/**
+ * Message of a command error, or the error itself for anything else
+ */
+function errorMessage(error) {
+    return (error)?.message ?? String(error);
+}
 /**
  * Settings App Class - Manages application settings UI
  * Handles loading and saving settings via Tauri commands
  */
 class SettingsApp {
     newChatDefault;
+    openAtLogin;
+    startHidden;
+    startupStatus;
+    closeBehavior;
     notificationsEnabled;
+    responseWindow;
+    notifyWhen;
+    quietHours;
+    quietHoursStatus;
+    proxyMode;
+    proxyUrl;
+    proxyBypass;
+    proxyTestBtn;
+    proxyStatus;
+    kimiOrigin;
+    linkDefaultAction;
+    linkRules;
+    stripTrackingParams;
+    linkStatus;
+    downloadDirectory;
+    downloadChooseBtn;
+    downloadStatus;
+    destinations;
+    destinationsStatus;
+    statsSummary;
+    statsSelectors;
+    statsWarning;
+    statsStatus;
+    statsResetBtn;
+    // Advanced timing inputs, each naming its TimingSettings field in data-timing
+    timingInputs;
+    timingStatus;
+    // Last settings loaded from the backend, so fields without a control here are preserved
+    current = {};
     constructor() {
         this.newChatDefault = document.getElementById('new-chat-default');
+        this.openAtLogin = document.getElementById('open-at-login');
+        this.startHidden = document.getElementById('start-hidden');
+        this.startupStatus = document.getElementById('startup-status');
+        this.closeBehavior = document.getElementById('close-behavior');
         this.notificationsEnabled = document.getElementById('notifications-enabled');
+        this.responseWindow = document.getElementById('response-window');
+        this.notifyWhen = document.getElementById('notify-when');
+        this.quietHours = document.getElementById('quiet-hours');
+        this.quietHoursStatus = document.getElementById('quiet-hours-status');
+        this.proxyMode = document.getElementById('proxy-mode');
+        this.proxyUrl = document.getElementById('proxy-url');
+        this.proxyBypass = document.getElementById('proxy-bypass');
+        this.proxyTestBtn = document.getElementById('proxy-test-btn');
+        this.proxyStatus = document.getElementById('proxy-status');
+        this.kimiOrigin = document.getElementById('kimi-origin');
+        this.linkDefaultAction = document.getElementById('link-default-action');
+        this.linkRules = document.getElementById('link-rules');
+        this.stripTrackingParams = document.getElementById('strip-tracking-params');
+        this.linkStatus = document.getElementById('link-status');
+        this.downloadDirectory = document.getElementById('download-directory');
+        this.downloadChooseBtn = document.getElementById('download-choose-btn');
+        this.downloadStatus = document.getElementById('download-status');
+        this.destinations = document.getElementById('destinations');
+        this.destinationsStatus = document.getElementById('destinations-status');
+        this.statsSummary = document.getElementById('stats-summary');
+        this.statsSelectors = document.getElementById('stats-selectors');
+        this.statsWarning = document.getElementById('stats-warning');
+        this.statsStatus = document.getElementById('stats-status');
+        this.statsResetBtn = document.getElementById('stats-reset-btn');
+        this.timingInputs = Array.from(document.querySelectorAll('input[data-timing]'));
+        this.timingStatus = document.getElementById('timing-status');
         this.initEventListeners();
         this.loadSettings();
+        this.loadInjectionStats();
     }
     /**
      * Initialize event listeners for settings changes
@@ -22,6 +96,319 @@ class SettingsApp {
         if (this.notificationsEnabled) {
             this.notificationsEnabled.addEventListener('change', () => this.saveSettings());
         }
+        this.openAtLogin?.addEventListener('change', () => this.saveSettings());
+        this.startHidden?.addEventListener('change', () => this.saveSettings());
+        this.closeBehavior?.addEventListener('change', () => this.saveSettings());
+        this.responseWindow?.addEventListener('change', () => this.saveSettings());
+        this.notifyWhen?.addEventListener('change', () => this.saveSettings());
+        this.quietHours?.addEventListener('change', () => this.saveSettings());
+        if (this.proxyMode) {
+            this.proxyMode.addEventListener('change', () => {
+                this.updateProxyFields();
+                this.saveSettings();
+            });
+        }
+        this.proxyUrl?.addEventListener('change', () => this.saveSettings());
+        this.proxyBypass?.addEventListener('change', () => this.saveSettings());
+        this.proxyTestBtn?.addEventListener('click', () => this.testProxyConnection());
+        this.kimiOrigin?.addEventListener('change', () => this.saveSettings());
+        this.linkDefaultAction?.addEventListener('change', () => this.saveSettings());
+        this.linkRules?.addEventListener('change', () => this.saveSettings());
+        this.stripTrackingParams?.addEventListener('change', () => this.saveSettings());
+        this.downloadDirectory?.addEventListener('change', () => this.saveSettings());
+        this.downloadChooseBtn?.addEventListener('click', () => this.chooseDownloadDirectory());
+        this.destinations?.addEventListener('change', () => this.saveSettings());
+        for (const input of this.timingInputs) {
+            input.addEventListener('change', () => this.saveSettings());
+        }
+        this.statsResetBtn?.addEventListener('click', () => {
+            invoke('reset_injection_stats').catch(error => console.error('Failed to reset stats:', error));
+        });
+        listen('injection-stats-changed', (event) => {
+            this.renderInjectionStats(event.payload);
+        }).catch(error => {
+            console.error('Failed to listen for injection-stats-changed event:', error);
+        });
+    }
+    /**
+     * Parse the quiet hours textarea, one "HH:MM-HH:MM [days]" period per line
+     * @returns The notification policy, or null if a line is malformed
+     */
+    readNotificationPolicy() {
+        const periods = [];
+        const lines = (this.quietHours?.value ?? '').split('\n');
+        for (const [index, line] of lines.entries()) {
+            const trimmed = line.trim();
+            if (!trimmed || trimmed.startsWith('#'))
+                continue;
+            const [range, dayList, ...rest] = trimmed.split(/\s+/);
+            const [start, end, ...extra] = range.split('-');
+            const days = dayList ? dayList.toLowerCase().split(',') : [];
+            const valid = rest.length === 0
+                && extra.length === 0
+                && TIME_PATTERN.test(start ?? '')
+                && TIME_PATTERN.test(end ?? '')
+                && days.every(day => DAYS.includes(day));
+            if (!valid) {
+                this.setQuietHoursStatus(`Line ${index + 1}: expected "HH:MM-HH:MM" optionally followed by days like mon,tue`, true);
+                return null;
+            }
+            periods.push({ start, end, days: days });
+        }
+        this.setQuietHoursStatus('');
+        return {
+            snoozed_until: this.current.notification_policy?.snoozed_until ?? null,
+            notify_when: (this.notifyWhen?.value) ?? 'unfocused',
+            quiet_hours: periods,
+        };
+    }
+    /**
+     * Show a status line below the quiet hours
+     */
+    setQuietHoursStatus(text, isError = false) {
+        if (!this.quietHoursStatus)
+            return;
+        this.quietHoursStatus.textContent = text;
+        this.quietHoursStatus.classList.toggle('error', isError);
+    }
+    /**
+     * Parse the link rules textarea, one "pattern action" pair per line
+     * @returns The routing settings, or null if a line is malformed
+     */
+    readLinkRouting() {
+        const rules = [];
+        const lines = (this.linkRules?.value ?? '').split('\n');
+        for (const [index, line] of lines.entries()) {
+            const trimmed = line.trim();
+            if (!trimmed || trimmed.startsWith('#'))
+                continue;
+            const [pattern, action, ...rest] = trimmed.split(/\s+/);
+            if (!action || rest.length > 0 || !LINK_ACTIONS.includes(action)) {
+                this.setLinkStatus(`Line ${index + 1}: expected "pattern action" with action one of ${LINK_ACTIONS.join(', ')}`, true);
+                return null;
+            }
+            rules.push({ pattern, action: action });
+        }
+        this.setLinkStatus('');
+        return {
+            rules,
+            default_action: (this.linkDefaultAction?.value) ?? 'browser',
+            strip_tracking_params: this.stripTrackingParams?.checked ?? false,
+        };
+    }
+    /**
+     * Show a status line below the link rules
+     */
+    setLinkStatus(text, isError = false) {
+        if (!this.linkStatus)
+            return;
+        this.linkStatus.textContent = text;
+        this.linkStatus.classList.toggle('error', isError);
+    }
+    /**
+     * Parse the destinations textarea, a JSON list; empty means none
+     * @returns The destinations, or null if the text is not a JSON list
+     */
+    readDestinations() {
+        const text = this.destinations?.value.trim() ?? '';
+        if (!text) {
+            this.setDestinationsStatus('');
+            return [];
+        }
+        let parsed;
+        try {
+            parsed = JSON.parse(text);
+        }
+        catch (error) {
+            this.setDestinationsStatus(`Not valid JSON: ${(error).message}`, true);
+            return null;
+        }
+        if (!Array.isArray(parsed)) {
+            this.setDestinationsStatus('Expected a JSON list of destinations', true);
+            return null;
+        }
+        this.setDestinationsStatus('');
+        return parsed;
+    }
+    /**
+     * Show a status line below the destinations
+     */
+    setDestinationsStatus(text, isError = false) {
+        if (!this.destinationsStatus)
+            return;
+        this.destinationsStatus.textContent = text;
+        this.destinationsStatus.classList.toggle('error', isError);
+    }
+    /**
+     * Read the proxy form into a ProxySettings object
+     */
+    readProxySettings() {
+        return {
+            mode: (this.proxyMode?.value) ?? 'system',
+            url: this.proxyUrl?.value.trim() ?? '',
+            bypass: (this.proxyBypass?.value ?? '')
+                .split(',')
+                .map(entry => entry.trim())
+                .filter(entry => entry.length > 0),
+        };
+    }
+    /**
+     * Only enable the manual proxy fields in manual mode
+     */
+    updateProxyFields() {
+        const manual = this.proxyMode?.value === 'manual';
+        if (this.proxyUrl) {
+            this.proxyUrl.disabled = !manual;
+        }
+        if (this.proxyBypass) {
+            this.proxyBypass.disabled = !manual;
+        }
+    }
+    /**
+     * Show a status line below the proxy settings
+     */
+    setProxyStatus(text, isError = false) {
+        if (!this.proxyStatus)
+            return;
+        this.proxyStatus.textContent = text;
+        this.proxyStatus.classList.toggle('error', isError);
+    }
+    /**
+     * Check that Kimi is reachable with the proxy settings currently in the form
+     */
+    async testProxyConnection() {
+        if (this.proxyTestBtn) {
+            this.proxyTestBtn.disabled = true;
+        }
+        this.setProxyStatus('Testing connection...');
+        try {
+            const report = await invoke('test_proxy_connection', {
+                proxy: this.readProxySettings(),
+            });
+            const route = report.proxy ? `via ${report.proxy}` : 'directly';
+            this.setProxyStatus(`Reached Kimi ${route} in ${report.elapsed_ms} ms`);
+        }
+        catch (error) {
+            this.setProxyStatus(errorMessage(error), true);
+        }
+        finally {
+            if (this.proxyTestBtn) {
+                this.proxyTestBtn.disabled = false;
+            }
+        }
+    }
+    /**
+     * Let the user pick the download folder with the native folder dialog
+     */
+    async chooseDownloadDirectory() {
+        try {
+            const directory = await invoke('choose_download_directory');
+            if (directory && this.downloadDirectory) {
+                this.downloadDirectory.value = directory;
+                await this.saveSettings();
+            }
+        }
+        catch (error) {
+            this.setDownloadStatus(errorMessage(error), true);
+        }
+    }
+    /**
+     * Show a status line below the open at login option
+     */
+    setStartupStatus(text, isError = false) {
+        if (!this.startupStatus)
+            return;
+        this.startupStatus.textContent = text;
+        this.startupStatus.classList.toggle('error', isError);
+    }
+    /**
+     * Show a status line below the download folder
+     */
+    setDownloadStatus(text, isError = false) {
+        if (!this.downloadStatus)
+            return;
+        this.downloadStatus.textContent = text;
+        this.downloadStatus.classList.toggle('error', isError);
+    }
+    /**
+     * Read the advanced timing inputs over the loaded timings
+     * @returns The timings, or null if an input is not a whole number
+     */
+    readTiming() {
+        const timing = { ...this.current.timing };
+        for (const input of this.timingInputs) {
+            const field = input.dataset.timing;
+            const value = Number(input.value);
+            if (input.value.trim() === '' || !Number.isInteger(value) || value < 0) {
+                this.setTimingStatus(`${input.labels?.[0]?.textContent ?? field} must be a whole number`, true, field);
+                return null;
+            }
+            timing[field] = value;
+        }
+        this.setTimingStatus('');
+        return timing;
+    }
+    /**
+     * Show a status line below the advanced timings, marking the input it is about
+     */
+    setTimingStatus(text, isError = false, field) {
+        for (const input of this.timingInputs) {
+            input.classList.toggle('invalid', input.dataset.timing === field);
+        }
+        if (!this.timingStatus)
+            return;
+        this.timingStatus.textContent = text;
+        this.timingStatus.classList.toggle('error', isError);
+    }
+    /**
+     * Load the injection stats from the backend
+     */
+    async loadInjectionStats() {
+        try {
+            this.renderInjectionStats(await invoke('get_injection_stats'));
+        }
+        catch (error) {
+            console.error('Failed to load injection stats:', error);
+        }
+    }
+    /**
+     * Show the injection stats in the diagnostics section
+     */
+    renderInjectionStats(stats) {
+        const counts = (selectors) =>
+            Object.entries(selectors)
+                .sort(([, a], [, b]) => b - a)
+                .map(([name, count]) => `${name} ${count}`)
+                .join(', ');
+        if (this.statsSummary) {
+            if (stats.attempts === 0) {
+                this.statsSummary.textContent = 'No messages sent yet';
+            }
+            else {
+                const rate = Math.round((stats.successes / stats.attempts) * 100);
+                const average = stats.average_submit_ms === null ? '' : `, ${stats.average_submit_ms} ms to submit on average`;
+                this.statsSummary.textContent =
+                    `${stats.successes} of ${stats.attempts} attempts sent (${rate}%), ${stats.retries} in-page retries${average}`;
+            }
+        }
+        if (this.statsSelectors) {
+            const lines = [];
+            if (Object.keys(stats.input_selectors).length > 0) {
+                lines.push(`Chat input found by: ${counts(stats.input_selectors)}`);
+            }
+            if (Object.keys(stats.send_selectors).length > 0) {
+                lines.push(`Sent with: ${counts(stats.send_selectors)}`);
+            }
+            this.statsSelectors.textContent = lines.join('. ');
+        }
+        if (this.statsWarning) {
+            this.statsWarning.textContent = stats.warning ?? '';
+        }
+        if (this.statsStatus) {
+            this.statsStatus.textContent = stats.last_attempt_at === null
+                ? ''
+                : `Last attempt ${new Date(stats.last_attempt_at * 1000).toLocaleString()}`;
+        }
     }
     /**
      * Load settings from backend and update UI
@@ -29,12 +416,71 @@ class SettingsApp {
     async loadSettings() {
         try {
             const settings = await invoke('get_settings');
+            this.current = settings;
             if (this.newChatDefault) {
                 this.newChatDefault.checked = settings.new_chat_default ?? true;
             }
             if (this.notificationsEnabled) {
                 this.notificationsEnabled.checked = settings.notifications_enabled ?? true;
             }
+            if (this.openAtLogin) {
+                this.openAtLogin.checked = settings.startup?.open_at_login ?? false;
+            }
+            if (this.startHidden) {
+                this.startHidden.checked = settings.startup?.start_hidden ?? true;
+            }
+            if (this.closeBehavior) {
+                this.closeBehavior.value = settings.close_behavior ?? 'hide_to_tray';
+            }
+            if (this.responseWindow) {
+                this.responseWindow.value = settings.response_window ?? 'flash';
+            }
+            const policy = settings.notification_policy;
+            if (this.notifyWhen) {
+                this.notifyWhen.value = policy?.notify_when ?? 'unfocused';
+            }
+            if (this.quietHours) {
+                this.quietHours.value = (policy?.quiet_hours ?? [])
+                    .map(period => [`${period.start}-${period.end}`, period.days.join(',')].filter(Boolean).join(' '))
+                    .join('\n');
+            }
+            const proxy = settings.proxy;
+            if (this.proxyMode) {
+                this.proxyMode.value = proxy?.mode ?? 'system';
+            }
+            if (this.proxyUrl) {
+                this.proxyUrl.value = proxy?.url ?? '';
+            }
+            if (this.proxyBypass) {
+                this.proxyBypass.value = (proxy?.bypass ?? []).join(', ');
+            }
+            this.updateProxyFields();
+            if (this.kimiOrigin) {
+                this.kimiOrigin.value = settings.kimi_origin ?? '';
+            }
+            const routing = settings.link_routing;
+            if (this.linkDefaultAction) {
+                this.linkDefaultAction.value = routing?.default_action ?? 'browser';
+            }
+            if (this.linkRules) {
+                this.linkRules.value = (routing?.rules ?? [])
+                    .map(rule => `${rule.pattern} ${rule.action}`)
+                    .join('\n');
+            }
+            if (this.stripTrackingParams) {
+                this.stripTrackingParams.checked = routing?.strip_tracking_params ?? false;
+            }
+            if (this.downloadDirectory) {
+                this.downloadDirectory.value = settings.downloads?.directory ?? '';
+            }
+            if (this.destinations) {
+                const destinations = settings.destinations ?? [];
+                this.destinations.value = destinations.length ? JSON.stringify(destinations, null, 2) : '';
+            }
+            for (const input of this.timingInputs) {
+                const value = settings.timing?.[input.dataset.timing];
+                input.value = value === undefined ? '' : String(value);
+            }
         }
         catch (error) {
             console.error('Failed to load settings:', error);
@@ -44,15 +490,65 @@ class SettingsApp {
      * Save current settings to backend
      */
     async saveSettings() {
+        const notificationPolicy = this.readNotificationPolicy();
+        const linkRouting = this.readLinkRouting();
+        const timing = this.readTiming();
+        const destinations = this.readDestinations();
+        if (!notificationPolicy || !linkRouting || !timing || !destinations)
+            return;
         const settings = {
+            ...this.current,
             new_chat_default: this.newChatDefault?.checked ?? true,
             notifications_enabled: this.notificationsEnabled?.checked ?? true,
+            response_window: (this.responseWindow?.value) ?? 'flash',
+            notification_policy: notificationPolicy,
+            proxy: this.readProxySettings(),
+            kimi_origin: this.kimiOrigin?.value.trim() || this.current.kimi_origin || 'https://www.kimi.com',
+            link_routing: linkRouting,
+            downloads: {
+                directory: this.downloadDirectory?.value.trim() || null,
+            },
+            startup: {
+                open_at_login: this.openAtLogin?.checked ?? false,
+                start_hidden: this.startHidden?.checked ?? true,
+            },
+            close_behavior: (this.closeBehavior?.value) ?? 'hide_to_tray',
+            timing,
+            destinations,
         };
         try {
             await invoke('save_settings', { settings });
+            this.current = settings;
+            this.setDownloadStatus('');
+            this.setStartupStatus('');
+            this.setDestinationsStatus('');
+            if (settings.proxy.mode === 'manual') {
+                this.setProxyStatus('Proxy changes apply after restarting Kimi');
+            }
         }
         catch (error) {
             console.error('Failed to save settings:', error);
+            const message = errorMessage(error);
+            const details = (error)?.details ?? {};
+            const field = String(details.field ?? '');
+            if (field.startsWith('timing.')) {
+                this.setTimingStatus(message, true, field.slice('timing.'.length));
+            }
+            else if (field.startsWith('downloads.')) {
+                this.setDownloadStatus(message, true);
+            }
+            else if (field === 'destinations') {
+                this.setDestinationsStatus(message, true);
+            }
+            else if (details.operation === 'update open at login') {
+                this.setStartupStatus(message, true);
+                if (this.openAtLogin) {
+                    this.openAtLogin.checked = this.current.startup?.open_at_login ?? false;
+                }
+            }
+            else {
+                this.setProxyStatus(message, true);
+            }
         }
     }
 }
@@ -1,7 +1,37 @@
 // Kimi Settings TypeScript
-import type { AppSettings } from './types';
+import type {
+  AppSettings,
+  CloseBehavior,
+  CommandError,
+  ConnectionReport,
+  Day,
+  Destination,
+  InjectionStats,
+  LinkAction,
+  LinkRoutingSettings,
+  LinkRule,
+  NotificationPolicy,
+  NotifyWhen,
+  ProxyMode,
+  ProxySettings,
+  QuietHours,
+  ResponseWindowAction,
+  TimingSettings,
+} from './types';
+
+const LINK_ACTIONS: LinkAction[] = ['in_app', 'browser', 'app_window', 'block', 'ask'];
+const DAYS: Day[] = ['mon', 'tue', 'wed', 'thu', 'fri', 'sat', 'sun'];
+const TIME_PATTERN = /^([01]?\d|2[0-3]):[0-5]\d$/;
 
 const { invoke } = window.__TAURI__.core;
+const { listen } = window.__TAURI__.event;
+
+/**
+ * Message of a command error, or the error itself for anything else
+ */
+function errorMessage(error: unknown): string {
+  return (error as Partial<CommandError> | null)?.message ?? String(error);
+}
 
 /**
  * Settings App Class - Manages application settings UI
@@ -9,14 +39,78 @@ const { invoke } = window.__TAURI__.core;
  */
 class SettingsApp {
   private readonly newChatDefault: HTMLInputElement | null;
+  private readonly openAtLogin: HTMLInputElement | null;
+  private readonly startHidden: HTMLInputElement | null;
+  private readonly startupStatus: HTMLElement | null;
+  private readonly closeBehavior: HTMLSelectElement | null;
   private readonly notificationsEnabled: HTMLInputElement | null;
+  private readonly responseWindow: HTMLSelectElement | null;
+  private readonly notifyWhen: HTMLSelectElement | null;
+  private readonly quietHours: HTMLTextAreaElement | null;
+  private readonly quietHoursStatus: HTMLElement | null;
+  private readonly proxyMode: HTMLSelectElement | null;
+  private readonly proxyUrl: HTMLInputElement | null;
+  private readonly proxyBypass: HTMLInputElement | null;
+  private readonly proxyTestBtn: HTMLButtonElement | null;
+  private readonly proxyStatus: HTMLElement | null;
+  private readonly kimiOrigin: HTMLInputElement | null;
+  private readonly linkDefaultAction: HTMLSelectElement | null;
+  private readonly linkRules: HTMLTextAreaElement | null;
+  private readonly stripTrackingParams: HTMLInputElement | null;
+  private readonly linkStatus: HTMLElement | null;
+  private readonly downloadDirectory: HTMLInputElement | null;
+  private readonly downloadChooseBtn: HTMLButtonElement | null;
+  private readonly downloadStatus: HTMLElement | null;
+  private readonly destinations: HTMLTextAreaElement | null;
+  private readonly destinationsStatus: HTMLElement | null;
+  private readonly statsSummary: HTMLElement | null;
+  private readonly statsSelectors: HTMLElement | null;
+  private readonly statsWarning: HTMLElement | null;
+  private readonly statsStatus: HTMLElement | null;
+  private readonly statsResetBtn: HTMLButtonElement | null;
+  // Advanced timing inputs, each naming its TimingSettings field in data-timing
+  private readonly timingInputs: HTMLInputElement[];
+  private readonly timingStatus: HTMLElement | null;
+  // Last settings loaded from the backend, so fields without a control here are preserved
+  private current: Partial<AppSettings> = {};
 
   constructor() {
     this.newChatDefault = document.getElementById('new-chat-default') as HTMLInputElement | null;
+    this.openAtLogin = document.getElementById('open-at-login') as HTMLInputElement | null;
+    this.startHidden = document.getElementById('start-hidden') as HTMLInputElement | null;
+    this.startupStatus = document.getElementById('startup-status');
+    this.closeBehavior = document.getElementById('close-behavior') as HTMLSelectElement | null;
     this.notificationsEnabled = document.getElementById('notifications-enabled') as HTMLInputElement | null;
+    this.responseWindow = document.getElementById('response-window') as HTMLSelectElement | null;
+    this.notifyWhen = document.getElementById('notify-when') as HTMLSelectElement | null;
+    this.quietHours = document.getElementById('quiet-hours') as HTMLTextAreaElement | null;
+    this.quietHoursStatus = document.getElementById('quiet-hours-status');
+    this.proxyMode = document.getElementById('proxy-mode') as HTMLSelectElement | null;
+    this.proxyUrl = document.getElementById('proxy-url') as HTMLInputElement | null;
+    this.proxyBypass = document.getElementById('proxy-bypass') as HTMLInputElement | null;
+    this.proxyTestBtn = document.getElementById('proxy-test-btn') as HTMLButtonElement | null;
+    this.proxyStatus = document.getElementById('proxy-status');
+    this.kimiOrigin = document.getElementById('kimi-origin') as HTMLInputElement | null;
+    this.linkDefaultAction = document.getElementById('link-default-action') as HTMLSelectElement | null;
+    this.linkRules = document.getElementById('link-rules') as HTMLTextAreaElement | null;
+    this.stripTrackingParams = document.getElementById('strip-tracking-params') as HTMLInputElement | null;
+    this.linkStatus = document.getElementById('link-status');
+    this.downloadDirectory = document.getElementById('download-directory') as HTMLInputElement | null;
+    this.downloadChooseBtn = document.getElementById('download-choose-btn') as HTMLButtonElement | null;
+    this.downloadStatus = document.getElementById('download-status');
+    this.destinations = document.getElementById('destinations') as HTMLTextAreaElement | null;
+    this.destinationsStatus = document.getElementById('destinations-status');
+    this.statsSummary = document.getElementById('stats-summary');
+    this.statsSelectors = document.getElementById('stats-selectors');
+    this.statsWarning = document.getElementById('stats-warning');
+    this.statsStatus = document.getElementById('stats-status');
+    this.statsResetBtn = document.getElementById('stats-reset-btn') as HTMLButtonElement | null;
+    this.timingInputs = Array.from(document.querySelectorAll<HTMLInputElement>('input[data-timing]'));
+    this.timingStatus = document.getElementById('timing-status');
 
     this.initEventListeners();
     this.loadSettings();
+    this.loadInjectionStats();
   }
 
   /**
@@ -29,6 +123,323 @@ class SettingsApp {
     if (this.notificationsEnabled) {
       this.notificationsEnabled.addEventListener('change', () => this.saveSettings());
     }
+    this.openAtLogin?.addEventListener('change', () => this.saveSettings());
+    this.startHidden?.addEventListener('change', () => this.saveSettings());
+    this.closeBehavior?.addEventListener('change', () => this.saveSettings());
+    this.responseWindow?.addEventListener('change', () => this.saveSettings());
+    this.notifyWhen?.addEventListener('change', () => this.saveSettings());
+    this.quietHours?.addEventListener('change', () => this.saveSettings());
+    if (this.proxyMode) {
+      this.proxyMode.addEventListener('change', () => {
+        this.updateProxyFields();
+        this.saveSettings();
+      });
+    }
+    this.proxyUrl?.addEventListener('change', () => this.saveSettings());
+    this.proxyBypass?.addEventListener('change', () => this.saveSettings());
+    this.proxyTestBtn?.addEventListener('click', () => this.testProxyConnection());
+    this.kimiOrigin?.addEventListener('change', () => this.saveSettings());
+    this.linkDefaultAction?.addEventListener('change', () => this.saveSettings());
+    this.linkRules?.addEventListener('change', () => this.saveSettings());
+    this.stripTrackingParams?.addEventListener('change', () => this.saveSettings());
+    this.downloadDirectory?.addEventListener('change', () => this.saveSettings());
+    this.downloadChooseBtn?.addEventListener('click', () => this.chooseDownloadDirectory());
+    this.destinations?.addEventListener('change', () => this.saveSettings());
+    for (const input of this.timingInputs) {
+      input.addEventListener('change', () => this.saveSettings());
+    }
+    this.statsResetBtn?.addEventListener('click', () => {
+      invoke('reset_injection_stats').catch(error => console.error('Failed to reset stats:', error));
+    });
+    listen<InjectionStats>('injection-stats-changed', (event) => {
+      this.renderInjectionStats(event.payload);
+    }).catch(error => {
+      console.error('Failed to listen for injection-stats-changed event:', error);
+    });
+  }
+
+  /**
+   * Parse the quiet hours textarea, one "HH:MM-HH:MM [days]" period per line
+   * @returns The notification policy, or null if a line is malformed
+   */
+  private readNotificationPolicy(): NotificationPolicy | null {
+    const periods: QuietHours[] = [];
+    const lines = (this.quietHours?.value ?? '').split('\n');
+    for (const [index, line] of lines.entries()) {
+      const trimmed = line.trim();
+      if (!trimmed || trimmed.startsWith('#')) continue;
+      const [range, dayList, ...rest] = trimmed.split(/\s+/);
+      const [start, end, ...extra] = range.split('-');
+      const days = dayList ? dayList.toLowerCase().split(',') : [];
+      const valid = rest.length === 0
+        && extra.length === 0
+        && TIME_PATTERN.test(start ?? '')
+        && TIME_PATTERN.test(end ?? '')
+        && days.every(day => DAYS.includes(day as Day));
+      if (!valid) {
+        this.setQuietHoursStatus(`Line ${index + 1}: expected "HH:MM-HH:MM" optionally followed by days like mon,tue`, true);
+        return null;
+      }
+      periods.push({ start, end, days: days as Day[] });
+    }
+    this.setQuietHoursStatus('');
+    return {
+      snoozed_until: this.current.notification_policy?.snoozed_until ?? null,
+      notify_when: (this.notifyWhen?.value as NotifyWhen | undefined) ?? 'unfocused',
+      quiet_hours: periods,
+    };
+  }
+
+  /**
+   * Show a status line below the quiet hours
+   */
+  private setQuietHoursStatus(text: string, isError = false): void {
+    if (!this.quietHoursStatus) return;
+    this.quietHoursStatus.textContent = text;
+    this.quietHoursStatus.classList.toggle('error', isError);
+  }
+
+  /**
+   * Parse the link rules textarea, one "pattern action" pair per line
+   * @returns The routing settings, or null if a line is malformed
+   */
+  private readLinkRouting(): LinkRoutingSettings | null {
+    const rules: LinkRule[] = [];
+    const lines = (this.linkRules?.value ?? '').split('\n');
+    for (const [index, line] of lines.entries()) {
+      const trimmed = line.trim();
+      if (!trimmed || trimmed.startsWith('#')) continue;
+      const [pattern, action, ...rest] = trimmed.split(/\s+/);
+      if (!action || rest.length > 0 || !LINK_ACTIONS.includes(action as LinkAction)) {
+        this.setLinkStatus(`Line ${index + 1}: expected "pattern action" with action one of ${LINK_ACTIONS.join(', ')}`, true);
+        return null;
+      }
+      rules.push({ pattern, action: action as LinkAction });
+    }
+    this.setLinkStatus('');
+    return {
+      rules,
+      default_action: (this.linkDefaultAction?.value as LinkAction | undefined) ?? 'browser',
+      strip_tracking_params: this.stripTrackingParams?.checked ?? false,
+    };
+  }
+
+  /**
+   * Show a status line below the link rules
+   */
+  private setLinkStatus(text: string, isError = false): void {
+    if (!this.linkStatus) return;
+    this.linkStatus.textContent = text;
+    this.linkStatus.classList.toggle('error', isError);
+  }
+
+  /**
+   * Parse the destinations textarea, a JSON list; empty means none
+   * @returns The destinations, or null if the text is not a JSON list
+   */
+  private readDestinations(): Destination[] | null {
+    const text = this.destinations?.value.trim() ?? '';
+    if (!text) {
+      this.setDestinationsStatus('');
+      return [];
+    }
+    let parsed: unknown;
+    try {
+      parsed = JSON.parse(text);
+    } catch (error) {
+      this.setDestinationsStatus(`Not valid JSON: ${(error as Error).message}`, true);
+      return null;
+    }
+    if (!Array.isArray(parsed)) {
+      this.setDestinationsStatus('Expected a JSON list of destinations', true);
+      return null;
+    }
+    this.setDestinationsStatus('');
+    return parsed as Destination[];
+  }
+
+  /**
+   * Show a status line below the destinations
+   */
+  private setDestinationsStatus(text: string, isError = false): void {
+    if (!this.destinationsStatus) return;
+    this.destinationsStatus.textContent = text;
+    this.destinationsStatus.classList.toggle('error', isError);
+  }
+
+  /**
+   * Read the proxy form into a ProxySettings object
+   */
+  private readProxySettings(): ProxySettings {
+    return {
+      mode: (this.proxyMode?.value as ProxyMode | undefined) ?? 'system',
+      url: this.proxyUrl?.value.trim() ?? '',
+      bypass: (this.proxyBypass?.value ?? '')
+        .split(',')
+        .map(entry => entry.trim())
+        .filter(entry => entry.length > 0),
+    };
+  }
+
+  /**
+   * Only enable the manual proxy fields in manual mode
+   */
+  private updateProxyFields(): void {
+    const manual = this.proxyMode?.value === 'manual';
+    if (this.proxyUrl) {
+      this.proxyUrl.disabled = !manual;
+    }
+    if (this.proxyBypass) {
+      this.proxyBypass.disabled = !manual;
+    }
+  }
+
+  /**
+   * Show a status line below the proxy settings
+   */
+  private setProxyStatus(text: string, isError = false): void {
+    if (!this.proxyStatus) return;
+    this.proxyStatus.textContent = text;
+    this.proxyStatus.classList.toggle('error', isError);
+  }
+
+  /**
+   * Check that Kimi is reachable with the proxy settings currently in the form
+   */
+  private async testProxyConnection(): Promise<void> {
+    if (this.proxyTestBtn) {
+      this.proxyTestBtn.disabled = true;
+    }
+    this.setProxyStatus('Testing connection...');
+
+    try {
+      const report = await invoke<ConnectionReport>('test_proxy_connection', {
+        proxy: this.readProxySettings(),
+      });
+      const route = report.proxy ? `via ${report.proxy}` : 'directly';
+      this.setProxyStatus(`Reached Kimi ${route} in ${report.elapsed_ms} ms`);
+    } catch (error) {
+      this.setProxyStatus(errorMessage(error), true);
+    } finally {
+      if (this.proxyTestBtn) {
+        this.proxyTestBtn.disabled = false;
+      }
+    }
+  }
+
+  /**
+   * Let the user pick the download folder with the native folder dialog
+   */
+  private async chooseDownloadDirectory(): Promise<void> {
+    try {
+      const directory = await invoke<string | null>('choose_download_directory');
+      if (directory && this.downloadDirectory) {
+        this.downloadDirectory.value = directory;
+        await this.saveSettings();
+      }
+    } catch (error) {
+      this.setDownloadStatus(errorMessage(error), true);
+    }
+  }
+
+  /**
+   * Show a status line below the open at login option
+   */
+  private setStartupStatus(text: string, isError = false): void {
+    if (!this.startupStatus) return;
+    this.startupStatus.textContent = text;
+    this.startupStatus.classList.toggle('error', isError);
+  }
+
+  /**
+   * Show a status line below the download folder
+   */
+  private setDownloadStatus(text: string, isError = false): void {
+    if (!this.downloadStatus) return;
+    this.downloadStatus.textContent = text;
+    this.downloadStatus.classList.toggle('error', isError);
+  }
+
+  /**
+   * Read the advanced timing inputs over the loaded timings
+   * @returns The timings, or null if an input is not a whole number
+   */
+  private readTiming(): TimingSettings | null {
+    const timing = { ...this.current.timing } as TimingSettings;
+    for (const input of this.timingInputs) {
+      const field = input.dataset.timing as keyof TimingSettings;
+      const value = Number(input.value);
+      if (input.value.trim() === '' || !Number.isInteger(value) || value < 0) {
+        this.setTimingStatus(`${input.labels?.[0]?.textContent ?? field} must be a whole number`, true, field);
+        return null;
+      }
+      timing[field] = value;
+    }
+    this.setTimingStatus('');
+    return timing;
+  }
+
+  /**
+   * Show a status line below the advanced timings, marking the input it is about
+   */
+  private setTimingStatus(text: string, isError = false, field?: string): void {
+    for (const input of this.timingInputs) {
+      input.classList.toggle('invalid', input.dataset.timing === field);
+    }
+    if (!this.timingStatus) return;
+    this.timingStatus.textContent = text;
+    this.timingStatus.classList.toggle('error', isError);
+  }
+
+  /**
+   * Load the injection stats from the backend
+   */
+  private async loadInjectionStats(): Promise<void> {
+    try {
+      this.renderInjectionStats(await invoke<InjectionStats>('get_injection_stats'));
+    } catch (error) {
+      console.error('Failed to load injection stats:', error);
+    }
+  }
+
+  /**
+   * Show the injection stats in the diagnostics section
+   */
+  private renderInjectionStats(stats: InjectionStats): void {
+    const counts = (selectors: Record<string, number>): string =>
+      Object.entries(selectors)
+        .sort(([, a], [, b]) => b - a)
+        .map(([name, count]) => `${name} ${count}`)
+        .join(', ');
+
+    if (this.statsSummary) {
+      if (stats.attempts === 0) {
+        this.statsSummary.textContent = 'No messages sent yet';
+      } else {
+        const rate = Math.round((stats.successes / stats.attempts) * 100);
+        const average = stats.average_submit_ms === null ? '' : `, ${stats.average_submit_ms} ms to submit on average`;
+        this.statsSummary.textContent =
+          `${stats.successes} of ${stats.attempts} attempts sent (${rate}%), ${stats.retries} in-page retries${average}`;
+      }
+    }
+    if (this.statsSelectors) {
+      const lines = [];
+      if (Object.keys(stats.input_selectors).length > 0) {
+        lines.push(`Chat input found by: ${counts(stats.input_selectors)}`);
+      }
+      if (Object.keys(stats.send_selectors).length > 0) {
+        lines.push(`Sent with: ${counts(stats.send_selectors)}`);
+      }
+      this.statsSelectors.textContent = lines.join('. ');
+    }
+    if (this.statsWarning) {
+      this.statsWarning.textContent = stats.warning ?? '';
+    }
+    if (this.statsStatus) {
+      this.statsStatus.textContent = stats.last_attempt_at === null
+        ? ''
+        : `Last attempt ${new Date(stats.last_attempt_at * 1000).toLocaleString()}`;
+    }
   }
 
   /**
@@ -37,12 +448,71 @@ class SettingsApp {
   private async loadSettings(): Promise<void> {
     try {
       const settings = await invoke<AppSettings>('get_settings');
+      this.current = settings;
       if (this.newChatDefault) {
         this.newChatDefault.checked = settings.new_chat_default ?? true;
       }
       if (this.notificationsEnabled) {
         this.notificationsEnabled.checked = settings.notifications_enabled ?? true;
       }
+      if (this.openAtLogin) {
+        this.openAtLogin.checked = settings.startup?.open_at_login ?? false;
+      }
+      if (this.startHidden) {
+        this.startHidden.checked = settings.startup?.start_hidden ?? true;
+      }
+      if (this.closeBehavior) {
+        this.closeBehavior.value = settings.close_behavior ?? 'hide_to_tray';
+      }
+      if (this.responseWindow) {
+        this.responseWindow.value = settings.response_window ?? 'flash';
+      }
+      const policy = settings.notification_policy;
+      if (this.notifyWhen) {
+        this.notifyWhen.value = policy?.notify_when ?? 'unfocused';
+      }
+      if (this.quietHours) {
+        this.quietHours.value = (policy?.quiet_hours ?? [])
+          .map(period => [`${period.start}-${period.end}`, period.days.join(',')].filter(Boolean).join(' '))
+          .join('\n');
+      }
+      const proxy = settings.proxy;
+      if (this.proxyMode) {
+        this.proxyMode.value = proxy?.mode ?? 'system';
+      }
+      if (this.proxyUrl) {
+        this.proxyUrl.value = proxy?.url ?? '';
+      }
+      if (this.proxyBypass) {
+        this.proxyBypass.value = (proxy?.bypass ?? []).join(', ');
+      }
+      this.updateProxyFields();
+      if (this.kimiOrigin) {
+        this.kimiOrigin.value = settings.kimi_origin ?? '';
+      }
+      const routing = settings.link_routing;
+      if (this.linkDefaultAction) {
+        this.linkDefaultAction.value = routing?.default_action ?? 'browser';
+      }
+      if (this.linkRules) {
+        this.linkRules.value = (routing?.rules ?? [])
+          .map(rule => `${rule.pattern} ${rule.action}`)
+          .join('\n');
+      }
+      if (this.stripTrackingParams) {
+        this.stripTrackingParams.checked = routing?.strip_tracking_params ?? false;
+      }
+      if (this.downloadDirectory) {
+        this.downloadDirectory.value = settings.downloads?.directory ?? '';
+      }
+      if (this.destinations) {
+        const destinations = settings.destinations ?? [];
+        this.destinations.value = destinations.length ? JSON.stringify(destinations, null, 2) : '';
+      }
+      for (const input of this.timingInputs) {
+        const value = settings.timing?.[input.dataset.timing as keyof TimingSettings];
+        input.value = value === undefined ? '' : String(value);
+      }
     } catch (error) {
       console.error('Failed to load settings:', error);
     }
@@ -52,15 +522,61 @@ class SettingsApp {
    * Save current settings to backend
    */
   private async saveSettings(): Promise<void> {
-    const settings: AppSettings = {
+    const notificationPolicy = this.readNotificationPolicy();
+    const linkRouting = this.readLinkRouting();
+    const timing = this.readTiming();
+    const destinations = this.readDestinations();
+    if (!notificationPolicy || !linkRouting || !timing || !destinations) return;
+
+    const settings = {
+      ...this.current,
       new_chat_default: this.newChatDefault?.checked ?? true,
       notifications_enabled: this.notificationsEnabled?.checked ?? true,
+      response_window: (this.responseWindow?.value as ResponseWindowAction | undefined) ?? 'flash',
+      notification_policy: notificationPolicy,
+      proxy: this.readProxySettings(),
+      kimi_origin: this.kimiOrigin?.value.trim() || this.current.kimi_origin || 'https://www.kimi.com',
+      link_routing: linkRouting,
+      downloads: {
+        directory: this.downloadDirectory?.value.trim() || null,
+      },
+      startup: {
+        open_at_login: this.openAtLogin?.checked ?? false,
+        start_hidden: this.startHidden?.checked ?? true,
+      },
+      close_behavior: (this.closeBehavior?.value as CloseBehavior | undefined) ?? 'hide_to_tray',
+      timing,
+      destinations,
     };
 
     try {
       await invoke('save_settings', { settings });
+      this.current = settings;
+      this.setDownloadStatus('');
+      this.setStartupStatus('');
+      this.setDestinationsStatus('');
+      if (settings.proxy.mode === 'manual') {
+        this.setProxyStatus('Proxy changes apply after restarting Kimi');
+      }
     } catch (error) {
       console.error('Failed to save settings:', error);
+      const message = errorMessage(error);
+      const details = (error as Partial<CommandError> | null)?.details ?? {};
+      const field = String(details.field ?? '');
+      if (field.startsWith('timing.')) {
+        this.setTimingStatus(message, true, field.slice('timing.'.length));
+      } else if (field.startsWith('downloads.')) {
+        this.setDownloadStatus(message, true);
+      } else if (field === 'destinations') {
+        this.setDestinationsStatus(message, true);
+      } else if (details.operation === 'update open at login') {
+        this.setStartupStatus(message, true);
+        if (this.openAtLogin) {
+          this.openAtLogin.checked = this.current.startup?.open_at_login ?? false;
+        }
+      } else {
+        this.setProxyStatus(message, true);
+      }
     }
   }
 }
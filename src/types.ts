@@ -20,17 +20,200 @@ declare global {
 export interface AppSettings {
   new_chat_default: boolean;
   notifications_enabled: boolean;
+  response_window: ResponseWindowAction;
+  notification_policy: NotificationPolicy;
+  zoom_levels: Record<string, number>;
+  proxy: ProxySettings;
+  kimi_origin: string;
+  link_routing: LinkRoutingSettings;
+  downloads: DownloadSettings;
+  startup: StartupSettings;
+  close_behavior: CloseBehavior;
+  close_hint_shown: boolean;
+  timing: TimingSettings;
+  chat_mode: ChatMode;
+  // Added to the built-in `chat` and `claw` destinations
+  destinations: Destination[];
+}
+
+export type CloseBehavior = 'hide_to_tray' | 'minimize' | 'quit';
+
+export type ResponseWindowAction = 'show' | 'flash' | 'none';
+
+export type NotifyWhen = 'unfocused' | 'hidden' | 'always';
+
+export type Day = 'mon' | 'tue' | 'wed' | 'thu' | 'fri' | 'sat' | 'sun';
+
+export interface QuietHours {
+  start: string;
+  end: string;
+  days: Day[];
+}
+
+export interface NotificationPolicy {
+  notify_when: NotifyWhen;
+  quiet_hours: QuietHours[];
+  // Unix time in seconds; set from the tray menu
+  snoozed_until: number | null;
+}
+
+export type ProxyMode = 'none' | 'system' | 'manual';
+
+export interface ProxySettings {
+  mode: ProxyMode;
+  url: string;
+  bypass: string[];
+}
+
+export type LinkAction = 'in_app' | 'browser' | 'app_window' | 'block' | 'ask';
+
+export interface LinkRule {
+  pattern: string;
+  action: LinkAction;
+}
+
+export interface LinkRoutingSettings {
+  rules: LinkRule[];
+  default_action: LinkAction;
+  strip_tracking_params: boolean;
+}
+
+export interface DownloadSettings {
+  directory: string | null;
+}
+
+// Waits in milliseconds, except for injection_max_retries and response_watcher_max_checks
+export interface TimingSettings {
+  injection_total: number;
+  injection_max_retries: number;
+  retry_delay: number;
+  react_init_delay: number;
+  focus_delay: number;
+  submit_delay: number;
+  fallback_load_timeout: number;
+  page_load_wait: number;
+  bot_page_load_wait: number;
+  window_visible_delay: number;
+  response_watcher_interval: number;
+  response_watcher_initial_delay: number;
+  response_watcher_max_checks: number;
+}
+
+export type Reasoning = 'unchanged' | 'standard' | 'thinking' | 'long_thinking';
+
+export type WebSearch = 'unchanged' | 'on' | 'off';
+
+// Picked in the launcher; `unchanged` leaves Kimi's toggle as it is
+export interface ChatMode {
+  reasoning: Reasoning;
+  web_search: WebSearch;
+}
+
+// Wait after opening a destination's page: one of the page load timings,
+// or a fixed number of milliseconds
+export type Readiness = 'page_load' | 'agent_page_load' | { delay: number };
+
+// Selectors tried before Kimi's own input and send button selectors
+export interface SelectorProfile {
+  input: string[];
+  send: string[];
+}
+
+// Result of `get_destinations`; `shortcut` is used with Cmd/Ctrl in the launcher
+export interface Destination {
+  id: string;
+  name: string;
+  path: string;
+  readiness: Readiness;
+  selectors: SelectorProfile;
+  shortcut: string | null;
+  prefix: string | null;
+}
+
+export interface StartupSettings {
+  open_at_login: boolean;
+  start_hidden: boolean;
+}
+
+export type DownloadState = 'in_progress' | 'completed' | 'failed';
+
+// Payload of the `download-progress` event
+export interface DownloadEntry {
+  id: number;
+  url: string;
+  file_name: string;
+  path: string;
+  state: DownloadState;
+  received_bytes: number;
+}
+
+// Rejection value of every Tauri command that can fail, mirroring
+// `CommandError` in src-tauri/src/wrappers/error.rs
+export interface CommandError {
+  code: ErrorCode;
+  category: ErrorCategory;
+  message: string;
+  // e.g. `field` for invalid_settings, `window_label` for window errors
+  details: Record<string, string | number> | null;
+}
+
+export type ErrorCode =
+  | 'injection_failed'
+  | 'window_not_found'
+  | 'window_operation_failed'
+  | 'eval_error'
+  | 'serialization_error'
+  | 'timeout'
+  | 'invalid_url'
+  | 'store_error'
+  | 'invalid_settings'
+  | 'connection_failed'
+  | 'template_error'
+  | 'delivery_failed'
+  | 'system_error';
+
+export type ErrorCategory =
+  | 'injection'
+  | 'window'
+  | 'validation'
+  | 'network'
+  | 'storage'
+  | 'system'
+  | 'internal';
+
+export interface ConnectionReport {
+  proxy: string | null;
+  elapsed_ms: number;
 }
 
 export interface SubmitMessageArgs {
   message: string;
   newChat: boolean;
-  botMode: boolean;
+  destination: string;
+  chatMode: ChatMode;
 }
 
 export interface InjectResult {
   success: boolean;
   error?: string | null;
+  input_selector?: string | null;
+  send_selector?: string | null;
+  retries?: number;
+  elapsed_ms?: number;
+}
+
+// Result of `get_injection_stats` and payload of `injection-stats-changed`
+export interface InjectionStats {
+  attempts: number;
+  successes: number;
+  retries: number;
+  submit_ms: number;
+  input_selectors: Record<string, number>;
+  send_selectors: Record<string, number>;
+  primary_input_misses: number;
+  last_attempt_at: number | null;
+  average_submit_ms: number | null;
+  warning: string | null;
 }
 
 // Launcher App Types
@@ -38,29 +221,76 @@ export interface LauncherElements {
   input: HTMLInputElement | null;
   submitBtn: HTMLButtonElement | null;
   newChatToggle: HTMLElement | null;
-  botModeToggle: HTMLElement | null;
+  destinationSelect: HTMLSelectElement | null;
 }
 
 export interface LauncherState {
   focusTimeout: number | null;
   isSubmitting: boolean;
   newChatMode: boolean;
-  botMode: boolean;
+  destination: string;
 }
 
 // Settings App Types  
 export interface SettingsElements {
   newChatDefault: HTMLInputElement | null;
   notificationsEnabled: HTMLInputElement | null;
+  responseWindow: HTMLSelectElement | null;
+  notifyWhen: HTMLSelectElement | null;
+  quietHours: HTMLTextAreaElement | null;
+  proxyMode: HTMLSelectElement | null;
+  proxyUrl: HTMLInputElement | null;
+  proxyBypass: HTMLInputElement | null;
+  kimiOrigin: HTMLInputElement | null;
+  linkDefaultAction: HTMLSelectElement | null;
+  linkRules: HTMLTextAreaElement | null;
+  stripTrackingParams: HTMLInputElement | null;
+  downloadDirectory: HTMLInputElement | null;
+  openAtLogin: HTMLInputElement | null;
+  startHidden: HTMLInputElement | null;
+  closeBehavior: HTMLSelectElement | null;
 }
 
 // Event Payload Types
 export interface SettingsChangedEvent {
   new_chat_default: boolean;
   notifications_enabled: boolean;
+  chat_mode: ChatMode;
 }
 
-export type LauncherShownEvent = void;
-export type ResponseCompleteEvent = void;
+// Text filled in from a kimi://ask link, or null when the launcher opens empty
+export type LauncherShownEvent = { message: string; new_chat: boolean | null } | null;
+
+export interface ResponseCompleteEvent {
+  title: string;
+  url: string;
+}
+
+// Payload of the `outbox-changed` event and result of `get_outbox`
+export interface OutboxEntry {
+  id: number;
+  message: string;
+  new_chat: boolean;
+  destination: string;
+  queued_at: number;
+}
+
+export type Connectivity = 'online' | 'degraded' | 'offline';
+
+// Payload of the `connectivity-changed` event
+export interface ConnectivityChangedEvent {
+  state: Connectivity;
+  previous: Connectivity | null;
+  detail: string | null;
+}
+
+export type Activity = 'offline' | 'generating' | 'unread' | 'idle';
+
+export interface AppStateChangedEvent {
+  online: boolean;
+  generating: boolean;
+  unread: boolean;
+  conversation: { title: string | null; url: string } | null;
+}
 
 export {};